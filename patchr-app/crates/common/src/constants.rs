@@ -1,4 +1,4 @@
-pub const PROJECT_VERSION: &str = "0.15";
+pub const PROJECT_VERSION: &str = "0.16";
 pub const PROJECT_NAME: &str = "patchr";
 
 pub const GIT_COMMAND: &str = "git";
\ No newline at end of file
@@ -37,4 +37,6 @@ fn test_commit_hash_validation() {
     assert!(!commit_hash_valid(" 000000000000000000000000000000000000000"));
     assert!(!commit_hash_valid("00000000000000000000000000000000000000"));
     assert!(!commit_hash_valid("000000000000000000000000000000000000000"));
+    assert!(!commit_hash_valid("a783a72463f175ca56b5d31be15f3ca5276368041"));
+    assert!(!commit_hash_valid("2a0bb70e36863ecdc9a924d07e9844a4086dd8693c86162a75f945186cd1850d"));
 }
\ No newline at end of file
@@ -1,6 +1,6 @@
 use std::ops::ControlFlow;
 
-pub fn to_unit<T>(_x: T) -> () {}
+pub fn to_unit<T>(_x: T) {}
 
 pub fn result_to_control_flow<U, E, O>(
     r: Result<U, E>, err_f: impl Fn(E) -> O,
@@ -0,0 +1,74 @@
+use std::{
+    fmt::Display,
+    fs,
+    io::{self, Write},
+    path::Path,
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    mail::{mail_files, MailHeaders},
+    GitError, GitErrorCode,
+};
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum ExportFormat {
+    Mbox,
+    Directory,
+}
+
+impl Display for ExportFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ExportFormat::Mbox => f.write_str("mbox"),
+            ExportFormat::Directory => f.write_str("directory"),
+        }
+    }
+}
+
+// Write the mails generated by format_series to output, the files
+// of format-patch are already mbox messages so they are concatenated
+// Returns the subjects of the exported mails
+pub fn export_mails(
+    dir: &Path, output: &Path, format: ExportFormat,
+) -> Result<Vec<String>, GitError> {
+    if output.exists() {
+        return Err(GitError::new(
+            GitErrorCode::InvalidPath,
+            format!("{} already exists", output.display()),
+        ));
+    }
+    let to_git_err = |e: io::Error| GitError::new(GitErrorCode::ExportFailed, e.to_string());
+
+    let mut subjects = Vec::new();
+    let files = mail_files(dir).map_err(to_git_err)?;
+    match format {
+        ExportFormat::Mbox => {
+            let mut out = fs::File::create_new(output).map_err(to_git_err)?;
+            for f in files {
+                let content = fs::read_to_string(&f).map_err(to_git_err)?;
+                out.write_all(content.as_bytes()).map_err(to_git_err)?;
+                if !content.ends_with('\n') {
+                    out.write_all(b"\n").map_err(to_git_err)?;
+                }
+                subjects.push(mail_subject(&content));
+            }
+        }
+        ExportFormat::Directory => {
+            fs::create_dir_all(output).map_err(to_git_err)?;
+            for f in files {
+                let content = fs::read_to_string(&f).map_err(to_git_err)?;
+                // mail_files only returns files
+                fs::write(output.join(f.file_name().unwrap()), &content).map_err(to_git_err)?;
+                subjects.push(mail_subject(&content));
+            }
+        }
+    }
+    Ok(subjects)
+}
+
+fn mail_subject(content: &str) -> String {
+    String::from(MailHeaders::parse(content).get("Subject").unwrap_or(""))
+}
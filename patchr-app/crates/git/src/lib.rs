@@ -3,9 +3,16 @@ use std::fmt::{Debug, Display};
 #[cfg(test)]
 mod test;
 
+pub mod export;
+pub mod mail;
+pub mod maildir_sender;
 pub mod patch_sender;
+pub mod range_diff;
 pub mod repo;
+pub mod sendmail_sender;
 pub mod series;
+pub mod smtp_sender;
+pub mod trailers;
 pub mod util;
 
 #[derive(Clone)]
@@ -25,6 +32,13 @@ pub enum GitErrorCode {
     SeriesAlreadyExists,
     UnknownSeries,
     RepoOpFailed,
+    ExportFailed,
+    UnsafeRewrite,
+    NothingToUndo,
+    InvalidCommitId,
+    CommitNotFound,
+    RewriteFailed,
+    ConcurrentUpdate,
 }
 
 impl GitError {
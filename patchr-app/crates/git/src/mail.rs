@@ -0,0 +1,155 @@
+use std::{fs, io, path::Path, path::PathBuf};
+
+// Minimal RFC 5322 header parsing, enough to read the
+// messages generated by git format-patch
+pub struct MailHeaders {
+    headers: Vec<(String, String)>,
+}
+
+impl MailHeaders {
+    pub fn parse(content: &str) -> Self {
+        let mut headers: Vec<(String, String)> = Vec::new();
+        let mut lines = content.lines().peekable();
+        // mbox separator, e.g. "From <sha1> Mon Sep 17 00:00:00 2001"
+        if lines.peek().is_some_and(|l| l.starts_with("From ")) {
+            lines.next();
+        }
+        for line in lines {
+            if line.trim().is_empty() {
+                break; // end of the headers
+            }
+            if line.starts_with([' ', '\t']) {
+                // folded header
+                if let Some((_, value)) = headers.last_mut() {
+                    value.push(' ');
+                    value.push_str(line.trim());
+                }
+                continue;
+            }
+            if let Some((name, value)) = line.split_once(':') {
+                headers.push((String::from(name.trim()), String::from(value.trim())));
+            }
+        }
+        Self { headers }
+    }
+
+    // Header names are case insensitive
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(n, _)| n.eq_ignore_ascii_case(name))
+            .map(|(_, v)| v.as_str())
+    }
+
+    pub fn message_id(&self) -> Option<&str> {
+        self.get("Message-Id")
+    }
+}
+
+// Mails of a directory generated by format-patch, sorted so
+// that the cover letter comes first
+pub fn mail_files(dir: &Path) -> Result<Vec<PathBuf>, io::Error> {
+    let mut files: Vec<_> = fs::read_dir(dir)?
+        .map(|e| e.map(|e| e.path()))
+        .collect::<Result<_, _>>()?;
+    files.retain(|f| f.extension().is_some_and(|e| e == "patch"));
+    files.sort();
+    Ok(files)
+}
+
+// A mail generated by format-patch prepared for a direct delivery, like
+// git send-email does: the sender replaces the From header, the author is
+// kept at the top of the body when it differs, and the Date is the time of
+// sending
+pub struct OutgoingMail {
+    recipients: Vec<String>,
+    data: String,
+}
+
+impl OutgoingMail {
+    pub fn new(content: &str, from_email: &str, date: &str) -> Self {
+        let headers = MailHeaders::parse(content);
+        let mut lines = content.lines().peekable();
+        if lines.peek().is_some_and(|l| l.starts_with("From ")) {
+            lines.next();
+        }
+
+        let mut data = format!("From: {}\nDate: {}\n", from_email, date);
+        let mut skip = false;
+        for line in lines.by_ref() {
+            if line.trim().is_empty() {
+                break; // end of the headers
+            }
+            if !line.starts_with([' ', '\t']) {
+                skip = line.split_once(':').is_some_and(|(name, _)| {
+                    let name = name.trim();
+                    name.eq_ignore_ascii_case("From") || name.eq_ignore_ascii_case("Date")
+                });
+            }
+            if !skip {
+                data.push_str(line);
+                data.push('\n');
+            }
+        }
+        data.push('\n');
+        if let Some(author) = headers.get("From") {
+            if !mail_address(author).eq_ignore_ascii_case(from_email) {
+                data.push_str(&format!("From: {}\n\n", author));
+            }
+        }
+        for line in lines {
+            data.push_str(line);
+            data.push('\n');
+        }
+
+        let mut recipients: Vec<String> = Vec::new();
+        for value in ["To", "Cc"].iter().filter_map(|n| headers.get(n)) {
+            for address in split_addresses(value).into_iter().map(mail_address) {
+                if !address.is_empty() && !recipients.iter().any(|r| r == address) {
+                    recipients.push(String::from(address));
+                }
+            }
+        }
+        Self { recipients, data }
+    }
+
+    pub fn recipients(&self) -> &[String] {
+        self.recipients.as_slice()
+    }
+
+    // Message with LF line endings, as expected by sendmail or a Maildir
+    pub fn data(&self) -> &str {
+        self.data.as_str()
+    }
+
+    // Message with CRLF line endings, as expected on the wire
+    pub fn data_crlf(&self) -> String {
+        self.data.replace('\n', "\r\n")
+    }
+}
+
+// Split an address list on the commas outside of quoted display names
+fn split_addresses(value: &str) -> Vec<&str> {
+    let mut res = Vec::new();
+    let (mut start, mut quoted) = (0, false);
+    for (i, c) in value.char_indices() {
+        match c {
+            '"' => quoted = !quoted,
+            ',' if !quoted => {
+                res.push(&value[start..i]);
+                start = i + 1;
+            }
+            _ => (),
+        }
+    }
+    res.push(&value[start..]);
+    res
+}
+
+// "Name <address>" or "address"
+fn mail_address(value: &str) -> &str {
+    match (value.rfind('<'), value.rfind('>')) {
+        (Some(s), Some(e)) if s < e => value[s + 1..e].trim(),
+        _ => value.trim(),
+    }
+}
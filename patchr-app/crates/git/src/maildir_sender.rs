@@ -0,0 +1,60 @@
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use uuid::Uuid;
+
+use crate::{
+    mail::OutgoingMail,
+    patch_sender::{format_for_sending, PatchSender, SendParams},
+    GitError, GitErrorCode,
+};
+
+// Drops every mail in the new directory of a Maildir instead of sending it
+pub struct MaildirPatchSender<'a> {
+    from_email: &'a str,
+    maildir: &'a Path,
+}
+
+impl<'a> MaildirPatchSender<'a> {
+    pub fn new(from_email: &'a str, maildir: &'a Path) -> Self {
+        Self {
+            from_email,
+            maildir,
+        }
+    }
+
+    // The mail is written in tmp then moved to new, readers never see partial mails
+    fn deliver(&self, mail: &OutgoingMail) -> Result<PathBuf, io::Error> {
+        for d in ["tmp", "new", "cur"] {
+            fs::create_dir_all(self.maildir.join(d))?;
+        }
+        let name = format!(
+            "{}.{}.patchr",
+            chrono::offset::Local::now().timestamp(),
+            Uuid::new_v4().simple()
+        );
+        let tmp_path = self.maildir.join("tmp").join(&name);
+        let new_path = self.maildir.join("new").join(&name);
+        fs::write(&tmp_path, mail.data())?;
+        fs::rename(&tmp_path, &new_path)?;
+        Ok(new_path)
+    }
+}
+
+impl PatchSender for MaildirPatchSender<'_> {
+    fn format(&self, params: &SendParams) -> Result<PathBuf, GitError> {
+        format_for_sending(self.from_email, params)
+    }
+
+    fn send_mail(&self, _params: &SendParams, mail: &Path) -> Result<(), GitError> {
+        let to_git_err =
+            |e: io::Error| GitError::new(GitErrorCode::SendSeriesFailed, e.to_string());
+        let content = fs::read_to_string(mail).map_err(to_git_err)?;
+        let date = chrono::offset::Local::now().to_rfc2822();
+        self.deliver(&OutgoingMail::new(&content, self.from_email, &date))
+            .map(|_| ())
+            .map_err(to_git_err)
+    }
+}
@@ -1,13 +1,20 @@
-use std::{fs, io, mem::MaybeUninit, path::Path, process, ptr};
-
-use common::{
-    constants::GIT_COMMAND,
-    util::{git::commit_hash_valid, rust::to_unit},
+use std::{
+    fs,
+    io::{self, Write},
+    mem::MaybeUninit,
+    path::{Path, PathBuf},
+    process, ptr,
 };
+
+use common::{constants::GIT_COMMAND, util::git::commit_hash_valid};
 use email_address::EmailAddress;
 use uuid::Uuid;
 
-use crate::{series::Series, GitError, GitErrorCode};
+use crate::{
+    mail::{mail_files, MailHeaders},
+    series::Series,
+    GitError, GitErrorCode,
+};
 
 // Constants for text replacement
 // We do this because we want to keep the series manager as transparent as possible
@@ -16,12 +23,287 @@ pub const COVER_LETTER_FILE_NAME: &str = "0000-cover-letter.patch";
 pub const CV_PATCH_SUBJECT_PLACEHOLDER: &str = "*** SUBJECT HERE ***";
 pub const CV_PATCH_DESCRIPTION_PLACEHOLDER: &str = "*** BLURB HERE ***";
 
-// TODO: maybe we should use a builder if the number of arguments increases again
+// Names of the mails of a directory that were delivered, one per line
+const DELIVERED_FILE_NAME: &str = "delivered";
+
 pub trait PatchSender {
-    fn send(
-        &self, series: &Series, to_email: &str, output_dir: &Path, first_commit: &str,
-        last_commit: &str, cc: Option<&str>,
-    ) -> Result<(), GitError>;
+    // Generate the mails in a new directory of the output directory,
+    // the caller owns the directory
+    fn format(&self, params: &SendParams) -> Result<PathBuf, GitError>;
+
+    // Deliver a single mail of a directory created by format
+    fn send_mail(&self, params: &SendParams, mail: &Path) -> Result<(), GitError>;
+
+    // Send the mails of a directory created by format, the mails delivered by a
+    // previous call are skipped. Every delivery is recorded in the directory as
+    // soon as it is done so that an interrupted send can be resumed
+    fn send_formatted(&self, params: &SendParams, dir: &Path) -> Result<SentMessages, GitError> {
+        send_one_by_one(self, params, dir)
+    }
+}
+
+fn to_send_err(e: io::Error) -> GitError {
+    GitError::new(GitErrorCode::SendSeriesFailed, e.to_string())
+}
+
+fn send_one_by_one<S: PatchSender + ?Sized>(
+    sender: &S, params: &SendParams, dir: &Path,
+) -> Result<SentMessages, GitError> {
+    let sent_messages = SentMessages::from_patch_dir(dir).map_err(to_send_err)?;
+    let delivered = delivered_mails(dir).map_err(to_send_err)?;
+    for f in mail_files(dir).map_err(to_send_err)? {
+        // mail_files only returns files
+        let name = f.file_name().unwrap().to_string_lossy();
+        if delivered.iter().any(|d| *d == name) {
+            continue;
+        }
+        sender.send_mail(params, &f)?;
+        mark_delivered(dir, &name).map_err(to_send_err)?;
+    }
+    Ok(sent_messages)
+}
+
+// File names of the mails of a directory already delivered by send_formatted
+pub fn delivered_mails(dir: &Path) -> Result<Vec<String>, io::Error> {
+    match fs::read_to_string(dir.join(DELIVERED_FILE_NAME)) {
+        Ok(content) => Ok(content.lines().map(String::from).collect()),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(Vec::new()),
+        Err(e) => Err(e),
+    }
+}
+
+fn mark_delivered(dir: &Path, name: &str) -> Result<(), io::Error> {
+    let mut file = fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(dir.join(DELIVERED_FILE_NAME))?;
+    writeln!(file, "{}", name)
+}
+
+pub struct SendParams<'a> {
+    series: &'a Series,
+    to_email: &'a str,
+    output_dir: &'a Path,
+    first_commit: &'a str,
+    last_commit: &'a str,
+    cc: Option<&'a str>,
+    in_reply_to: Option<&'a str>,
+    subject_prefix: Option<&'a str>,
+    base_commit: Option<&'a str>,
+}
+
+pub struct SendParamsBuilder<'a> {
+    params: SendParams<'a>,
+}
+
+// Message ids and subjects of the mails that were sent
+pub struct SentMessages {
+    cover_letter_id: Option<String>,
+    patch_ids: Vec<String>,
+    subjects: Vec<String>,
+}
+
+impl<'a> SendParams<'a> {
+    pub fn builder(
+        series: &'a Series, to_email: &'a str, output_dir: &'a Path, first_commit: &'a str,
+        last_commit: &'a str,
+    ) -> SendParamsBuilder<'a> {
+        SendParamsBuilder {
+            params: Self {
+                series,
+                to_email,
+                output_dir,
+                first_commit,
+                last_commit,
+                cc: None,
+                in_reply_to: None,
+                subject_prefix: None,
+                base_commit: None,
+            },
+        }
+    }
+
+    pub fn series(&self) -> &'a Series {
+        self.series
+    }
+
+    pub fn to_email(&self) -> &'a str {
+        self.to_email
+    }
+
+    pub fn cc(&self) -> Option<&'a str> {
+        self.cc
+    }
+}
+
+impl<'a> SendParamsBuilder<'a> {
+    pub fn set_cc(&mut self, cc: &'a str) {
+        self.params.cc = Some(cc);
+    }
+
+    // Message id the cover letter replies to
+    pub fn set_in_reply_to(&mut self, in_reply_to: &'a str) {
+        self.params.in_reply_to = Some(in_reply_to);
+    }
+
+    // Replaces the default prefix of the series
+    pub fn set_subject_prefix(&mut self, subject_prefix: &'a str) {
+        self.params.subject_prefix = Some(subject_prefix);
+    }
+
+    // Given in the mails with the prerequisite patches, see git format-patch --base
+    pub fn set_base_commit(&mut self, base_commit: &'a str) {
+        self.params.base_commit = Some(base_commit);
+    }
+
+    pub fn build(self) -> SendParams<'a> {
+        self.params
+    }
+}
+
+impl SentMessages {
+    // Collect the message ids of the patches generated by format-patch,
+    // the cover letter comes first
+    pub(crate) fn from_patch_dir(dir: &Path) -> Result<Self, io::Error> {
+        let files = mail_files(dir)?;
+        let mut result = Self {
+            cover_letter_id: None,
+            patch_ids: Vec::new(),
+            subjects: Vec::new(),
+        };
+        for f in files {
+            let content = fs::read_to_string(&f)?;
+            let headers = MailHeaders::parse(&content);
+            let Some(id) = headers.message_id().map(String::from) else {
+                continue;
+            };
+            result
+                .subjects
+                .push(String::from(headers.get("Subject").unwrap_or("")));
+            if f.file_name().is_some_and(|n| n == COVER_LETTER_FILE_NAME) {
+                result.cover_letter_id = Some(id);
+            } else {
+                result.patch_ids.push(id);
+            }
+        }
+        Ok(result)
+    }
+
+    pub fn cover_letter_id(&self) -> Option<&str> {
+        self.cover_letter_id.as_deref()
+    }
+
+    pub fn patch_ids(&self) -> &[String] {
+        self.patch_ids.as_slice()
+    }
+
+    // Subjects of all the mails, including the cover letter
+    pub fn subjects(&self) -> &[String] {
+        self.subjects.as_slice()
+    }
+}
+
+// Run format-patch on first_commit..last_commit and fill the cover letter,
+// the mails are generated in a new directory of output_dir
+pub fn format_series(
+    series: &Series, output_dir: &Path, first_commit: &str, last_commit: &str,
+    subject_prefix: &str, extra_args: &[String],
+) -> Result<PathBuf, GitError> {
+    if !fs::metadata(output_dir).is_ok_and(|m| m.is_dir()) {
+        return Err(GitError::new(
+            crate::GitErrorCode::InvalidPath,
+            String::from("Invalid output path"),
+        ));
+    }
+    if !commit_hash_valid(first_commit) || !commit_hash_valid(last_commit) {
+        return Err(GitError::new(
+            crate::GitErrorCode::StringFormatError,
+            String::from("Invalid commit hash format"),
+        ));
+    }
+
+    let tmp_out = output_dir.join(Uuid::new_v4().to_string());
+
+    let to_git_err = |e: io::Error| GitError::new(GitErrorCode::SendSeriesFailed, e.to_string());
+    let clean_and_map_err = |e: io::Error| {
+        let _ = fs::remove_dir_all(&tmp_out);
+        to_git_err(e)
+    };
+
+    fs::create_dir(&tmp_out).map_err(to_git_err)?;
+
+    let status = process::Command::new(GIT_COMMAND)
+        .arg("format-patch")
+        .arg("--cover-letter")
+        .arg("-n") // numbered
+        .arg("--thread=shallow") // every patch replies to the cover letter
+        .arg("-o")
+        .arg(&tmp_out) // output
+        .arg(format!("--subject-prefix={}", subject_prefix))
+        .args(extra_args)
+        .arg(format!("{}..{}", first_commit, last_commit))
+        .status()
+        .map_err(clean_and_map_err)?;
+    if !status.success() {
+        let _ = fs::remove_dir_all(&tmp_out);
+        return Err(GitError::new(
+            GitErrorCode::CommandExecutionFailed,
+            String::from("format-patch failed"),
+        ));
+    }
+
+    // prepare cover letter
+    let cv_path = tmp_out.join(COVER_LETTER_FILE_NAME);
+    let cv_content = fs::read_to_string(&cv_path).map_err(clean_and_map_err)?;
+    let cv_content = cv_content
+        .replace(CV_PATCH_SUBJECT_PLACEHOLDER, series.title())
+        .replace(CV_PATCH_DESCRIPTION_PLACEHOLDER, series.to_string().as_str());
+    fs::write(&cv_path, cv_content).map_err(clean_and_map_err)?;
+    Ok(tmp_out)
+}
+
+// Generate the mails with their recipients, the sender is set by the transport
+pub(crate) fn format_for_sending(
+    from_email: &str, params: &SendParams,
+) -> Result<PathBuf, GitError> {
+    format_mails(from_email, params, true)
+}
+
+fn format_mails(
+    from_email: &str, params: &SendParams, recipient_headers: bool,
+) -> Result<PathBuf, GitError> {
+    if !EmailAddress::is_valid(from_email) || !EmailAddress::is_valid(params.to_email) {
+        return Err(GitError::new(
+            crate::GitErrorCode::StringFormatError,
+            String::from("Invalid email address"),
+        ));
+    }
+
+    let mut args = Vec::new();
+    if recipient_headers {
+        args.push(format!("--to={}", params.to_email));
+        if let Some(c) = params.cc {
+            args.push(format!("--cc={}", c));
+        }
+    }
+    if let Some(id) = params.in_reply_to {
+        args.push(format!("--in-reply-to={}", id));
+    }
+    if let Some(base) = params.base_commit {
+        args.push(format!("--base={}", base));
+    }
+    let subject_prefix = match params.subject_prefix {
+        Some(p) => String::from(p),
+        None => params.series.subject_prefix(None, false, false)?,
+    };
+    format_series(
+        params.series,
+        params.output_dir,
+        params.first_commit,
+        params.last_commit,
+        &subject_prefix,
+        args.as_slice(),
+    )
 }
 
 pub struct GitPatchSender<'a> {
@@ -74,7 +356,7 @@ impl<'a> GitPatchSenderBuilder<'a> {
         self.smtp_encryption = Some(smtp_encryption)
     }
 
-    pub fn build(&self) -> GitPatchSender {
+    pub fn build(&self) -> GitPatchSender<'a> {
         GitPatchSender::new(
             self.from_email,
             self.send_command,
@@ -101,7 +383,7 @@ impl<'a> GitPatchSender<'a> {
         }
     }
 
-    pub fn builder(from_email: &'a str) -> GitPatchSenderBuilder {
+    pub fn builder(from_email: &'a str) -> GitPatchSenderBuilder<'a> {
         GitPatchSenderBuilder::new(from_email)
     }
 
@@ -122,81 +404,15 @@ impl<'a> GitPatchSender<'a> {
             }
         }
     }
-}
-
-impl PatchSender for GitPatchSender<'_> {
-    fn send(
-        &self, series: &Series, to_email: &str, output_dir: &Path, first_commit: &str,
-        last_commit: &str, cc: Option<&str>,
-    ) -> Result<(), GitError> {
-        if !fs::metadata(output_dir).is_ok_and(|m| m.is_dir()) {
-            return Err(GitError::new(
-                crate::GitErrorCode::InvalidPath,
-                String::from("Invalid output path"),
-            ));
-        }
-        if !commit_hash_valid(first_commit) || !commit_hash_valid(last_commit) {
-            return Err(GitError::new(
-                crate::GitErrorCode::StringFormatError,
-                String::from("Invalid commit hash format"),
-            ));
-        }
-        if !EmailAddress::is_valid(self.from_email) || !EmailAddress::is_valid(to_email) {
-            return Err(GitError::new(
-                crate::GitErrorCode::StringFormatError,
-                String::from("Invalid email address"),
-            ));
-        }
-
-        let tmp_out = output_dir.join(Uuid::new_v4().to_string());
-
-        let return_err = |e: io::Error| {
-            Err::<(), GitError>(GitError::new(GitErrorCode::SendSeriesFailed, e.to_string()))
-        };
-        let clean_and_return_err = |e: io::Error| {
-            let _ = fs::remove_dir_all(&tmp_out);
-            return_err(e)
-        };
-
-        fs::create_dir(&tmp_out).or_else(return_err)?;
-
-        let mut short_name = String::from(series.short_name());
-        if !short_name.is_empty() {
-            short_name.push(' ');
-        }
-        process::Command::new(GIT_COMMAND)
-            .arg("format-patch")
-            .arg("--cover-letter")
-            .arg("-n") // numbered
-            .arg("-o")
-            .arg(&tmp_out) // output
-            .arg(format!(
-                "--subject-prefix=PATCH {}v{}",
-                short_name,
-                series.current_revision()
-            ))
-            .arg(format!("{}..{}", first_commit, last_commit))
-            .status()
-            .map(to_unit)
-            .or_else(clean_and_return_err)?;
-
-        // prepare cover letter
-        let cv_path = tmp_out.join(COVER_LETTER_FILE_NAME);
-        let read_cv_res = fs::read_to_string(&cv_path);
-        let Ok(cv_content) = read_cv_res else {
-            return clean_and_return_err(read_cv_res.err().unwrap());
-        };
-        let cv_content = cv_content
-            .replace(CV_PATCH_SUBJECT_PLACEHOLDER, series.title())
-            .replace(CV_PATCH_DESCRIPTION_PLACEHOLDER, series.to_string().as_str());
-        fs::write(&cv_path, cv_content).or_else(clean_and_return_err)?;
 
+    fn send_email(&self, params: &SendParams, mail: &Path) -> Result<(), GitError> {
         let mut send_email_cmd = process::Command::new(GIT_COMMAND);
         send_email_cmd
             .arg("send-email")
             .arg(format!("--from={}", self.from_email))
-            .arg(format!("--to={}", to_email))
-            .arg(&tmp_out);
+            .arg(format!("--to={}", params.to_email))
+            .arg("--no-thread") // threading headers are set by format-patch
+            .arg(mail);
 
         if let Some(cmd) = self.send_command {
             send_email_cmd.arg(format!("--sendmail-cmd={}", cmd));
@@ -213,7 +429,7 @@ impl PatchSender for GitPatchSender<'_> {
         if let Some(encryption) = self.smtp_encryption {
             send_email_cmd.arg(format!("--smtp-encryption={}", encryption));
         };
-        if let Some(c) = cc {
+        if let Some(c) = params.cc {
             send_email_cmd.arg(format!("--cc={}", c));
         };
 
@@ -222,7 +438,6 @@ impl PatchSender for GitPatchSender<'_> {
         let send_email_cmd_res = send_email_cmd.status();
         Self::setup_signal_handler(libc::SIGINT, libc::SIG_DFL);
 
-        let _ = fs::remove_dir_all(&tmp_out);
         let send_email_cmd_res = send_email_cmd_res
             .map_err(|e| GitError::new(GitErrorCode::CommandExecutionFailed, e.to_string()));
         match send_email_cmd_res {
@@ -240,3 +455,15 @@ impl PatchSender for GitPatchSender<'_> {
         }
     }
 }
+
+impl PatchSender for GitPatchSender<'_> {
+    // The recipients are given to send-email only, it would otherwise
+    // also read them from the headers
+    fn format(&self, params: &SendParams) -> Result<PathBuf, GitError> {
+        format_mails(self.from_email, params, false)
+    }
+
+    fn send_mail(&self, params: &SendParams, mail: &Path) -> Result<(), GitError> {
+        self.send_email(params, mail)
+    }
+}
@@ -0,0 +1,217 @@
+use std::fmt::Display;
+
+use crate::{
+    util::{Commit, CommitId, GitRepo},
+    GitError,
+};
+
+// Result of the comparison of two versions of the same series.
+// Patches are matched by patch id first, then by subject so that
+// a patch that was reworked is reported as modified.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RangeDiffStatus {
+    Unchanged,
+    Modified,
+    Added,
+    Dropped,
+}
+
+#[derive(Debug, Clone)]
+pub struct RangeDiffEntry {
+    status: RangeDiffStatus,
+    // 1-based positions in the old and new ranges
+    old_pos: Option<usize>,
+    new_pos: Option<usize>,
+    reordered: bool,
+    subject: String,
+}
+
+struct PatchInfo {
+    id: String,
+    patch_id: String,
+    subject: String,
+}
+
+impl RangeDiffEntry {
+    pub fn status(&self) -> &RangeDiffStatus {
+        &self.status
+    }
+
+    pub fn old_pos(&self) -> Option<usize> {
+        self.old_pos
+    }
+
+    pub fn new_pos(&self) -> Option<usize> {
+        self.new_pos
+    }
+
+    pub fn reordered(&self) -> bool {
+        self.reordered
+    }
+
+    pub fn subject(&self) -> &str {
+        self.subject.as_str()
+    }
+}
+
+impl Display for RangeDiffStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Unchanged => f.write_str("unchanged"),
+            Self::Modified => f.write_str("modified"),
+            Self::Added => f.write_str("added"),
+            Self::Dropped => f.write_str("dropped"),
+        }
+    }
+}
+
+impl Display for RangeDiffEntry {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let pos = |p: Option<usize>| p.map_or(String::from("-"), |p| p.to_string());
+        f.write_fmt(format_args!(
+            "{:>3} -> {:<3} {:<9}",
+            pos(self.old_pos),
+            pos(self.new_pos),
+            self.status.to_string()
+        ))?;
+        f.write_str(if self.reordered { " reordered " } else { " " })?;
+        f.write_str(self.subject.as_str())
+    }
+}
+
+fn collect_patches(
+    repo: &GitRepo, base: &CommitId, last: &CommitId,
+) -> Result<Vec<PatchInfo>, GitError> {
+    repo.commit_range(base, last)?
+        .iter()
+        .map(|c: &Commit| {
+            Ok(PatchInfo {
+                id: c.id().to_string(),
+                patch_id: c.patch_id()?,
+                subject: String::from(c.short_name()),
+            })
+        })
+        .collect()
+}
+
+// Indexes (in the given slice) of the elements that belong to
+// a longest increasing subsequence
+fn longest_increasing_subsequence(values: &[usize]) -> Vec<usize> {
+    // O(n^2) is fine, series are short
+    let mut len = vec![1usize; values.len()];
+    let mut prev: Vec<Option<usize>> = vec![None; values.len()];
+    for i in 0..values.len() {
+        for j in 0..i {
+            if values[j] < values[i] && len[j] + 1 > len[i] {
+                len[i] = len[j] + 1;
+                prev[i] = Some(j);
+            }
+        }
+    }
+    let mut result = Vec::new();
+    // On ties, keep the sequence that ends first
+    let mut cur = (0..values.len()).rev().max_by_key(|&i| len[i]);
+    while let Some(i) = cur {
+        result.push(i);
+        cur = prev[i];
+    }
+    result
+}
+
+pub fn range_diff(
+    repo: &GitRepo, old: (&CommitId, &CommitId), new: (&CommitId, &CommitId),
+) -> Result<Vec<RangeDiffEntry>, GitError> {
+    let old_patches = collect_patches(repo, old.0, old.1)?;
+    let new_patches = collect_patches(repo, new.0, new.1)?;
+
+    // matches[i] = index in old_patches of the patch matching new_patches[i]
+    let mut matches: Vec<Option<usize>> = vec![None; new_patches.len()];
+    let mut old_used = vec![false; old_patches.len()];
+    let mut match_by = |same: &dyn Fn(&PatchInfo, &PatchInfo) -> bool| {
+        for (i, n) in new_patches.iter().enumerate() {
+            if matches[i].is_some() {
+                continue;
+            }
+            let found = old_patches
+                .iter()
+                .enumerate()
+                .find(|(j, o)| !old_used[*j] && same(o, n));
+            if let Some((j, _)) = found {
+                matches[i] = Some(j);
+                old_used[j] = true;
+            }
+        }
+    };
+    match_by(&|o, n| o.id == n.id || o.patch_id == n.patch_id);
+    match_by(&|o, n| o.subject == n.subject);
+
+    // A matched patch is reordered if it is not part of the longest
+    // sequence of patches that kept their relative order
+    let matched: Vec<(usize, usize)> =
+        matches.iter().enumerate().filter_map(|(i, m)| m.map(|j| (i, j))).collect();
+    let in_order = longest_increasing_subsequence(
+        matched.iter().map(|(_, j)| *j).collect::<Vec<usize>>().as_slice(),
+    );
+
+    let mut result: Vec<RangeDiffEntry> = Vec::new();
+    for (k, (i, j)) in matched.iter().enumerate() {
+        let (o, n) = (&old_patches[*j], &new_patches[*i]);
+        let status = if o.id == n.id || o.patch_id == n.patch_id {
+            RangeDiffStatus::Unchanged
+        } else {
+            RangeDiffStatus::Modified
+        };
+        result.push(RangeDiffEntry {
+            status,
+            old_pos: Some(j + 1),
+            new_pos: Some(i + 1),
+            reordered: !in_order.contains(&k),
+            subject: n.subject.clone(),
+        });
+    }
+    for (i, n) in new_patches.iter().enumerate() {
+        if matches[i].is_none() {
+            result.push(RangeDiffEntry {
+                status: RangeDiffStatus::Added,
+                old_pos: None,
+                new_pos: Some(i + 1),
+                reordered: false,
+                subject: n.subject.clone(),
+            });
+        }
+    }
+    for (j, o) in old_patches.iter().enumerate() {
+        if !old_used[j] {
+            result.push(RangeDiffEntry {
+                status: RangeDiffStatus::Dropped,
+                old_pos: Some(j + 1),
+                new_pos: None,
+                reordered: false,
+                subject: o.subject.clone(),
+            });
+        }
+    }
+
+    // Follow the new series, dropped patches are printed
+    // right after the patch that preceded them
+    result.sort_by_key(|e| match (e.new_pos, e.old_pos) {
+        (Some(n), _) => (n, 0),
+        (None, Some(o)) => {
+            let prev_new = new_pos_before(&matches, o);
+            (prev_new, 1)
+        }
+        (None, None) => (0, 0),
+    });
+    Ok(result)
+}
+
+// Position in the new series of the patch that precedes the old patch at `old_pos`
+fn new_pos_before(matches: &[Option<usize>], old_pos: usize) -> usize {
+    matches
+        .iter()
+        .enumerate()
+        .filter(|(_, m)| m.is_some_and(|j| j + 1 < old_pos))
+        .map(|(i, _)| i + 1)
+        .max()
+        .unwrap_or(0)
+}
@@ -2,6 +2,7 @@ use std::{fs, path::Path};
 
 use common::constants::PROJECT_VERSION;
 use serde::{Deserialize, Serialize};
+use serde_json::Value;
 
 use crate::{series::Series, util::GitRepo, GitError, GitErrorCode};
 
@@ -9,6 +10,8 @@ use crate::{series::Series, util::GitRepo, GitError, GitErrorCode};
 pub struct RepoMetadata {
     name: String,
     path: String,
+    // Whether the series are kept in the git repo, see util::DATA_REF
+    git_storage: bool,
 }
 
 // This is the serializable repo struct
@@ -25,12 +28,24 @@ pub struct Repo {
     series: Vec<Series>,
 }
 
+// Series changed on both sides of a merge. When both sides still have it,
+// the other version is kept under a new name
+pub struct MergeConflict {
+    name: String,
+    copy: Option<String>,
+}
+
+fn find_series<'a>(series: &'a [Value], name: &Value) -> Option<&'a Value> {
+    series.iter().find(|s| &s["name"] == name)
+}
+
 impl RepoMetadata {
     pub fn new(name: &str, path: &str) -> Self {
         let abs_path = fs::canonicalize(path).expect("Tried to find canonicalize an invalid path");
         Self {
             name: String::from(name),
             path: abs_path.to_string_lossy().to_string(),
+            git_storage: false,
         }
     }
 
@@ -41,7 +56,7 @@ impl RepoMetadata {
     pub fn dirname(&self) -> String {
         Path::new(self.path.as_str())
             .components()
-            .last()
+            .next_back()
             .unwrap()
             .as_os_str()
             .to_string_lossy()
@@ -51,6 +66,14 @@ impl RepoMetadata {
     pub fn path(&self) -> &str {
         self.path.as_str()
     }
+
+    pub fn git_storage(&self) -> bool {
+        self.git_storage
+    }
+
+    pub fn set_git_storage(&mut self, git_storage: bool) {
+        self.git_storage = git_storage;
+    }
 }
 
 impl PartialEq for RepoMetadata {
@@ -78,11 +101,21 @@ impl RepoData {
         &self.meta
     }
 
+    pub fn meta_mut(&mut self) -> &mut RepoMetadata {
+        &mut self.meta
+    }
+
     pub fn open_git_repo(&self) -> Option<GitRepo> {
         GitRepo::open(self.meta.path.as_str())
     }
 }
 
+impl Default for Repo {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl Repo {
     pub fn new() -> Self {
         Self {
@@ -97,6 +130,7 @@ impl Repo {
 
     pub fn add_series(
         &mut self, name: &str, title: &str, short_name: Option<&str>, cv_skel: Option<&str>,
+        branch: Option<&str>, base: Option<&str>,
     ) -> Result<(), GitError> {
         let Some(mut series) = Series::new(name, title) else {
             return Err(GitError::new(
@@ -116,6 +150,12 @@ impl Repo {
         if let Some(s) = cv_skel {
             series.set_cover_letter(s)?;
         }
+        if let Some(b) = branch {
+            series.set_branch(b)?;
+        }
+        if let Some(b) = base {
+            series.set_base(b)?;
+        }
         self.series.push(series);
         Ok(())
     }
@@ -143,4 +183,97 @@ impl Repo {
     pub fn version(&self) -> &str {
         self.version.as_str()
     }
+
+    // Three-way merge of two versions of the data, series by series. A
+    // series changed (or deleted) on one side only takes this version, the
+    // local one (ours) is kept when both sides changed it, along with a copy
+    // of theirs. Also returns these conflicting series
+    pub fn merge(
+        base: Option<&Repo>, ours: &Repo, theirs: &Repo,
+    ) -> Result<(Repo, Vec<MergeConflict>), GitError> {
+        let to_err = |e: serde_json::Error| GitError::repo_op_failed(e.to_string().as_str());
+        let series_values = |repo: Option<&Repo>| -> Result<Vec<Value>, GitError> {
+            repo.map(|r| r.series.as_slice())
+                .unwrap_or_default()
+                .iter()
+                .map(serde_json::to_value)
+                .collect::<Result<_, _>>()
+                .map_err(to_err)
+        };
+        let base_series = series_values(base)?;
+        let our_series = series_values(Some(ours))?;
+        let their_series = series_values(Some(theirs))?;
+
+        // local series first, then the ones added on the other side
+        let mut names: Vec<&Value> = Vec::new();
+        for s in our_series.iter().chain(their_series.iter()) {
+            if !names.contains(&&s["name"]) {
+                names.push(&s["name"]);
+            }
+        }
+
+        let mut series = Vec::new();
+        let mut conflicts = Vec::new();
+        let mut copies = Vec::new();
+        for name in names.iter().copied() {
+            let b = find_series(&base_series, name);
+            let o = find_series(&our_series, name);
+            let t = find_series(&their_series, name);
+            let kept = if o == t || t == b {
+                o
+            } else if o == b {
+                t
+            } else {
+                let name = name.as_str().unwrap_or_default();
+                let copy = match (o, t) {
+                    (Some(_), Some(t)) => {
+                        let copy = (1..)
+                            .map(|n| match n {
+                                1 => format!("{}-theirs", name),
+                                n => format!("{}-theirs-{}", name, n),
+                            })
+                            .find(|c| {
+                                !names.iter().any(|n| n.as_str() == Some(c))
+                                    && !copies.iter().any(|s: &Value| s["name"] == *c)
+                            })
+                            .unwrap();
+                        let mut t = t.clone();
+                        t["name"] = Value::from(copy.as_str());
+                        copies.push(t);
+                        Some(copy)
+                    }
+                    _ => None,
+                };
+                conflicts.push(MergeConflict {
+                    name: String::from(name),
+                    copy,
+                });
+                o.or(t)
+            };
+            if let Some(s) = kept {
+                series.push(serde_json::from_value(s.clone()).map_err(to_err)?);
+            }
+        }
+        for s in copies {
+            series.push(serde_json::from_value(s).map_err(to_err)?);
+        }
+        Ok((
+            Repo {
+                version: ours.version.clone(),
+                series,
+            },
+            conflicts,
+        ))
+    }
+}
+
+impl MergeConflict {
+    pub fn name(&self) -> &str {
+        self.name.as_str()
+    }
+
+    // Name of the copy of their version
+    pub fn copy(&self) -> Option<&str> {
+        self.copy.as_deref()
+    }
 }
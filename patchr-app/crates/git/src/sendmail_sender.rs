@@ -0,0 +1,84 @@
+use std::{
+    fs, io,
+    io::Write,
+    path::{Path, PathBuf},
+    process::{self, Stdio},
+};
+
+use crate::{
+    mail::OutgoingMail,
+    patch_sender::{format_for_sending, PatchSender, SendParams},
+    series::SeriesLog,
+    GitError, GitErrorCode,
+};
+
+// Pipes every mail to a sendmail compatible command, one invocation per
+// mail. The exit code and stderr of each invocation go to the series log
+pub struct SendmailPatchSender<'a> {
+    from_email: &'a str,
+    command: &'a str,
+}
+
+impl<'a> SendmailPatchSender<'a> {
+    // The command is run by the shell, like the sendmail-cmd of git send-email
+    pub fn new(from_email: &'a str, command: &'a str) -> Self {
+        Self {
+            from_email,
+            command,
+        }
+    }
+
+    fn deliver(&self, mail: &OutgoingMail) -> Result<process::Output, io::Error> {
+        let mut child = process::Command::new("sh")
+            .arg("-c")
+            .arg(format!("{} \"$@\"", self.command))
+            .arg(self.command)
+            .arg("-i") // a single dot does not end the message
+            .arg("-f")
+            .arg(self.from_email)
+            .args(mail.recipients())
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .stderr(Stdio::piped())
+            .spawn()?;
+        // the child owns a piped stdin, dropping it closes the pipe
+        let write_res = child
+            .stdin
+            .take()
+            .unwrap()
+            .write_all(mail.data().as_bytes());
+        let output = child.wait_with_output()?;
+        write_res.map(|_| output)
+    }
+}
+
+impl PatchSender for SendmailPatchSender<'_> {
+    fn format(&self, params: &SendParams) -> Result<PathBuf, GitError> {
+        format_for_sending(self.from_email, params)
+    }
+
+    fn send_mail(&self, params: &SendParams, mail: &Path) -> Result<(), GitError> {
+        let content = fs::read_to_string(mail)
+            .map_err(|e| GitError::new(GitErrorCode::SendSeriesFailed, e.to_string()))?;
+        let date = chrono::offset::Local::now().to_rfc2822();
+        let outgoing = OutgoingMail::new(&content, self.from_email, &date);
+        // format-patch names the mails, they are valid file names
+        let name = mail.file_name().unwrap().to_string_lossy();
+
+        let output = self.deliver(&outgoing).map_err(|e| {
+            GitError::new(
+                GitErrorCode::CommandExecutionFailed,
+                format!("Failed to run '{}' for {}: {}", self.command, name, e),
+            )
+        })?;
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        SeriesLog::delivery(params.series(), &name, output.status.code(), &stderr);
+        if !output.status.success() {
+            return Err(GitError::new(
+                GitErrorCode::CommandExecutionFailed,
+                format!("'{}' failed for {}: {}", self.command, name, stderr.trim()),
+            ));
+        }
+        Ok(())
+    }
+}
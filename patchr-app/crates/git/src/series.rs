@@ -4,40 +4,120 @@ use std::{
     ops::ControlFlow,
 };
 
-use common::util::{input::sanitize_cc_list, misc::{DEFAULT_DATE_TIME_FORMAT, LINE_SEP}};
+use chrono::{DateTime, Local, SecondsFormat};
+use common::util::{
+    input::sanitize_cc_list,
+    misc::{DEFAULT_DATE_TIME_FORMAT, LINE_SEP},
+};
 use lazy_static::lazy_static;
 use regex::Regex;
 use serde::{Deserialize, Serialize};
 
-use crate::{GitError, GitErrorCode};
+use crate::{export::ExportFormat, patch_sender::SentMessages, GitError, GitErrorCode};
 
 lazy_static! {
     static ref SERIES_TITLE_REGEX: Regex = Regex::new(r"^[a-zA-Z0-9 _-]+$").unwrap();
     static ref SERIES_NAME_REGEX: Regex = Regex::new(r"^[a-zA-Z0-9_-]+$").unwrap();
     static ref SERIES_SHORT_NAME_REGEX: Regex = Regex::new(r"^[a-zA-Z0-9_.-]{0,24}$").unwrap();
+    static ref SUBJECT_TAG_REGEX: Regex = Regex::new(r"^[a-zA-Z0-9 _./-]{1,64}$").unwrap();
 }
 
+pub const DEFAULT_SUBJECT_TAG: &str = "PATCH";
+
 #[derive(Serialize, Deserialize)]
 pub struct Series {
     name: String,
     title: String,
     cover_letter: String,
     short_name: String,
+    initial_revision: SeriesRevision,
     revisions: Vec<SeriesRevision>,
     logs: RefCell<Vec<SeriesLog>>,
+    sends: Vec<SendRecord>,
+    exports: Vec<ExportRecord>,
+    pending_send: Option<PendingSend>,
     cc: String,
+    // None to use the setting of the mailing list
+    thread_revisions: Option<bool>,
+    // Start of the subject prefix, PATCH if not set
+    subject_tag: Option<String>,
+    // Whether v1 is left out of the subjects of the first revision
+    drop_v1: bool,
+    // Branch holding the commits of the series, and the revision it is
+    // based on (e.g. origin/main), the upstream of the branch if not set
+    branch: Option<String>,
+    base: Option<String>,
+    // Whether the mails give the base commit, None to use the setting
+    // of the mailing list
+    base_info: Option<bool>,
 }
 
-// We only store the content for
-// now but we may add fields in the future
+// The content is the changelog of the revision, the range and
+// the message ids are recorded when the revision is sent
 #[derive(Serialize, Deserialize)]
 pub struct SeriesRevision {
     content: String,
+    range: Option<RevisionRange>,
+    cover_letter_id: Option<String>,
+    patch_ids: Vec<String>,
+}
+
+// Commits sent for a given revision
+// The base commit is excluded from the series, it is the commit
+// the first patch applies to
+#[derive(Serialize, Deserialize, Clone, PartialEq, Eq, Debug)]
+pub struct RevisionRange {
+    base_commit: String,
+    first_commit: String,
+    last_commit: String,
+    branch: Option<String>,
+    // Commit given as base-commit in the mails, None if not given
+    tree_base: Option<String>,
 }
 
 #[derive(Serialize, Deserialize)]
 pub struct SeriesLog(String);
 
+// Structured record of a send, unlike the logs
+// it is meant to be queried
+#[derive(Serialize, Deserialize, Clone)]
+pub struct SendRecord {
+    date: String, // RFC 3339
+    revision: u32,
+    from: String,
+    to: String,
+    cc: Option<String>,
+    range: RevisionRange,
+    cover_letter_id: Option<String>,
+    patch_ids: Vec<String>,
+    subjects: Vec<String>,
+    subject_prefix: String,
+}
+
+// Send that did not deliver all its mails yet, the generated
+// mails are kept in dir along with the names of the delivered ones
+#[derive(Serialize, Deserialize, Clone)]
+pub struct PendingSend {
+    date: String, // RFC 3339
+    revision: u32,
+    from: String,
+    to: String,
+    cc: Option<String>,
+    range: RevisionRange,
+    subject_prefix: String,
+    dir: String,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct ExportRecord {
+    date: String, // RFC 3339
+    revision: u32,
+    format: ExportFormat,
+    path: String,
+    range: RevisionRange,
+    subjects: Vec<String>,
+}
+
 impl SeriesLog {
     fn log(series: &Series, op: &str, msg: &str) {
         let log = SeriesLog(format!(
@@ -49,14 +129,66 @@ impl SeriesLog {
         series.log(log);
     }
 
-    pub fn send(series: &Series, to: &str) {
+    pub fn send(series: &Series, to: &str, subject_prefix: &str) {
+        Self::log(
+            series,
+            "send",
+            &format!(
+                "revision {} send to {} as [{}]",
+                series.current_revision(),
+                to,
+                subject_prefix
+            ),
+        );
+    }
+
+    pub fn send_interrupted(series: &Series, to: &str, delivered: usize, total: usize) {
         Self::log(
             series,
             "send",
-            &format!("revision {} send to {}", series.current_revision(), to),
+            &format!(
+                "revision {} send to {} interrupted, {}/{} mails delivered",
+                series.current_revision(),
+                to,
+                delivered,
+                total
+            ),
+        );
+    }
+
+    pub fn export(series: &Series, path: &str) {
+        Self::log(
+            series,
+            "export",
+            &format!("revision {} exported to {}", series.current_revision(), path),
         );
     }
 
+    pub fn trailers(series: &Series, revision: u32, commits: usize) {
+        Self::log(
+            series,
+            "trailers",
+            &format!(
+                "trailers of the replies to revision {} added to {} commits",
+                revision, commits
+            ),
+        );
+    }
+
+    pub fn delivery(series: &Series, mail: &str, exit_code: Option<i32>, stderr: &str) {
+        let status = if exit_code == Some(0) {
+            "delivered"
+        } else {
+            "failed"
+        };
+        let exit_code = exit_code.map_or(String::from("none"), |c| c.to_string());
+        let mut msg = format!("{} {}, exit code {}", mail, status, exit_code);
+        if !stderr.trim().is_empty() {
+            msg.push_str(&format!(", stderr: {}", stderr.trim()));
+        }
+        Self::log(series, "delivery", &msg);
+    }
+
     pub fn add_revision(series: &mut Series, rev: usize) {
         Self::log(series, "add revision", &format!("revision updated to {}", rev));
     }
@@ -79,9 +211,19 @@ impl Series {
             title: String::from(title),
             cover_letter: String::new(),
             short_name: String::new(),
+            initial_revision: SeriesRevision::new(""),
             revisions: Vec::new(),
             logs: RefCell::new(Vec::new()),
+            sends: Vec::new(),
+            exports: Vec::new(),
+            pending_send: None,
             cc: String::new(),
+            thread_revisions: None,
+            subject_tag: None,
+            drop_v1: false,
+            branch: None,
+            base: None,
+            base_info: None,
         })
     }
 
@@ -186,12 +328,211 @@ impl Series {
     }
 
     pub fn revision_mut(&mut self, n: usize) -> Option<&mut SeriesRevision> {
-        let Some(n) = Self::revision_index(n) else {
-            return None;
-        };
+        let n = Self::revision_index(n)?;
         self.revisions.get_mut(n)
     }
 
+    // Unlike revision_mut, this also gives access to the first revision
+    // which has no changelog but may have a recorded range
+    pub fn revision(&self, n: usize) -> Option<&SeriesRevision> {
+        match n {
+            0 => None,
+            1 => Some(&self.initial_revision),
+            _ => self.revisions.get(n - 2),
+        }
+    }
+
+    pub fn current_range(&self) -> Option<&RevisionRange> {
+        self.revision(self.current_revision() as usize)?.range()
+    }
+
+    fn current_revision_mut(&mut self) -> &mut SeriesRevision {
+        match self.revisions.last_mut() {
+            Some(r) => r,
+            None => &mut self.initial_revision,
+        }
+    }
+
+    pub fn set_current_range(&mut self, range: RevisionRange) {
+        self.current_revision_mut().range = Some(range);
+    }
+
+    pub fn set_revision_range(&mut self, n: usize, range: RevisionRange) {
+        let revision = match n {
+            1 => Some(&mut self.initial_revision),
+            _ => self.revision_mut(n),
+        };
+        if let Some(r) = revision {
+            r.range = Some(range);
+        }
+    }
+
+    pub fn set_current_message_ids(&mut self, cover_letter_id: Option<&str>, patch_ids: &[String]) {
+        let current = self.current_revision_mut();
+        current.cover_letter_id = cover_letter_id.map(String::from);
+        current.patch_ids = patch_ids.to_vec();
+    }
+
+    // Cover letter the next mails should reply to, if the previous
+    // revision was sent
+    pub fn previous_cover_letter_id(&self) -> Option<&str> {
+        let previous = (self.current_revision() as usize).checked_sub(1)?;
+        self.revision(previous)?.cover_letter_id()
+    }
+
+    pub fn sends(&self) -> &[SendRecord] {
+        self.sends.as_slice()
+    }
+
+    pub fn add_send_record(&mut self, record: SendRecord) {
+        self.sends.push(record);
+    }
+
+    pub fn pending_send(&self) -> Option<&PendingSend> {
+        self.pending_send.as_ref()
+    }
+
+    pub fn set_pending_send(&mut self, pending_send: Option<PendingSend>) {
+        self.pending_send = pending_send;
+    }
+
+    pub fn exports(&self) -> &[ExportRecord] {
+        self.exports.as_slice()
+    }
+
+    pub fn add_export_record(&mut self, record: ExportRecord) {
+        self.exports.push(record);
+    }
+
+    pub fn thread_revisions(&self) -> Option<bool> {
+        self.thread_revisions
+    }
+
+    pub fn set_thread_revisions(&mut self, thread_revisions: Option<bool>) {
+        self.thread_revisions = thread_revisions;
+    }
+
+    pub fn subject_tag(&self) -> &str {
+        self.subject_tag.as_deref().unwrap_or(DEFAULT_SUBJECT_TAG)
+    }
+
+    // An empty tag restores the default one
+    pub fn set_subject_tag(&mut self, tag: &str) -> Result<(), GitError> {
+        let tag = tag.trim();
+        if tag.is_empty() {
+            self.subject_tag = None;
+            return Ok(());
+        }
+        let Some(tag) = Series::validate_subject_tag(tag) else {
+            return Err(GitError::new(
+                GitErrorCode::StringFormatError,
+                String::from("Invalid subject prefix format"),
+            ));
+        };
+        self.subject_tag = Some(tag);
+        Ok(())
+    }
+
+    pub fn drop_v1(&self) -> bool {
+        self.drop_v1
+    }
+
+    pub fn set_drop_v1(&mut self, drop_v1: bool) {
+        self.drop_v1 = drop_v1;
+    }
+
+    pub fn branch(&self) -> Option<&str> {
+        self.branch.as_deref()
+    }
+
+    // An empty branch unsets it
+    pub fn set_branch(&mut self, branch: &str) -> Result<(), GitError> {
+        let branch = branch.trim();
+        if branch.is_empty() {
+            self.branch = None;
+            return Ok(());
+        }
+        if !git2::Branch::name_is_valid(branch).unwrap_or(false) {
+            return Err(GitError::new(
+                GitErrorCode::StringFormatError,
+                String::from("Invalid branch name"),
+            ));
+        }
+        self.branch = Some(String::from(branch));
+        Ok(())
+    }
+
+    pub fn base(&self) -> Option<&str> {
+        self.base.as_deref()
+    }
+
+    // Any revision, resolved when the series is sent. An empty base unsets it
+    pub fn set_base(&mut self, base: &str) -> Result<(), GitError> {
+        let base = base.trim();
+        if base.is_empty() {
+            self.base = None;
+            return Ok(());
+        }
+        if base.contains(char::is_whitespace) {
+            return Err(GitError::new(
+                GitErrorCode::StringFormatError,
+                String::from("Invalid base format"),
+            ));
+        }
+        self.base = Some(String::from(base));
+        Ok(())
+    }
+
+    pub fn base_info(&self) -> Option<bool> {
+        self.base_info
+    }
+
+    pub fn set_base_info(&mut self, base_info: Option<bool>) {
+        self.base_info = base_info;
+    }
+
+    // Prefix of the subjects of the current revision without the brackets,
+    // e.g. RFC PATCH net-next RESEND short v2. The tag replaces the one
+    // of the series
+    pub fn subject_prefix(
+        &self, tag: Option<&str>, rfc: bool, resend: bool,
+    ) -> Result<String, GitError> {
+        let tag = match tag {
+            Some(t) => Series::validate_subject_tag(t).ok_or_else(|| {
+                GitError::new(
+                    GitErrorCode::StringFormatError,
+                    String::from("Invalid subject prefix format"),
+                )
+            })?,
+            None => String::from(self.subject_tag()),
+        };
+        let mut parts = Vec::new();
+        if rfc {
+            parts.push(String::from("RFC"));
+        }
+        parts.push(tag);
+        if resend {
+            parts.push(String::from("RESEND"));
+        }
+        if !self.short_name.is_empty() {
+            parts.push(self.short_name.clone());
+        }
+        if !(self.drop_v1 && self.current_revision() == 1) {
+            parts.push(format!("v{}", self.current_revision()));
+        }
+        Ok(parts.join(" "))
+    }
+
+    // Collapses the whitespaces, the brackets are added by format-patch
+    fn validate_subject_tag(tag: &str) -> Option<String> {
+        let tag = tag.split_whitespace().collect::<Vec<_>>().join(" ");
+        if SUBJECT_TAG_REGEX.is_match(&tag) {
+            Some(tag)
+        } else {
+            None
+        }
+    }
+
     fn validate_title(title: &str) -> Option<&str> {
         let title = title.trim();
         if SERIES_TITLE_REGEX.is_match(title) {
@@ -258,6 +599,43 @@ impl Debug for Series {
         f.write_fmt(format_args!("Title : {}{}", self.title, LINE_SEP))?;
         f.write_fmt(format_args!("Short name : {}{}", self.short_name, LINE_SEP))?;
         f.write_fmt(format_args!("Revision : {}{}", self.current_revision(), LINE_SEP))?;
+        let thread_revisions = match self.thread_revisions {
+            Some(true) => "yes",
+            Some(false) => "no",
+            None => "mailing list setting",
+        };
+        f.write_fmt(format_args!("Thread revisions : {}{}", thread_revisions, LINE_SEP))?;
+        let base_info = match self.base_info {
+            Some(true) => "yes",
+            Some(false) => "no",
+            None => "mailing list setting",
+        };
+        f.write_fmt(format_args!("Base commit info : {}{}", base_info, LINE_SEP))?;
+        if let Ok(prefix) = self.subject_prefix(None, false, false) {
+            f.write_fmt(format_args!("Subject prefix : [{}]{}", prefix, LINE_SEP))?;
+        }
+        if let Some(branch) = self.branch.as_ref() {
+            f.write_fmt(format_args!("Branch : {}{}", branch, LINE_SEP))?;
+            let base = self.base.as_deref().unwrap_or("upstream of the branch");
+            f.write_fmt(format_args!("Base : {}{}", base, LINE_SEP))?;
+        }
+        if let Some(i) = self.pending_send.as_ref() {
+            f.write_fmt(format_args!(
+                "Pending send : v{} to {} on {}, kept in {}{}",
+                i.revision,
+                i.to,
+                i.local_date(),
+                i.dir,
+                LINE_SEP
+            ))?;
+        }
+        f.write_str(LINE_SEP)?;
+        f.write_fmt(format_args!("Commits :{}", LINE_SEP))?;
+        for n in 1..=self.current_revision() as usize {
+            if let Some(range) = self.revision(n).and_then(SeriesRevision::range) {
+                f.write_fmt(format_args!("v{} {}{}", n, range, LINE_SEP))?;
+            }
+        }
         f.write_str(LINE_SEP)?;
         Display::fmt(&self, f)?;
         f.write_fmt(format_args!("Log :{}", LINE_SEP))?;
@@ -272,9 +650,24 @@ impl SeriesRevision {
     pub fn new(content: &str) -> Self {
         Self {
             content: String::from(content),
+            range: None,
+            cover_letter_id: None,
+            patch_ids: Vec::new(),
         }
     }
 
+    pub fn range(&self) -> Option<&RevisionRange> {
+        self.range.as_ref()
+    }
+
+    pub fn cover_letter_id(&self) -> Option<&str> {
+        self.cover_letter_id.as_deref()
+    }
+
+    pub fn patch_ids(&self) -> &[String] {
+        self.patch_ids.as_slice()
+    }
+
     pub fn content(&self) -> &str {
         self.content.as_ref()
     }
@@ -299,3 +692,227 @@ impl Display for SeriesRevision {
         }
     }
 }
+
+impl RevisionRange {
+    pub fn new(
+        base_commit: &str, first_commit: &str, last_commit: &str, branch: Option<&str>,
+    ) -> Self {
+        Self {
+            base_commit: String::from(base_commit),
+            first_commit: String::from(first_commit),
+            last_commit: String::from(last_commit),
+            branch: branch.map(String::from),
+            tree_base: None,
+        }
+    }
+
+    pub fn base_commit(&self) -> &str {
+        self.base_commit.as_str()
+    }
+
+    pub fn first_commit(&self) -> &str {
+        self.first_commit.as_str()
+    }
+
+    pub fn last_commit(&self) -> &str {
+        self.last_commit.as_str()
+    }
+
+    pub fn branch(&self) -> Option<&str> {
+        self.branch.as_deref()
+    }
+
+    pub fn tree_base(&self) -> Option<&str> {
+        self.tree_base.as_deref()
+    }
+
+    pub fn set_tree_base(&mut self, tree_base: Option<&str>) {
+        self.tree_base = tree_base.map(String::from);
+    }
+}
+
+impl Display for RevisionRange {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_fmt(format_args!("{}..{}", self.base_commit, self.last_commit))?;
+        if let Some(branch) = self.branch.as_ref() {
+            f.write_fmt(format_args!(" ({})", branch))?;
+        }
+        if let Some(tree_base) = self.tree_base.as_ref() {
+            f.write_fmt(format_args!(" base-commit {}", tree_base))?;
+        }
+        Ok(())
+    }
+}
+
+impl SendRecord {
+    pub fn new(
+        revision: u32, from: &str, to: &str, cc: Option<&str>, range: RevisionRange,
+        subject_prefix: &str, sent: &SentMessages,
+    ) -> Self {
+        Self {
+            date: now_rfc3339(),
+            revision,
+            from: String::from(from),
+            to: String::from(to),
+            cc: cc.map(String::from),
+            range,
+            cover_letter_id: sent.cover_letter_id().map(String::from),
+            patch_ids: sent.patch_ids().to_vec(),
+            subjects: sent.subjects().to_vec(),
+            subject_prefix: String::from(subject_prefix),
+        }
+    }
+
+    pub fn date(&self) -> &str {
+        self.date.as_str()
+    }
+
+    pub fn local_date(&self) -> String {
+        local_date(self.date.as_str())
+    }
+
+    pub fn revision(&self) -> u32 {
+        self.revision
+    }
+
+    pub fn from(&self) -> &str {
+        self.from.as_str()
+    }
+
+    pub fn to(&self) -> &str {
+        self.to.as_str()
+    }
+
+    pub fn cc(&self) -> Option<&str> {
+        self.cc.as_deref()
+    }
+
+    pub fn range(&self) -> &RevisionRange {
+        &self.range
+    }
+
+    pub fn cover_letter_id(&self) -> Option<&str> {
+        self.cover_letter_id.as_deref()
+    }
+
+    pub fn patch_ids(&self) -> &[String] {
+        self.patch_ids.as_slice()
+    }
+
+    pub fn subjects(&self) -> &[String] {
+        self.subjects.as_slice()
+    }
+
+    pub fn subject_prefix(&self) -> &str {
+        self.subject_prefix.as_str()
+    }
+}
+
+impl PendingSend {
+    pub fn new(
+        revision: u32, from: &str, to: &str, cc: Option<&str>, range: RevisionRange,
+        subject_prefix: &str, dir: &str,
+    ) -> Self {
+        Self {
+            date: now_rfc3339(),
+            revision,
+            from: String::from(from),
+            to: String::from(to),
+            cc: cc.map(String::from),
+            range,
+            subject_prefix: String::from(subject_prefix),
+            dir: String::from(dir),
+        }
+    }
+
+    pub fn local_date(&self) -> String {
+        local_date(self.date.as_str())
+    }
+
+    pub fn revision(&self) -> u32 {
+        self.revision
+    }
+
+    pub fn from(&self) -> &str {
+        self.from.as_str()
+    }
+
+    pub fn to(&self) -> &str {
+        self.to.as_str()
+    }
+
+    pub fn cc(&self) -> Option<&str> {
+        self.cc.as_deref()
+    }
+
+    pub fn range(&self) -> &RevisionRange {
+        &self.range
+    }
+
+    pub fn subject_prefix(&self) -> &str {
+        self.subject_prefix.as_str()
+    }
+
+    pub fn dir(&self) -> &str {
+        self.dir.as_str()
+    }
+}
+
+impl ExportRecord {
+    pub fn new(
+        revision: u32, format: ExportFormat, path: &str, range: RevisionRange,
+        subjects: Vec<String>,
+    ) -> Self {
+        Self {
+            date: now_rfc3339(),
+            revision,
+            format,
+            path: String::from(path),
+            range,
+            subjects,
+        }
+    }
+
+    pub fn date(&self) -> &str {
+        self.date.as_str()
+    }
+
+    pub fn local_date(&self) -> String {
+        local_date(self.date.as_str())
+    }
+
+    pub fn revision(&self) -> u32 {
+        self.revision
+    }
+
+    pub fn format(&self) -> ExportFormat {
+        self.format
+    }
+
+    pub fn path(&self) -> &str {
+        self.path.as_str()
+    }
+
+    pub fn range(&self) -> &RevisionRange {
+        &self.range
+    }
+
+    pub fn subjects(&self) -> &[String] {
+        self.subjects.as_slice()
+    }
+}
+
+fn now_rfc3339() -> String {
+    chrono::offset::Local::now().to_rfc3339_opts(SecondsFormat::Secs, false)
+}
+
+// Date in the format used by the logs, falls back to the raw value
+fn local_date(date: &str) -> String {
+    match DateTime::parse_from_rfc3339(date) {
+        Ok(d) => d
+            .with_timezone(&Local)
+            .format(DEFAULT_DATE_TIME_FORMAT)
+            .to_string(),
+        Err(_) => String::from(date),
+    }
+}
@@ -0,0 +1,138 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use lettre::{
+    address::Envelope,
+    transport::smtp::{
+        authentication::Credentials,
+        client::{Tls, TlsParameters},
+        SMTP_PORT, SUBMISSIONS_PORT, SUBMISSION_PORT,
+    },
+    Address, SmtpTransport, Transport,
+};
+
+use crate::{
+    mail::OutgoingMail,
+    patch_sender::{format_for_sending, PatchSender, SendParams},
+    GitError, GitErrorCode,
+};
+
+// Same values as git send-email, ssl for an implicit TLS
+// connection and tls for STARTTLS
+pub const SMTP_ENCRYPTION_SSL: &str = "ssl";
+pub const SMTP_ENCRYPTION_TLS: &str = "tls";
+
+// Delivers the mails over SMTP without git send-email
+pub struct SmtpPatchSender<'a> {
+    from_email: &'a str,
+    server: &'a str,
+    port: Option<u16>,
+    credentials: Option<(&'a str, &'a str)>,
+    encryption: Option<&'a str>,
+}
+
+pub struct SmtpPatchSenderBuilder<'a> {
+    sender: SmtpPatchSender<'a>,
+}
+
+impl<'a> SmtpPatchSenderBuilder<'a> {
+    pub fn set_port(&mut self, port: u16) {
+        self.sender.port = Some(port);
+    }
+
+    pub fn set_credentials(&mut self, user: &'a str, password: &'a str) {
+        self.sender.credentials = Some((user, password));
+    }
+
+    pub fn set_encryption(&mut self, encryption: &'a str) {
+        self.sender.encryption = Some(encryption);
+    }
+
+    pub fn build(self) -> SmtpPatchSender<'a> {
+        self.sender
+    }
+}
+
+impl<'a> SmtpPatchSender<'a> {
+    pub fn builder(from_email: &'a str, server: &'a str) -> SmtpPatchSenderBuilder<'a> {
+        SmtpPatchSenderBuilder {
+            sender: Self {
+                from_email,
+                server,
+                port: None,
+                credentials: None,
+                encryption: None,
+            },
+        }
+    }
+
+    fn transport(&self) -> Result<SmtpTransport, GitError> {
+        let tls_parameters = || {
+            TlsParameters::new(String::from(self.server))
+                .map_err(|e| GitError::new(GitErrorCode::SendSeriesFailed, e.to_string()))
+        };
+        let (tls, default_port) = match self.encryption {
+            None => (Tls::None, SMTP_PORT),
+            Some(SMTP_ENCRYPTION_SSL) => (Tls::Wrapper(tls_parameters()?), SUBMISSIONS_PORT),
+            Some(SMTP_ENCRYPTION_TLS) => (Tls::Required(tls_parameters()?), SUBMISSION_PORT),
+            Some(e) => {
+                return Err(GitError::new(
+                    GitErrorCode::StringFormatError,
+                    format!("Unknown smtp encryption '{}'", e),
+                ))
+            }
+        };
+        let mut builder = SmtpTransport::builder_dangerous(self.server)
+            .tls(tls)
+            .port(self.port.unwrap_or(default_port));
+        if let Some((user, password)) = self.credentials {
+            builder =
+                builder.credentials(Credentials::new(String::from(user), String::from(password)));
+        }
+        Ok(builder.build())
+    }
+
+    fn parse_address(address: &str) -> Result<Address, GitError> {
+        address.parse::<Address>().map_err(|e| {
+            GitError::new(
+                GitErrorCode::StringFormatError,
+                format!("Invalid email address '{}': {}", address, e),
+            )
+        })
+    }
+}
+
+impl PatchSender for SmtpPatchSender<'_> {
+    fn format(&self, params: &SendParams) -> Result<PathBuf, GitError> {
+        format_for_sending(self.from_email, params)
+    }
+
+    fn send_mail(&self, _params: &SendParams, mail: &Path) -> Result<(), GitError> {
+        let content = fs::read_to_string(mail)
+            .map_err(|e| GitError::new(GitErrorCode::SendSeriesFailed, e.to_string()))?;
+        let date = chrono::offset::Local::now().to_rfc2822();
+        let outgoing = OutgoingMail::new(&content, self.from_email, &date);
+        // the recipients are read from the mail, format-patch wrote them
+        let recipients = outgoing
+            .recipients()
+            .iter()
+            .map(|r| Self::parse_address(r))
+            .collect::<Result<Vec<_>, _>>()?;
+        let envelope = Envelope::new(Some(Self::parse_address(self.from_email)?), recipients)
+            .map_err(|e| GitError::new(GitErrorCode::SendSeriesFailed, e.to_string()))?;
+        // lettre terminates the data with CRLF.CRLF
+        let data = outgoing.data_crlf();
+        let data = data.strip_suffix("\r\n").unwrap_or(&data);
+        self.transport()?
+            .send_raw(&envelope, data.as_bytes())
+            .map(|_| ())
+            .map_err(|e| {
+                GitError::new(
+                    GitErrorCode::SendSeriesFailed,
+                    format!("Failed to send {}: {}", mail.display(), e),
+                )
+            })
+    }
+}
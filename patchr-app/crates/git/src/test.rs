@@ -1 +1,10 @@
-mod series;
\ No newline at end of file
+mod export;
+mod mail;
+mod maildir_sender;
+mod range_diff;
+mod repo;
+mod sendmail_sender;
+mod series;
+mod smtp_sender;
+mod trailers;
+mod util;
@@ -0,0 +1,29 @@
+use std::fs;
+
+use crate::export::{export_mails, ExportFormat};
+
+use super::util::TestDir;
+
+#[test]
+fn test_export_mails() {
+    let dir = TestDir::new();
+    let mails = dir.join("mails");
+    fs::create_dir_all(&mails).unwrap();
+    let cover = "From 0 Mon Sep 17 00:00:00 2001\nSubject: [PATCH v2 0/1] t\n\nblurb\n";
+    let patch = "From 1 Mon Sep 17 00:00:00 2001\nSubject: [PATCH v2 1/1] a\n\ndiff";
+    fs::write(mails.join("0000-cover-letter.patch"), cover).unwrap();
+    fs::write(mails.join("0001-a.patch"), patch).unwrap();
+
+    let mbox = dir.join("out.mbox");
+    let subjects = export_mails(&mails, &mbox, ExportFormat::Mbox).unwrap();
+    assert_eq!(subjects, ["[PATCH v2 0/1] t", "[PATCH v2 1/1] a"]);
+    assert_eq!(fs::read_to_string(&mbox).unwrap(), format!("{}{}\n", cover, patch));
+    // never overwrite an existing output
+    assert!(export_mails(&mails, &mbox, ExportFormat::Mbox).is_err());
+
+    let out_dir = dir.join("out");
+    export_mails(&mails, &out_dir, ExportFormat::Directory).unwrap();
+    assert_eq!(fs::read_to_string(out_dir.join("0001-a.patch")).unwrap(), patch);
+    assert!(out_dir.join("0000-cover-letter.patch").is_file());
+
+}
@@ -0,0 +1,60 @@
+use std::fs;
+
+use crate::mail::{mail_files, MailHeaders, OutgoingMail};
+
+use super::util::TestDir;
+
+#[test]
+fn test_parse_headers() {
+    let content = "From 7ac63f28fbb52736232655da7c817c181a91f384 Mon Sep 17 00:00:00 2001\n\
+                   Message-ID: <cover.1.git.test@patchr.com>\n\
+                   From: Patchr <test@patchr.com>\n\
+                   Subject: [PATCH v2 0/2] A very long subject\n \
+                   that is folded\n\
+                   In-Reply-To: <previous@patchr.com>\n\
+                   \n\
+                   Subject: not a header\n";
+    let headers = MailHeaders::parse(content);
+    assert_eq!(headers.message_id(), Some("<cover.1.git.test@patchr.com>"));
+    assert_eq!(headers.get("from"), Some("Patchr <test@patchr.com>"));
+    assert_eq!(headers.get("Subject"), Some("[PATCH v2 0/2] A very long subject that is folded"));
+    assert_eq!(headers.get("in-reply-to"), Some("<previous@patchr.com>"));
+    assert_eq!(headers.get("References"), None);
+}
+
+#[test]
+fn test_parse_headers_without_separator() {
+    let headers = MailHeaders::parse("Subject: test\n");
+    assert_eq!(headers.get("subject"), Some("test"));
+    assert!(MailHeaders::parse("").get("subject").is_none());
+}
+
+#[test]
+fn test_mail_files() {
+    let dir = TestDir::new();
+    for name in ["0002-b.patch", "0000-cover-letter.patch", "notes.txt", "0001-a.patch"] {
+        fs::write(dir.join(name), "").unwrap();
+    }
+    let files = mail_files(&dir).unwrap();
+
+    let names: Vec<_> = files.iter().map(|f| f.file_name().unwrap().to_owned()).collect();
+    assert_eq!(names, ["0000-cover-letter.patch", "0001-a.patch", "0002-b.patch"]);
+}
+
+#[test]
+fn test_outgoing_mail() {
+    let content = "From 1 Mon Sep 17 00:00:00 2001\n\
+                   From: Patchr <Test@patchr.com>\n\
+                   Date: Thu, 1 Jan 1970 00:00:00 +0000\n\
+                   Subject: a\n\
+                   To: list@patchr.com\n\
+                   Cc: a@patchr.com, \"B, b\" <b@patchr.com>, list@patchr.com\n\
+                   \n\
+                   body\n";
+    let mail = OutgoingMail::new(content, "test@patchr.com", "now");
+    assert_eq!(mail.recipients(), ["list@patchr.com", "a@patchr.com", "b@patchr.com"]);
+    // same address, the author is not repeated in the body
+    assert!(mail.data().starts_with("From: test@patchr.com\nDate: now\nSubject: a\n"));
+    assert!(mail.data().ends_with("\n\nbody\n"));
+    assert!(mail.data_crlf().ends_with("\r\n\r\nbody\r\n"));
+}
@@ -0,0 +1,37 @@
+use std::fs;
+
+use crate::{
+    maildir_sender::MaildirPatchSender,
+    patch_sender::{PatchSender, SendParams},
+    series::Series,
+};
+
+use super::util::{write_mails, TestDir};
+
+#[test]
+fn test_maildir_sender() {
+    let dir = TestDir::new();
+    let mails_dir = dir.join("mails");
+    let maildir = dir.join("maildir");
+    write_mails(&mails_dir);
+
+    let series = Series::new("name", "title").unwrap();
+    let params = SendParams::builder(&series, "list@patchr.com", &mails_dir, "a", "b").build();
+    let sent =
+        MaildirPatchSender::new("test@patchr.com", &maildir).send_formatted(&params, &mails_dir);
+    let mut mails: Vec<String> = fs::read_dir(maildir.join("new"))
+        .unwrap()
+        .map(|e| fs::read_to_string(e.unwrap().path()).unwrap())
+        .collect();
+    let tmp_count = fs::read_dir(maildir.join("tmp")).unwrap().count();
+    let cur_count = fs::read_dir(maildir.join("cur")).unwrap().count();
+
+    assert_eq!(sent.unwrap().patch_ids(), [String::from("<p1@patchr.com>")]);
+    assert_eq!((tmp_count, cur_count), (0, 0));
+    // the names are not ordered within the same second
+    mails.sort_by_key(|m| !m.contains("Subject: [PATCH v1 0/1]"));
+    assert_eq!(mails.len(), 2);
+    assert!(mails[0].starts_with("From: test@patchr.com\nDate: "));
+    assert!(mails[0].contains("\nCc: \"Doe, John\" <john@patchr.com>,\n jane@patchr.com\n"));
+    assert!(mails[1].contains("\nSubject: [PATCH v1 1/1] a\n"));
+}
@@ -0,0 +1,58 @@
+use crate::{
+    range_diff::{range_diff, RangeDiffStatus},
+    util::CommitId,
+};
+
+use super::util::TestRepo;
+
+type EntrySummary<'a> = (&'a str, RangeDiffStatus, Option<usize>, Option<usize>, bool);
+
+fn id(hex: &str) -> CommitId {
+    CommitId::new(hex).unwrap()
+}
+
+#[test]
+fn test_range_diff() {
+    let test_repo = TestRepo::new();
+    let base = test_repo.commit("base");
+    test_repo.commit("first");
+    test_repo.commit_file("second", "second", "v1");
+    test_repo.commit("third");
+    let v1 = test_repo.commit("dropped");
+
+    test_repo.reset(&base);
+    test_repo.commit("first");
+    test_repo.commit("third");
+    test_repo.commit_file("second", "second", "v2");
+    let v2 = test_repo.commit("added");
+
+    let repo = test_repo.open();
+    let entries = range_diff(&repo, (&id(&base), &id(&v1)), (&id(&base), &id(&v2))).unwrap();
+    let summary: Vec<EntrySummary> = entries
+        .iter()
+        .map(|e| (e.subject(), e.status().clone(), e.old_pos(), e.new_pos(), e.reordered()))
+        .collect();
+    assert_eq!(
+        summary,
+        vec![
+            ("first", RangeDiffStatus::Unchanged, Some(1), Some(1), false),
+            ("third", RangeDiffStatus::Unchanged, Some(3), Some(2), false),
+            ("second", RangeDiffStatus::Modified, Some(2), Some(3), true),
+            ("dropped", RangeDiffStatus::Dropped, Some(4), None, false),
+            ("added", RangeDiffStatus::Added, None, Some(4), false),
+        ]
+    );
+}
+
+#[test]
+fn test_range_diff_same_range() {
+    let test_repo = TestRepo::new();
+    let base = test_repo.commit("base");
+    test_repo.commit("first");
+    let last = test_repo.commit("second");
+
+    let repo = test_repo.open();
+    let entries = range_diff(&repo, (&id(&base), &id(&last)), (&id(&base), &id(&last))).unwrap();
+    assert_eq!(entries.len(), 2);
+    assert!(entries.iter().all(|e| *e.status() == RangeDiffStatus::Unchanged && !e.reordered()));
+}
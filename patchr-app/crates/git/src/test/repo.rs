@@ -0,0 +1,76 @@
+use crate::repo::{MergeConflict, Repo};
+
+fn short_names(repo: &Repo) -> Vec<(&str, &str)> {
+    repo.series()
+        .iter()
+        .map(|s| (s.name(), s.short_name()))
+        .collect()
+}
+
+fn conflict_names(conflicts: &[MergeConflict]) -> Vec<(&str, Option<&str>)> {
+    conflicts.iter().map(|c| (c.name(), c.copy())).collect()
+}
+
+fn set_short_name(repo: &mut Repo, name: &str, short_name: &str) {
+    repo.get_series_by_name_mut(name)
+        .unwrap()
+        .set_short_name(short_name)
+        .unwrap();
+}
+
+#[test]
+fn test_merge() {
+    let mut base = Repo::new();
+    for name in ["kept", "ours", "theirs", "both", "deleted"] {
+        base.add_series(name, "Title", None, None, None, None)
+            .unwrap();
+    }
+    let base_json = serde_json::to_string(&base).unwrap();
+    let mut ours: Repo = serde_json::from_str(&base_json).unwrap();
+    let mut theirs: Repo = serde_json::from_str(&base_json).unwrap();
+
+    set_short_name(&mut ours, "ours", "o");
+    set_short_name(&mut theirs, "theirs", "t");
+    set_short_name(&mut ours, "both", "o");
+    set_short_name(&mut theirs, "both", "t");
+    theirs.delete_series("deleted").unwrap();
+    ours.add_series("new-ours", "Title", None, None, None, None)
+        .unwrap();
+    theirs
+        .add_series("new-theirs", "Title", None, None, None, None)
+        .unwrap();
+
+    let (merged, conflicts) = Repo::merge(Some(&base), &ours, &theirs).unwrap();
+    assert_eq!(
+        short_names(&merged),
+        vec![
+            ("kept", ""),
+            ("ours", "o"),
+            ("theirs", "t"),
+            ("both", "o"),
+            ("new-ours", ""),
+            ("new-theirs", ""),
+            ("both-theirs", "t")
+        ]
+    );
+    assert_eq!(conflict_names(&conflicts), vec![("both", Some("both-theirs"))]);
+
+    // Without a common version, the series differing are conflicts
+    let (merged, conflicts) = Repo::merge(None, &ours, &theirs).unwrap();
+    assert_eq!(merged.series().len(), 10);
+    assert_eq!(
+        conflict_names(&conflicts),
+        vec![
+            ("ours", Some("ours-theirs")),
+            ("theirs", Some("theirs-theirs")),
+            ("both", Some("both-theirs"))
+        ]
+    );
+
+    // The copies do not replace an existing series
+    ours.add_series("both-theirs", "Title", None, None, None, None)
+        .unwrap();
+    let (merged, conflicts) = Repo::merge(Some(&base), &ours, &theirs).unwrap();
+    assert_eq!(conflict_names(&conflicts), vec![("both", Some("both-theirs-2"))]);
+    assert_eq!(merged.get_series_by_name("both-theirs-2").unwrap().short_name(), "t");
+}
@@ -0,0 +1,128 @@
+use std::{fs, os::unix::fs::PermissionsExt, path::Path};
+
+use crate::{
+    patch_sender::{delivered_mails, PatchSender, SendParams},
+    sendmail_sender::SendmailPatchSender,
+    series::Series,
+};
+
+use super::util::{write_mails, TestDir};
+
+// Script appending its arguments and the mail to files of the out dir
+fn write_fake_sendmail(path: &Path, out: &Path, exit_code: i32) {
+    fs::write(
+        path,
+        format!(
+            "#!/bin/sh\n\
+             echo \"$@\" >> {out}/args\n\
+             cat >> {out}/stdin\n\
+             echo \"--\" >> {out}/stdin\n\
+             echo \"warning from sendmail\" >&2\n\
+             exit {exit_code}\n",
+            out = out.display(),
+            exit_code = exit_code
+        ),
+    )
+    .unwrap();
+    fs::set_permissions(path, fs::Permissions::from_mode(0o755)).unwrap();
+}
+
+#[test]
+fn test_sendmail_sender() {
+    let dir = TestDir::new();
+    let mails_dir = dir.join("mails");
+    write_mails(&mails_dir);
+    let command = dir.join("sendmail");
+    write_fake_sendmail(&command, &dir, 0);
+
+    let series = Series::new("name", "title").unwrap();
+    let params = SendParams::builder(&series, "list@patchr.com", &mails_dir, "a", "b").build();
+    let command = command.to_string_lossy();
+    let sent =
+        SendmailPatchSender::new("test@patchr.com", &command).send_formatted(&params, &mails_dir);
+    let args = fs::read_to_string(dir.join("args")).unwrap();
+    let mails = fs::read_to_string(dir.join("stdin")).unwrap();
+
+    let sent = sent.unwrap();
+    assert_eq!(sent.cover_letter_id(), Some("<cover@patchr.com>"));
+    assert_eq!(
+        args,
+        "-i -f test@patchr.com list@patchr.com john@patchr.com jane@patchr.com\n\
+         -i -f test@patchr.com list@patchr.com\n"
+    );
+    let mails: Vec<&str> = mails.split("--\n").collect();
+    assert!(mails[0].starts_with("From: test@patchr.com\nDate: "));
+    assert!(mails[0].ends_with("\n\nblurb\n"));
+    assert!(mails[1].contains("\n\nFrom: Author <author@patchr.com>\n\n.diff\n"));
+
+    let log = format!("{:?}", series);
+    assert!(log
+        .contains("0000-cover-letter.patch delivered, exit code 0, stderr: warning from sendmail"));
+    assert!(log.contains("0001-a.patch delivered, exit code 0"));
+}
+
+#[test]
+fn test_sendmail_sender_failure() {
+    let dir = TestDir::new();
+    let mails_dir = dir.join("mails");
+    write_mails(&mails_dir);
+    let command = dir.join("sendmail");
+    write_fake_sendmail(&command, &dir, 75);
+
+    let series = Series::new("name", "title").unwrap();
+    let params = SendParams::builder(&series, "list@patchr.com", &mails_dir, "a", "b").build();
+    let command = command.to_string_lossy();
+    let sent =
+        SendmailPatchSender::new("test@patchr.com", &command).send_formatted(&params, &mails_dir);
+    let args = fs::read_to_string(dir.join("args")).unwrap();
+
+    // the first failure stops the delivery
+    assert!(sent
+        .err()
+        .unwrap()
+        .to_string()
+        .contains("warning from sendmail"));
+    assert_eq!(args.lines().count(), 1);
+    let log = format!("{:?}", series);
+    assert!(log.contains("0000-cover-letter.patch failed, exit code 75"));
+    assert!(!log.contains("0001-a.patch"));
+}
+
+#[test]
+fn test_sendmail_sender_resume() {
+    let dir = TestDir::new();
+    let mails_dir = dir.join("mails");
+    write_mails(&mails_dir);
+    let command = dir.join("sendmail");
+    // the second mail fails
+    fs::write(
+        &command,
+        format!(
+            "#!/bin/sh\n\
+             echo \"$@\" >> {out}/args\n\
+             cat > /dev/null\n\
+             [ \"$(wc -l < {out}/args)\" -lt 2 ]\n",
+            out = dir.display()
+        ),
+    )
+    .unwrap();
+    fs::set_permissions(&command, fs::Permissions::from_mode(0o755)).unwrap();
+
+    let series = Series::new("name", "title").unwrap();
+    let params = SendParams::builder(&series, "list@patchr.com", &mails_dir, "a", "b").build();
+    let command = command.to_string_lossy();
+    let sender = SendmailPatchSender::new("test@patchr.com", &command);
+    let first = sender.send_formatted(&params, &mails_dir);
+    let delivered_after_failure = delivered_mails(&mails_dir).unwrap();
+    // the next attempt only sends the remaining mail
+    fs::write(dir.join("args"), "").unwrap();
+    let second = sender.send_formatted(&params, &mails_dir);
+    let args = fs::read_to_string(dir.join("args")).unwrap();
+    let delivered = delivered_mails(&mails_dir).unwrap();
+
+    assert!(first.is_err());
+    assert_eq!(delivered_after_failure, ["0000-cover-letter.patch"]);
+    assert_eq!(second.unwrap().patch_ids(), [String::from("<p1@patchr.com>")]);
+    assert_eq!(args, "-i -f test@patchr.com list@patchr.com\n");
+    assert_eq!(delivered, ["0000-cover-letter.patch", "0001-a.patch"]);
+}
@@ -1,4 +1,11 @@
-use crate::series::Series;
+use std::fs;
+
+use crate::{
+    patch_sender::SentMessages,
+    series::{RevisionRange, SendRecord, Series},
+};
+
+use super::util::TestDir;
 
 #[test]
 fn test_series_creation() {
@@ -123,6 +130,34 @@ fn test_series_short_name() {
     // TODO: add more test
 }
 
+#[test]
+fn test_series_subject_prefix() {
+    let mut series = Series::new("name", "title").unwrap();
+    let prefix = |s: &Series, tag, rfc, resend| s.subject_prefix(tag, rfc, resend).unwrap();
+
+    assert_eq!(series.subject_tag(), "PATCH");
+    assert_eq!(prefix(&series, None, false, false), "PATCH v1");
+    assert_eq!(prefix(&series, None, true, true), "RFC PATCH RESEND v1");
+
+    series.set_drop_v1(true);
+    assert_eq!(prefix(&series, None, false, false), "PATCH");
+    series.set_short_name("short").unwrap();
+    series.add_revision();
+    assert_eq!(prefix(&series, None, false, false), "PATCH short v2");
+
+    assert!(series.set_subject_tag("  PATCH   net-next ").is_ok());
+    assert_eq!(series.subject_tag(), "PATCH net-next");
+    assert_eq!(prefix(&series, None, true, false), "RFC PATCH net-next short v2");
+    assert_eq!(prefix(&series, Some("PATCH drm/i915"), false, false), "PATCH drm/i915 short v2");
+
+    assert!(series.set_subject_tag("[PATCH]").is_err());
+    assert!(series
+        .subject_prefix(Some("PATCH\nBcc: x"), false, false)
+        .is_err());
+    assert!(series.set_subject_tag("").is_ok());
+    assert_eq!(series.subject_tag(), "PATCH");
+}
+
 #[test]
 fn test_series_title() {
     let mut series = Series::new("name", "title").unwrap();
@@ -154,4 +189,79 @@ fn test_series_cover_letter() {
 
     assert!(series.set_cover_letter("").is_ok());
     assert_eq!(series.cover_letter(), "");
-}
\ No newline at end of file
+}
+#[test]
+fn test_series_ranges() {
+    let mut series = Series::new("name", "title").unwrap();
+    assert!(series.current_range().is_none());
+    assert!(series.revision(0).is_none());
+    assert!(series.revision(1).unwrap().range().is_none());
+
+    let v1 = RevisionRange::new("a", "b", "c", Some("topic"));
+    series.set_current_range(v1.clone());
+    assert_eq!(series.current_range(), Some(&v1));
+    assert_eq!(series.revision(1).unwrap().range(), Some(&v1));
+
+    // A new revision does not inherit the range of the previous one
+    series.add_revision();
+    assert!(series.current_range().is_none());
+    let v2 = RevisionRange::new("d", "e", "f", None);
+    series.set_current_range(v2.clone());
+    assert_eq!(series.revision(1).unwrap().range(), Some(&v1));
+    assert_eq!(series.revision(2).unwrap().range(), Some(&v2));
+    assert!(series.revision(3).is_none());
+
+    series.delete_revision(2);
+    assert_eq!(series.current_range(), Some(&v1));
+}
+
+#[test]
+fn test_series_message_ids() {
+    let mut series = Series::new("name", "title").unwrap();
+    assert!(series.previous_cover_letter_id().is_none());
+    assert!(series.thread_revisions().is_none());
+
+    let patch_ids = vec![String::from("<p1>"), String::from("<p2>")];
+    series.set_current_message_ids(Some("<v1>"), patch_ids.as_slice());
+    assert!(series.previous_cover_letter_id().is_none());
+    assert_eq!(series.revision(1).unwrap().patch_ids(), patch_ids.as_slice());
+
+    series.add_revision();
+    assert_eq!(series.previous_cover_letter_id(), Some("<v1>"));
+    series.set_current_message_ids(Some("<v2>"), &[]);
+    series.add_revision();
+    assert_eq!(series.previous_cover_letter_id(), Some("<v2>"));
+    series.delete_revision(2);
+    assert_eq!(series.previous_cover_letter_id(), Some("<v1>"));
+}
+
+#[test]
+fn test_series_send_records() {
+    let dir = TestDir::new();
+    fs::write(dir.join("0000-cover-letter.patch"), "Message-Id: <c>\nSubject: [PATCH 0/1] t\n\n")
+        .unwrap();
+    fs::write(dir.join("0001-a.patch"), "Message-Id: <p1>\nSubject: [PATCH 1/1] a\n\n").unwrap();
+    let sent = SentMessages::from_patch_dir(&dir).unwrap();
+
+    let mut series = Series::new("name", "title").unwrap();
+    assert!(series.sends().is_empty());
+    let range = RevisionRange::new("base", "first", "last", None);
+    series.add_send_record(SendRecord::new(
+        1,
+        "me@a.b",
+        "list@a.b",
+        Some("cc@a.b"),
+        range,
+        "PATCH v1",
+        &sent,
+    ));
+
+    let record = &series.sends()[0];
+    assert_eq!(record.revision(), 1);
+    assert_eq!(record.cc(), Some("cc@a.b"));
+    assert_eq!(record.cover_letter_id(), Some("<c>"));
+    assert_eq!(record.patch_ids(), &[String::from("<p1>")]);
+    assert_eq!(record.subjects(), &[String::from("[PATCH 0/1] t"), String::from("[PATCH 1/1] a")]);
+    assert_eq!(record.range().last_commit(), "last");
+    assert_eq!(record.subject_prefix(), "PATCH v1");
+}
@@ -0,0 +1,126 @@
+use std::{
+    io::{BufRead, BufReader, Write},
+    net::{TcpListener, TcpStream},
+    thread::{self, JoinHandle},
+};
+
+use crate::{
+    patch_sender::{PatchSender, SendParams},
+    series::Series,
+    smtp_sender::SmtpPatchSender,
+};
+
+use super::util::{write_mails, TestDir};
+
+// Commands received by the fake server and the content of the messages
+#[derive(Default)]
+struct Transcript {
+    commands: Vec<String>,
+    messages: Vec<String>,
+}
+
+// Minimal SMTP server accepting `connections` connections, lettre
+// opens one for every message when the pool is disabled
+fn start_fake_server(connections: usize) -> (u16, JoinHandle<Transcript>) {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let port = listener.local_addr().unwrap().port();
+    let handle = thread::spawn(move || {
+        let mut transcript = Transcript::default();
+        for _ in 0..connections {
+            let (stream, _) = listener.accept().unwrap();
+            handle_connection(stream, &mut transcript);
+        }
+        transcript
+    });
+    (port, handle)
+}
+
+fn handle_connection(mut stream: TcpStream, transcript: &mut Transcript) {
+    let mut reader = BufReader::new(stream.try_clone().unwrap());
+    stream.write_all(b"220 fake ESMTP\r\n").unwrap();
+    let mut line = String::new();
+    while reader.read_line(&mut line).unwrap() > 0 {
+        let command = String::from(line.trim_end());
+        line.clear();
+        let reply: &[u8] = match command.split(' ').next().unwrap().to_uppercase().as_str() {
+            "EHLO" => b"250-fake\r\n250 AUTH PLAIN\r\n",
+            "AUTH" => b"235 ok\r\n",
+            "DATA" => {
+                stream.write_all(b"354 go ahead\r\n").unwrap();
+                let mut message = String::new();
+                loop {
+                    reader.read_line(&mut line).unwrap();
+                    if line == ".\r\n" {
+                        break;
+                    }
+                    message.push_str(&line);
+                    line.clear();
+                }
+                line.clear();
+                transcript.messages.push(message);
+                b"250 queued\r\n"
+            }
+            "QUIT" => {
+                stream.write_all(b"221 bye\r\n").unwrap();
+                transcript.commands.push(command);
+                return;
+            }
+            _ => b"250 ok\r\n",
+        };
+        transcript.commands.push(command);
+        stream.write_all(reply).unwrap();
+    }
+}
+
+#[test]
+fn test_smtp_sender() {
+    let dir = TestDir::new();
+    write_mails(&dir);
+    let (port, server) = start_fake_server(2);
+
+    let series = Series::new("name", "title").unwrap();
+    let params = SendParams::builder(&series, "list@patchr.com", &dir, "a", "b").build();
+    let mut builder = SmtpPatchSender::builder("test@patchr.com", "127.0.0.1");
+    builder.set_port(port);
+    builder.set_credentials("user", "password");
+    let sent = builder.build().send_formatted(&params, &dir);
+    let sent = sent.unwrap();
+    assert_eq!(sent.cover_letter_id(), Some("<cover@patchr.com>"));
+    assert_eq!(sent.patch_ids(), [String::from("<p1@patchr.com>")]);
+
+    let transcript = server.join().unwrap();
+    let envelope: Vec<_> = transcript
+        .commands
+        .iter()
+        .filter(|c| c.starts_with("MAIL") || c.starts_with("RCPT"))
+        .map(String::as_str)
+        .collect();
+    assert_eq!(
+        envelope,
+        [
+            "MAIL FROM:<test@patchr.com>",
+            "RCPT TO:<list@patchr.com>",
+            "RCPT TO:<john@patchr.com>",
+            "RCPT TO:<jane@patchr.com>",
+            "MAIL FROM:<test@patchr.com>",
+            "RCPT TO:<list@patchr.com>",
+        ]
+    );
+    assert_eq!(
+        transcript
+            .commands
+            .iter()
+            .filter(|c| c.starts_with("AUTH PLAIN"))
+            .count(),
+        2
+    );
+
+    // the sender replaces the author, who is kept in the body
+    let cover = &transcript.messages[0];
+    assert!(cover.starts_with("From: test@patchr.com\r\nDate: "));
+    assert!(!cover.contains("1970"));
+    assert!(cover.contains("\r\nSubject: [PATCH v1 0/1] Title\r\n"));
+    assert!(cover.ends_with("\r\n\r\nblurb\r\n"));
+    let patch = &transcript.messages[1];
+    assert!(patch.contains("\r\n\r\nFrom: Author <author@patchr.com>\r\n\r\n..diff\r\n"));
+}
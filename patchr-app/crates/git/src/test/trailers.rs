@@ -0,0 +1,142 @@
+use std::fs;
+
+use crate::{
+    trailers::{add_trailer, collect_trailers, read_mailbox, remove_trailers, TrailerOrder},
+    util::CommitTag,
+};
+
+use super::util::TestDir;
+
+// Replies to v2 of a series of two patches, along with the cover letter
+// and a reply to v1
+const MBOX: &str = "From 0 Mon Sep 17 00:00:00 2001\n\
+Subject: [PATCH v2 0/2] Title\n\
+Message-Id: <cover@patchr.com>\n\
+\n\
+Reviewed-by: Not A Reply <no@patchr.com>\n\
+\n\
+From john Mon Sep 17 00:00:00 2001\n\
+Subject: Re: [PATCH v2 0/2] Title\n\
+In-Reply-To: <cover@patchr.com>\n\
+\n\
+> Reviewed-by: Quoted <quoted@patchr.com>\n\
+Acked-by: John <john@patchr.com>\n\
+\n\
+From jane Mon Sep 17 00:00:00 2001\n\
+Subject: Re: [PATCH v2 2/2] Second\n\
+In-Reply-To: <p2@patchr.com>\n\
+\n\
+reviewed-by:  Jane <jane@patchr.com> \n\
+>From the test results\n\
+Tested-by: Jane <jane@patchr.com>\n\
+\n\
+From bob Mon Sep 17 00:00:00 2001\n\
+Subject: RE: [PATCH v2 1/2] First\n\
+In-Reply-To: <reply@patchr.com>\n\
+\n\
+Acked-by: John <john@patchr.com>\n\
+Reviewed-by: Bob <bob@patchr.com>\n\
+-- \n\
+Tested-by: Signature <sig@patchr.com>\n\
+\n\
+From old Mon Sep 17 00:00:00 2001\n\
+Subject: Re: [PATCH 1/2] First\n\
+\n\
+Reviewed-by: Old <old@patchr.com>\n";
+
+#[test]
+fn test_collect_trailers() {
+    let dir = TestDir::new();
+    let path = dir.join("replies.mbox");
+    fs::write(&path, MBOX).unwrap();
+    let mails = read_mailbox(&path).unwrap();
+    assert_eq!(mails.len(), 5);
+    assert!(mails[2].contains("\nFrom the test results\n"));
+
+    let patch_ids = [
+        String::from("<p1@patchr.com>"),
+        String::from("<p2@patchr.com>"),
+    ];
+    let trailers = collect_trailers(&mails, 2, Some("<cover@patchr.com>"), &patch_ids);
+    let acked = (
+        CommitTag::Custom(String::from("Acked-by")),
+        String::from("John <john@patchr.com>"),
+    );
+    let reviewed = |v: &str| (CommitTag::ReviewedBy, String::from(v));
+    assert!(trailers[0] == [acked.clone(), reviewed("Bob <bob@patchr.com>")]);
+    let tested = (
+        CommitTag::Custom(String::from("Tested-by")),
+        String::from("Jane <jane@patchr.com>"),
+    );
+    assert!(trailers[1] == [acked, reviewed("Jane <jane@patchr.com>"), tested]);
+
+    // v1 was sent without a version in the subjects
+    let v1_ids = [
+        String::from("<v1p1@patchr.com>"),
+        String::from("<v1p2@patchr.com>"),
+    ];
+    let trailers = collect_trailers(&mails, 1, None, &v1_ids);
+    assert!(trailers[0] == [reviewed("Old <old@patchr.com>")]);
+    assert!(trailers[1].is_empty());
+}
+
+#[test]
+fn test_add_trailer() {
+    let end = TrailerOrder::default();
+    // A blank line separates the new block from the body
+    assert_eq!(
+        add_trailer("Subject\n\nBody\n", "Acked-by", "A", &end).unwrap(),
+        "Subject\n\nBody\n\nAcked-by: A\n"
+    );
+    assert_eq!(
+        add_trailer("Subject\n", "Acked-by", "A", &end).unwrap(),
+        "Subject\n\nAcked-by: A\n"
+    );
+    // The subject is never a trailer block
+    assert_eq!(
+        add_trailer("Fix: crash", "Acked-by", "A", &end).unwrap(),
+        "Fix: crash\n\nAcked-by: A\n"
+    );
+
+    let message = "Subject\n\nBody\n\nFixes: 123\nSigned-off-by: Me\n";
+    assert_eq!(
+        add_trailer(message, "Acked-by", "A", &end).unwrap(),
+        "Subject\n\nBody\n\nFixes: 123\nSigned-off-by: Me\nAcked-by: A\n"
+    );
+    let order = TrailerOrder::parse("Fixes, *, signed-off-by").unwrap();
+    assert_eq!(
+        add_trailer(message, "Acked-by", "A", &order).unwrap(),
+        "Subject\n\nBody\n\nFixes: 123\nAcked-by: A\nSigned-off-by: Me\n"
+    );
+    let order = TrailerOrder::parse("Signed-off-by").unwrap();
+    assert_eq!(
+        add_trailer(message, "Acked-by", "A", &order).unwrap(),
+        "Subject\n\nBody\n\nFixes: 123\nSigned-off-by: Me\nAcked-by: A\n"
+    );
+    assert!(add_trailer(message, "signed-off-by", "Me", &end).is_none());
+
+    // Enough trailers along with a git generated one
+    let message = "Subject\n\nSigned-off-by: Me\n[fixed a typo]\nAcked-by: B\n";
+    assert_eq!(
+        add_trailer(message, "Tested-by", "C", &end).unwrap(),
+        format!("{}Tested-by: C\n", message)
+    );
+    assert!(TrailerOrder::parse("Fixes,*,*").is_none());
+    assert!(TrailerOrder::parse("Bad tag").is_none());
+}
+
+#[test]
+fn test_remove_trailers() {
+    let message = "Subject\n\nBody\nAcked-by: body line\n\nAcked-by: A\n  continued\nacked-by: B\nSigned-off-by: Me\n";
+    assert_eq!(
+        remove_trailers(message, "Acked-by", Some("B")).unwrap(),
+        "Subject\n\nBody\nAcked-by: body line\n\nAcked-by: A\n  continued\nSigned-off-by: Me\n"
+    );
+    let message = remove_trailers(message, "ACKED-BY", None).unwrap();
+    assert_eq!(message, "Subject\n\nBody\nAcked-by: body line\n\nSigned-off-by: Me\n");
+    assert_eq!(
+        remove_trailers(&message, "Signed-off-by", None).unwrap(),
+        "Subject\n\nBody\nAcked-by: body line\n"
+    );
+    assert!(remove_trailers(&message, "Tested-by", None).is_none());
+}
@@ -0,0 +1,449 @@
+use std::{
+    env, fs,
+    ops::Deref,
+    path::{Path, PathBuf},
+};
+
+use uuid::Uuid;
+
+use crate::{
+    util::{CommitId, GitRepo, BACKUP_REF_PREFIX, DATA_REF},
+    GitError, GitErrorCode,
+};
+
+// Temporary directory, deleted when dropped
+pub struct TestDir {
+    path: PathBuf,
+}
+
+impl TestDir {
+    pub fn new() -> Self {
+        let path = env::temp_dir().join(format!("patchr-test-{}", Uuid::new_v4()));
+        fs::create_dir_all(&path).unwrap();
+        Self { path }
+    }
+}
+
+impl Deref for TestDir {
+    type Target = Path;
+
+    fn deref(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl Drop for TestDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.path);
+    }
+}
+
+// Temporary repo, deleted when dropped
+pub struct TestRepo {
+    path: TestDir,
+    repo: git2::Repository,
+}
+
+impl TestRepo {
+    pub fn new() -> Self {
+        let path = TestDir::new();
+        let repo = git2::Repository::init(&*path).unwrap();
+        let mut config = repo.config().unwrap();
+        config.set_str("user.name", "Patchr").unwrap();
+        config.set_str("user.email", "test@patchr.com").unwrap();
+        Self { path, repo }
+    }
+
+    pub fn path(&self) -> &str {
+        self.path.to_str().unwrap()
+    }
+
+    pub fn open(&self) -> GitRepo {
+        GitRepo::open(self.path()).unwrap()
+    }
+
+    // Commit a file named after the message on top of HEAD
+    pub fn commit(&self, message: &str) -> String {
+        self.commit_file(message, message.replace(' ', "_").as_str(), message)
+    }
+
+    pub fn commit_file(&self, message: &str, file_name: &str, content: &str) -> String {
+        fs::write(self.path.join(file_name), content).unwrap();
+        let mut index = self.repo.index().unwrap();
+        index.add_path(file_name.as_ref()).unwrap();
+        index.write().unwrap();
+        let tree = self.repo.find_tree(index.write_tree().unwrap()).unwrap();
+        let sig = self.repo.signature().unwrap();
+        let parent = self.repo.head().ok().map(|h| h.peel_to_commit().unwrap());
+        let parents: Vec<&git2::Commit> = parent.iter().collect();
+        self.repo
+            .commit(Some("HEAD"), &sig, &sig, message, &tree, parents.as_slice())
+            .unwrap()
+            .to_string()
+    }
+
+    // Move HEAD (and the current branch) to the given commit
+    pub fn reset(&self, commit: &str) {
+        let commit = self
+            .repo
+            .find_commit(git2::Oid::from_str(commit).unwrap())
+            .unwrap();
+        self.repo
+            .reset(commit.as_object(), git2::ResetType::Hard, None)
+            .unwrap();
+    }
+}
+
+// A cover letter and a patch as written by format-patch, the patch
+// author differs from the sender
+pub fn write_mails(dir: &Path) {
+    fs::create_dir_all(dir).unwrap();
+    fs::write(
+        dir.join("0000-cover-letter.patch"),
+        "From 0 Mon Sep 17 00:00:00 2001\n\
+         From: Patchr <test@patchr.com>\n\
+         Date: Thu, 1 Jan 1970 00:00:00 +0000\n\
+         Subject: [PATCH v1 0/1] Title\n\
+         Message-Id: <cover@patchr.com>\n\
+         To: list@patchr.com\n\
+         Cc: \"Doe, John\" <john@patchr.com>,\n \
+         jane@patchr.com\n\
+         \n\
+         blurb\n",
+    )
+    .unwrap();
+    fs::write(
+        dir.join("0001-a.patch"),
+        "From 1 Mon Sep 17 00:00:00 2001\n\
+         From: Author <author@patchr.com>\n\
+         Subject: [PATCH v1 1/1] a\n\
+         Message-Id: <p1@patchr.com>\n\
+         To: list@patchr.com\n\
+         \n\
+         .diff\n",
+    )
+    .unwrap();
+}
+
+#[test]
+fn test_commit_range() {
+    let test_repo = TestRepo::new();
+    let base = test_repo.commit("base");
+    let first = test_repo.commit("first");
+    let second = test_repo.commit("second");
+    let last = test_repo.commit("last");
+
+    let repo = test_repo.open();
+    let ids = |from: &str, to: &str| -> Vec<String> {
+        repo.commit_range(&CommitId::new(from).unwrap(), &CommitId::new(to).unwrap())
+            .unwrap()
+            .iter()
+            .map(|c| c.id().to_string())
+            .collect()
+    };
+
+    assert_eq!(ids(&base, &last), vec![first.clone(), second.clone(), last.clone()]);
+    assert_eq!(ids(&first, &second), vec![second.clone()]);
+    assert!(ids(&last, &last).is_empty());
+    assert!(ids(&last, &base).is_empty());
+}
+
+#[test]
+fn test_current_branch() {
+    let test_repo = TestRepo::new();
+    test_repo.commit("base");
+    let repo = test_repo.open();
+    let branch = repo.current_branch().unwrap();
+    assert!(branch == "master" || branch == "main");
+}
+
+#[test]
+fn test_reword_commits() {
+    let test_repo = TestRepo::new();
+    let base = test_repo.commit("base");
+    let first = test_repo.commit("first");
+    test_repo.commit("second");
+    let third = test_repo.commit("third");
+
+    let repo = test_repo.open();
+    let messages = [
+        (CommitId::new(&third).unwrap(), String::from("third\n\nAcked-by: Me")),
+        (CommitId::new(&first).unwrap(), String::from("first\n\nAcked-by: Me")),
+    ];
+    let rewritten = repo.reword_commits(&messages).unwrap();
+    let new_id = |old: &str| {
+        let (_, new) = rewritten
+            .iter()
+            .find(|(o, _)| o.to_string() == old)
+            .unwrap();
+        new.to_string()
+    };
+    assert_eq!(rewritten.len(), 3);
+    let head = test_repo.repo.head().unwrap().peel_to_commit().unwrap();
+    assert_eq!(new_id(&third), head.id().to_string());
+
+    let commits = repo
+        .commit_range(&CommitId::new(&base).unwrap(), &CommitId::new(&new_id(&third)).unwrap())
+        .unwrap();
+    let messages: Vec<_> = commits.iter().map(|c| c.message().unwrap()).collect();
+    assert_eq!(messages, ["first\n\nAcked-by: Me", "second", "third\n\nAcked-by: Me"]);
+    assert_eq!(commits[0].id().to_string(), new_id(&first));
+}
+
+#[test]
+fn test_rewrite_safety_and_undo() {
+    let test_repo = TestRepo::new();
+    test_repo.commit("base");
+    let first = test_repo.commit("first");
+    let second = test_repo.commit("second");
+    let repo = test_repo.open();
+    let reword = |commit: &str| {
+        repo.reword_commits(&[(CommitId::new(commit).unwrap(), String::from("reworded"))])
+    };
+    let head = || test_repo.repo.head().unwrap().target().unwrap().to_string();
+    let refused = |res: Result<_, GitError>| {
+        matches!(
+            res,
+            Err(GitError {
+                code: GitErrorCode::UnsafeRewrite,
+                ..
+            })
+        )
+    };
+
+    // Uncommitted changes
+    fs::write(Path::new(test_repo.path()).join("first"), "changed").unwrap();
+    assert!(refused(reword(&first)));
+    fs::write(Path::new(test_repo.path()).join("first"), "first").unwrap();
+
+    // Not an ancestor of HEAD
+    test_repo.reset(&first);
+    assert!(refused(reword(&second)));
+    test_repo.reset(&second);
+
+    // Detached HEAD
+    let branch = String::from(test_repo.repo.head().unwrap().name().unwrap());
+    test_repo
+        .repo
+        .set_head_detached(git2::Oid::from_str(&second).unwrap())
+        .unwrap();
+    assert!(refused(reword(&first)));
+    test_repo.repo.set_head(&branch).unwrap();
+
+    reword(&first).unwrap();
+    assert_ne!(head(), second);
+    assert_eq!(repo.undo_rewrite().unwrap().to_string(), second);
+    assert_eq!(head(), second);
+    assert!(matches!(
+        repo.undo_rewrite(),
+        Err(GitError {
+            code: GitErrorCode::NothingToUndo,
+            ..
+        })
+    ));
+
+    // Work done after the rewrite is never discarded
+    reword(&first).unwrap();
+    test_repo.commit("third");
+    assert!(matches!(
+        repo.undo_rewrite(),
+        Err(GitError {
+            code: GitErrorCode::UnsafeRewrite,
+            ..
+        })
+    ));
+}
+
+#[test]
+fn test_undo_rewritten_branch() {
+    let test_repo = TestRepo::new();
+    test_repo.commit("base");
+    test_repo.commit("first");
+    let repo = test_repo.open();
+    let branch = String::from(test_repo.repo.head().unwrap().name().unwrap());
+    let backups = || {
+        test_repo
+            .repo
+            .references_glob(format!("{}*", BACKUP_REF_PREFIX).as_str())
+            .unwrap()
+            .count()
+    };
+    for i in 0..12 {
+        let head = test_repo.repo.head().unwrap().target().unwrap().to_string();
+        repo.reword_commits(&[(CommitId::new(&head).unwrap(), format!("first {}", i))])
+            .unwrap();
+    }
+    assert_eq!(backups(), 10);
+
+    // Same tree on another branch
+    let head = test_repo.repo.head().unwrap().peel_to_commit().unwrap();
+    test_repo.repo.branch("other", &head, false).unwrap();
+    test_repo.repo.set_head("refs/heads/other").unwrap();
+    assert!(matches!(
+        repo.undo_rewrite(),
+        Err(GitError {
+            code: GitErrorCode::UnsafeRewrite,
+            ..
+        })
+    ));
+    test_repo.repo.set_head(&branch).unwrap();
+    repo.undo_rewrite().unwrap();
+    assert_eq!(backups(), 9);
+    let head = test_repo.repo.head().unwrap().peel_to_commit().unwrap();
+    assert_eq!(head.message(), Some("first 10"));
+}
+
+#[test]
+fn test_reword_root_and_merge() {
+    let test_repo = TestRepo::new();
+    let base = test_repo.commit("base");
+    let side = test_repo.commit("side");
+    test_repo.reset(&base);
+    let main = test_repo.commit("main");
+
+    // Merge side, keeping the tree of main
+    let find = |id: &str| {
+        test_repo
+            .repo
+            .find_commit(git2::Oid::from_str(id).unwrap())
+            .unwrap()
+    };
+    let sig = test_repo.repo.signature().unwrap();
+    let (main_commit, side_commit) = (find(&main), find(&side));
+    test_repo
+        .repo
+        .commit(
+            Some("HEAD"),
+            &sig,
+            &sig,
+            "merge",
+            &main_commit.tree().unwrap(),
+            &[&main_commit, &side_commit],
+        )
+        .unwrap();
+    test_repo.commit("top");
+    let old_tree = test_repo.repo.head().unwrap().peel_to_tree().unwrap().id();
+
+    let repo = test_repo.open();
+    let rewritten = repo
+        .reword_commits(&[(CommitId::new(&base).unwrap(), String::from("root"))])
+        .unwrap();
+    assert_eq!(rewritten.len(), 5);
+
+    let head = test_repo.repo.head().unwrap().peel_to_commit().unwrap();
+    assert_eq!(head.tree_id(), old_tree);
+    let merge = head.parent(0).unwrap();
+    assert_eq!(merge.parent_count(), 2);
+    let new_side = merge.parent(1).unwrap();
+    assert_eq!(new_side.message(), Some("side"));
+    assert_ne!(new_side.id().to_string(), side);
+    let root = new_side.parent(0).unwrap();
+    assert_eq!(root.message(), Some("root"));
+    assert_eq!(root.parent_count(), 0);
+    assert_eq!(merge.parent(0).unwrap().parent(0).unwrap().id(), root.id());
+}
+
+#[test]
+fn test_fixes_reference() {
+    let test_repo = TestRepo::new();
+    let culprit = test_repo.commit("Culprit");
+    let fix = test_repo.commit("Fix");
+    let repo = test_repo.open();
+
+    let commit = repo.revparse_commit("HEAD~1").unwrap();
+    assert_eq!(commit.id().to_string(), culprit);
+    assert_eq!(commit.fixes_reference(12), format!("{} (\"Culprit\")", &culprit[..12]));
+    assert!(repo.revparse_commit(&culprit[..8]).is_ok());
+    assert_eq!(repo.revparse("HEAD").unwrap().to_string(), fix);
+    assert!(matches!(
+        repo.revparse_commit("unknown"),
+        Err(GitError {
+            code: GitErrorCode::CommitNotFound,
+            ..
+        })
+    ));
+
+    let (culprit, fix) = (CommitId::new(&culprit).unwrap(), CommitId::new(&fix).unwrap());
+    assert!(repo.is_ancestor(&culprit, &fix).unwrap());
+    assert!(!repo.is_ancestor(&fix, &culprit).unwrap());
+    assert!(!repo.is_ancestor(&fix, &fix).unwrap());
+}
+
+#[test]
+fn test_branch_and_base() {
+    let test_repo = TestRepo::new();
+    let fork = test_repo.commit("Fork");
+    let upstream = test_repo.commit("Upstream");
+    test_repo.reset(&fork);
+    test_repo.commit("First");
+    let last = test_repo.commit("Last");
+    let repo = test_repo.open();
+    let branch = repo.current_branch().unwrap();
+
+    let (tip, base) = repo.branch_and_base(&branch, Some(&upstream)).unwrap();
+    assert_eq!(tip.to_string(), last);
+    assert_eq!(repo.merge_base(&tip, &base).unwrap().to_string(), fork);
+    assert_eq!(repo.ahead_behind(&tip, &base).unwrap(), (2, 1));
+
+    // No upstream configured
+    assert!(matches!(
+        repo.branch_and_base(&branch, None),
+        Err(GitError {
+            code: GitErrorCode::CommitNotFound,
+            ..
+        })
+    ));
+    assert!(repo.branch_and_base("unknown", Some(&upstream)).is_err());
+}
+
+#[test]
+fn test_data_ref() {
+    let test_repo = TestRepo::new();
+    test_repo.commit("base");
+    let repo = test_repo.open();
+    assert!(repo.read_data(DATA_REF).unwrap().is_none());
+
+    let first = repo
+        .write_data(DATA_REF, "first", &[], None, "first")
+        .unwrap();
+    assert!(matches!(
+        repo.write_data(DATA_REF, "other", &[], None, "other"),
+        Err(GitError {
+            code: GitErrorCode::ConcurrentUpdate,
+            ..
+        })
+    ));
+    let second = repo
+        .write_data(DATA_REF, "second", &[&first], Some(&first), "second")
+        .unwrap();
+    let (commit, content) = repo.read_data(DATA_REF).unwrap().unwrap();
+    assert_eq!(commit.to_string(), second.to_string());
+    assert_eq!(content, "second");
+    assert_eq!(repo.read_data_at(&first).unwrap(), "first");
+    assert!(repo.is_ancestor(&first, &second).unwrap());
+
+    // The ref moved since first was read
+    assert!(matches!(
+        repo.write_data(DATA_REF, "stale", &[&first], Some(&first), "stale"),
+        Err(GitError {
+            code: GitErrorCode::ConcurrentUpdate,
+            ..
+        })
+    ));
+    assert!(repo
+        .move_ref(DATA_REF, &first, Some(&first), "stale")
+        .is_err());
+    assert_eq!(repo.read_data(DATA_REF).unwrap().unwrap().1, "second");
+    repo.move_ref(DATA_REF, &first, Some(&second), "back")
+        .unwrap();
+    assert_eq!(repo.read_data(DATA_REF).unwrap().unwrap().1, "first");
+
+    // Other failures are not taken for a concurrent update
+    assert!(matches!(
+        repo.move_ref("refs/patchr/bad..name", &first, None, "invalid"),
+        Err(GitError {
+            code: GitErrorCode::RepoOpFailed,
+            ..
+        })
+    ));
+}
@@ -0,0 +1,346 @@
+use std::{fs, io, path::Path};
+
+use lazy_static::lazy_static;
+use regex::Regex;
+
+use crate::{mail::MailHeaders, util::CommitTag};
+
+// Trailers collected from the replies to a series
+pub const REVIEW_TRAILERS: [&str; 3] = ["Reviewed-by", "Acked-by", "Tested-by"];
+
+// Trailers git adds itself, a block containing one of them needs only
+// 25% of trailer lines, like git interpret-trailers
+const GIT_GENERATED_PREFIXES: [&str; 2] = ["Signed-off-by: ", "(cherry picked from commit "];
+
+lazy_static! {
+    static ref TRAILER_TAG_REGEX: Regex = Regex::new(r"^[A-Za-z0-9][A-Za-z0-9-]*$").unwrap();
+    static ref TRAILER_LINE_REGEX: Regex =
+        Regex::new(r"^([A-Za-z0-9][A-Za-z0-9-]*)\s*:\s*(.*?)\s*$").unwrap();
+    static ref REVIEW_TRAILER_REGEX: Regex =
+        Regex::new(r"(?i)^\s*(reviewed-by|acked-by|tested-by)\s*:\s*(\S.*?)\s*$").unwrap();
+    static ref PATCH_NUMBER_REGEX: Regex = Regex::new(r"^(\d+)/(\d+)$").unwrap();
+    static ref VERSION_REGEX: Regex = Regex::new(r"^[vV](\d+)$").unwrap();
+}
+
+// Mails of an mbox file, or of a Maildir (cur and new directories)
+// or of a plain directory with one mail per file
+pub fn read_mailbox(path: &Path) -> Result<Vec<String>, io::Error> {
+    if !path.is_dir() {
+        let content = String::from_utf8_lossy(&fs::read(path)?).into_owned();
+        return Ok(split_mbox(&content));
+    }
+
+    let maildir: Vec<_> = ["cur", "new"]
+        .iter()
+        .map(|d| path.join(d))
+        .filter(|d| d.is_dir())
+        .collect();
+    let dirs = if maildir.is_empty() {
+        vec![path.to_path_buf()]
+    } else {
+        maildir
+    };
+    let mut files = Vec::new();
+    for dir in dirs {
+        for entry in fs::read_dir(dir)? {
+            let file = entry?.path();
+            let hidden = file
+                .file_name()
+                .is_some_and(|n| n.to_string_lossy().starts_with('.'));
+            if file.is_file() && !hidden {
+                files.push(file);
+            }
+        }
+    }
+    files.sort();
+    files
+        .iter()
+        .map(|f| fs::read(f).map(|c| String::from_utf8_lossy(&c).into_owned()))
+        .collect()
+}
+
+// A mail starts with a "From " line at the top of the file or after
+// an empty line, ">From " lines are unescaped (mboxrd)
+fn split_mbox(content: &str) -> Vec<String> {
+    let mut mails: Vec<String> = Vec::new();
+    let mut previous_empty = true;
+    for line in content.lines() {
+        if previous_empty && line.starts_with("From ") {
+            mails.push(String::new());
+        }
+        previous_empty = line.is_empty();
+        let Some(mail) = mails.last_mut() else {
+            continue; // garbage before the first mail
+        };
+        let unescaped = match line.strip_prefix('>') {
+            Some(l) if l.trim_start_matches('>').starts_with("From ") => l,
+            _ => line,
+        };
+        mail.push_str(unescaped);
+        mail.push('\n');
+    }
+    mails
+}
+
+fn normalize_id(id: &str) -> &str {
+    id.split_whitespace()
+        .next()
+        .unwrap_or("")
+        .trim_start_matches('<')
+        .trim_end_matches('>')
+}
+
+// Index of the sent mail a reply answers, 0 for the cover letter and n
+// for the nth patch. The In-Reply-To header is used first, then the
+// subject, e.g. "Re: [PATCH v2 1/3] ..." for the first patch of v2
+fn reply_target(
+    headers: &MailHeaders, revision: u32, cover_letter_id: Option<&str>, patch_ids: &[String],
+) -> Option<usize> {
+    if let Some(in_reply_to) = headers.get("In-Reply-To").map(normalize_id) {
+        if cover_letter_id.is_some_and(|id| normalize_id(id) == in_reply_to) {
+            return Some(0);
+        }
+        if let Some(n) = patch_ids
+            .iter()
+            .position(|id| normalize_id(id) == in_reply_to)
+        {
+            return Some(n + 1);
+        }
+    }
+
+    // Only replies, the patches themselves may carry trailers
+    let subject = headers.get("Subject")?;
+    if !subject
+        .get(..3)
+        .is_some_and(|s| s.eq_ignore_ascii_case("re:"))
+    {
+        return None;
+    }
+    let subject = &subject[3..];
+    let prefix = subject.split_once('[')?.1.split_once(']')?.0;
+    let mut mail_revision = 1;
+    let mut number = None;
+    for token in prefix.split_whitespace() {
+        if let Some(c) = VERSION_REGEX.captures(token) {
+            mail_revision = c[1].parse().ok()?;
+        } else if let Some(c) = PATCH_NUMBER_REGEX.captures(token) {
+            let (n, total): (usize, usize) = (c[1].parse().ok()?, c[2].parse().ok()?);
+            if total != patch_ids.len() || n > total {
+                return None;
+            }
+            number = Some(n);
+        }
+    }
+    if mail_revision != revision {
+        return None;
+    }
+    number
+}
+
+// Review trailers of a mail body, quoted lines are skipped
+fn review_trailers(mail: &str) -> Vec<(CommitTag, String)> {
+    let mut lines = mail.lines().peekable();
+    if lines.peek().is_some_and(|l| l.starts_with("From ")) {
+        lines.next();
+    }
+    lines
+        .skip_while(|l| !l.trim().is_empty()) // headers
+        .take_while(|&l| l != "-- ") // signature
+        .filter(|l| !l.trim_start().starts_with('>'))
+        .filter_map(|l| {
+            let c = REVIEW_TRAILER_REGEX.captures(l)?;
+            let name = REVIEW_TRAILERS
+                .iter()
+                .find(|t| t.eq_ignore_ascii_case(&c[1]))?;
+            let tag = match *name {
+                "Reviewed-by" => CommitTag::ReviewedBy,
+                _ => CommitTag::Custom(String::from(*name)),
+            };
+            Some((tag, String::from(&c[2])))
+        })
+        .collect()
+}
+
+// Trailers given in the replies to a sent revision, one list per patch
+// in the series order. Trailers given to the cover letter apply to every
+// patch, the mails of the revision itself are ignored
+pub fn collect_trailers(
+    mails: &[String], revision: u32, cover_letter_id: Option<&str>, patch_ids: &[String],
+) -> Vec<Vec<(CommitTag, String)>> {
+    let mut trailers: Vec<Vec<(CommitTag, String)>> = vec![Vec::new(); patch_ids.len()];
+    let mut add = |patch: usize, trailer: &(CommitTag, String)| {
+        if !trailers[patch].contains(trailer) {
+            trailers[patch].push(trailer.clone());
+        }
+    };
+
+    for mail in mails {
+        let headers = MailHeaders::parse(mail);
+        let own_mail = headers.message_id().map(normalize_id).is_some_and(|id| {
+            cover_letter_id.is_some_and(|c| normalize_id(c) == id)
+                || patch_ids.iter().any(|p| normalize_id(p) == id)
+        });
+        if own_mail {
+            continue;
+        }
+        let Some(target) = reply_target(&headers, revision, cover_letter_id, patch_ids) else {
+            continue;
+        };
+        for trailer in review_trailers(mail) {
+            match target {
+                0 => (0..patch_ids.len()).for_each(|p| add(p, &trailer)),
+                n => add(n - 1, &trailer),
+            }
+        }
+    }
+    trailers
+}
+
+// Position of the trailers added to a commit message: the tags are listed
+// in order and '*' stands for the unlisted ones, e.g. "Fixes,*,Signed-off-by".
+// Unlisted tags come last without '*', and without any order a trailer is
+// added at the end of the block like git interpret-trailers does
+#[derive(Clone, Default)]
+pub struct TrailerOrder {
+    tags: Vec<String>,
+}
+
+impl TrailerOrder {
+    pub fn parse(order: &str) -> Option<Self> {
+        let tags: Vec<String> = order
+            .split(',')
+            .map(|t| String::from(t.trim()))
+            .filter(|t| !t.is_empty())
+            .collect();
+        let valid = tags.iter().all(|t| t == "*" || is_trailer_tag(t))
+            && tags.iter().filter(|t| *t == "*").count() <= 1;
+        valid.then_some(Self { tags })
+    }
+
+    fn rank(&self, tag: &str) -> usize {
+        let position = |t: &str| self.tags.iter().position(|o| o.eq_ignore_ascii_case(t));
+        position(tag)
+            .or_else(|| position("*"))
+            .unwrap_or(self.tags.len())
+    }
+}
+
+// Tag names accepted by git interpret-trailers
+pub fn is_trailer_tag(tag: &str) -> bool {
+    TRAILER_TAG_REGEX.is_match(tag)
+}
+
+fn parse_trailer(line: &str) -> Option<(&str, &str)> {
+    let c = TRAILER_LINE_REGEX.captures(line)?;
+    Some((c.get(1)?.as_str(), c.get(2)?.as_str()))
+}
+
+// Number of lines without the trailing blank ones
+fn content_len(lines: &[&str]) -> usize {
+    lines
+        .iter()
+        .rposition(|l| !l.trim().is_empty())
+        .map_or(0, |i| i + 1)
+}
+
+fn is_continuation(line: &str) -> bool {
+    line.starts_with([' ', '\t'])
+}
+
+// Lines start..end of the trailer block of a message, which is its last
+// paragraph when it holds only trailers, or enough of them next to a
+// trailer generated by git. The subject is never a trailer block
+fn trailer_block(lines: &[&str]) -> Option<(usize, usize)> {
+    let end = content_len(lines);
+    let start = lines[..end]
+        .iter()
+        .rposition(|l| l.trim().is_empty())
+        .map_or(0, |i| i + 1);
+    if start == 0 || end == 0 {
+        return None;
+    }
+
+    let (mut trailers, mut others, mut generated) = (0, 0, false);
+    for line in &lines[start..end] {
+        if parse_trailer(line).is_some() {
+            trailers += 1;
+        } else if !is_continuation(line) {
+            others += 1;
+        }
+        generated |= GIT_GENERATED_PREFIXES.iter().any(|p| line.starts_with(p));
+    }
+    let is_block = (trailers > 0 && others == 0) || (generated && trailers * 3 >= others);
+    is_block.then_some((start, end))
+}
+
+fn join_lines(lines: &[&str]) -> String {
+    let mut message = lines.join("\n");
+    message.push('\n');
+    message
+}
+
+// Message with the trailer added to its trailer block, or to a new block
+// after a blank line. None if the message already has the trailer, tags
+// are case insensitive
+pub fn add_trailer(message: &str, tag: &str, value: &str, order: &TrailerOrder) -> Option<String> {
+    let mut lines: Vec<&str> = message.lines().collect();
+    let trailer = format!("{}: {}", tag, value.trim());
+
+    let Some((start, end)) = trailer_block(&lines) else {
+        lines.truncate(content_len(&lines));
+        if !lines.is_empty() {
+            lines.push("");
+        }
+        lines.push(&trailer);
+        return Some(join_lines(&lines));
+    };
+
+    let rank = order.rank(tag);
+    let mut position = start;
+    for (i, line) in lines.iter().enumerate().take(end).skip(start) {
+        let Some((t, v)) = parse_trailer(line) else {
+            continue;
+        };
+        if t.eq_ignore_ascii_case(tag) && v == value.trim() {
+            return None;
+        }
+        if order.rank(t) <= rank {
+            position = i + 1;
+            while position < end && is_continuation(lines[position]) {
+                position += 1;
+            }
+        }
+    }
+    lines.insert(position, &trailer);
+    Some(join_lines(&lines))
+}
+
+// Message without the trailers of the tag, only the ones with the value
+// if given. None if the message has no such trailer
+pub fn remove_trailers(message: &str, tag: &str, value: Option<&str>) -> Option<String> {
+    let lines: Vec<&str> = message.lines().collect();
+    let (start, end) = trailer_block(&lines)?;
+
+    let mut kept: Vec<&str> = lines[..start].to_vec();
+    let mut removing = false;
+    for line in &lines[start..end] {
+        if removing && is_continuation(line) {
+            continue;
+        }
+        removing = parse_trailer(line).is_some_and(|(t, v)| {
+            t.eq_ignore_ascii_case(tag) && (value.is_none() || value.map(str::trim) == Some(v))
+        });
+        if !removing {
+            kept.push(line);
+        }
+    }
+    if kept.len() == end {
+        return None;
+    }
+    if kept.len() == start {
+        // Empty block, drop its separator
+        kept.truncate(content_len(&kept));
+    }
+    kept.extend_from_slice(&lines[end..]);
+    Some(join_lines(&kept))
+}
@@ -1,8 +1,30 @@
-use std::{fmt::Display, path::PathBuf};
+use std::{
+    collections::HashMap,
+    fmt::Display,
+    path::{Path, PathBuf},
+    process,
+};
 
-use common::util::misc::LINE_SEP;
+use common::constants::GIT_COMMAND;
+use log::warn;
 
-use crate::GitError;
+use crate::{
+    trailers::{add_trailer, remove_trailers, TrailerOrder},
+    GitError, GitErrorCode,
+};
+
+// Every history rewrite keeps the previous HEAD under this prefix
+pub const BACKUP_REF_PREFIX: &str = "refs/patchr/backup/";
+// Number of rewrites that can be undone
+const MAX_BACKUPS: usize = 10;
+
+// The data of the repos stored in git is committed to this ref, a commit
+// whose tree holds the data file, see GitRepo::write_data
+pub const DATA_REF: &str = "refs/patchr/data";
+const DATA_FILE_NAME: &str = "repo.json";
+
+// Trailer naming the commit a patch fixes, see Commit::fixes_reference
+pub const FIXES_TAG: &str = "Fixes";
 
 // Define wrappers so that we do not expose libgit2
 // structs to the rest of the code
@@ -20,7 +42,7 @@ pub struct CommitId {
     oid: git2::Oid,
 }
 
-#[derive(Clone)]
+#[derive(Clone, PartialEq, Eq)]
 pub enum CommitTag {
     ReviewedBy,
     SignedOffBy,
@@ -32,12 +54,13 @@ impl CommitId {
         let oid = match git2::Oid::from_str(hex) {
             Ok(o) => o,
             Err(e) => {
-                return Err(GitError::repo_op_failed(e.message()));
+                return Err(GitError::new(
+                    GitErrorCode::InvalidCommitId,
+                    String::from(e.message()),
+                ));
             }
         };
-        Ok(Self {
-            oid
-        })
+        Ok(Self { oid })
     }
 
     fn from_oid(oid: git2::Oid) -> Self {
@@ -67,6 +90,7 @@ impl From<&str> for CommitTag {
         match value {
             "rb" => CommitTag::ReviewedBy,
             "so" => CommitTag::SignedOffBy,
+            "fixes" => CommitTag::Custom(String::from(FIXES_TAG)),
             _ => CommitTag::Custom(String::from(value)),
         }
     }
@@ -88,7 +112,7 @@ impl GitRepo {
                 return Err(GitError::repo_op_failed("Failed retrieve the new commit id"));
             };
             let Ok(commit) = self.repo.find_commit(oid) else {
-                return Err(GitError::repo_op_failed(format!("Failed to find the commit with hash {}", oid.to_string()).as_str()));
+                return Err(GitError::repo_op_failed(format!("Failed to find the commit with hash {}", oid).as_str()));
             };
             if !func(&Commit::new(commit, self)) {
                 return Ok(());
@@ -97,16 +121,498 @@ impl GitRepo {
         Ok(())
     }
 
-    pub fn find_commit(&self, commit: &CommitId) -> Result<Commit, GitError> {
-        match self.repo.find_commit(commit.oid) {
-            Ok(c) => {
-                return Ok(Commit::new(c, self))
+    // Returns the commits of base..last, oldest first
+    pub fn commit_range(
+        &self, base: &CommitId, last: &CommitId,
+    ) -> Result<Vec<Commit<'_>>, GitError> {
+        let mut revwalk = self.repo.revwalk().map_err(|e| GitError::repo_op_failed(e.message()))?;
+        revwalk
+            .set_sorting(git2::Sort::TOPOLOGICAL | git2::Sort::REVERSE)
+            .map_err(|e| GitError::repo_op_failed(e.message()))?;
+        revwalk.push(last.oid).map_err(|e| GitError::repo_op_failed(e.message()))?;
+        revwalk.hide(base.oid).map_err(|e| GitError::repo_op_failed(e.message()))?;
+        revwalk
+            .map(|roid| {
+                let oid = roid.map_err(|e| GitError::repo_op_failed(e.message()))?;
+                self.repo
+                    .find_commit(oid)
+                    .map(|c| Commit::new(c, self))
+                    .map_err(|e| GitError::repo_op_failed(e.message()))
+            })
+            .collect()
+    }
+
+    // Name of the branch HEAD points to, None if HEAD is detached
+    pub fn current_branch(&self) -> Option<String> {
+        let head = self.repo.head().ok()?;
+        if !head.is_branch() {
+            return None;
+        }
+        head.shorthand().map(String::from)
+    }
+
+    // A rewrite moves the current branch, it requires a clean worktree
+    // and commits reachable from HEAD
+    fn check_rewritable(&self, commits: &[&CommitId]) -> Result<git2::Oid, GitError> {
+        let unsafe_rewrite =
+            |msg: &str| GitError::new(GitErrorCode::UnsafeRewrite, String::from(msg));
+        if self.repo.state() != git2::RepositoryState::Clean {
+            return Err(unsafe_rewrite("Another operation (rebase, merge...) is in progress"));
+        }
+        let head = self
+            .repo
+            .head()
+            .map_err(|e| unsafe_rewrite(format!("No HEAD to rewrite, {}", e.message()).as_str()))?;
+        if !head.is_branch() {
+            return Err(unsafe_rewrite("HEAD is detached, checkout a branch first"));
+        }
+        let mut options = git2::StatusOptions::new();
+        options.include_untracked(false).include_ignored(false);
+        let statuses = self.repo.statuses(Some(&mut options)).map_err(|e| {
+            unsafe_rewrite(format!("Failed to check the worktree, {}", e.message()).as_str())
+        })?;
+        if !statuses.is_empty() {
+            return Err(unsafe_rewrite("The worktree or the index has uncommitted changes"));
+        }
+
+        let head = head.target().ok_or_else(|| unsafe_rewrite("HEAD has no target"))?;
+        for commit in commits {
+            let reachable = commit.oid == head
+                || self.repo.graph_descendant_of(head, commit.oid).map_err(|e| {
+                    GitError::new(
+                        GitErrorCode::CommitNotFound,
+                        format!("Failed to find {}, {}", commit, e.message()),
+                    )
+                })?;
+            if !reachable {
+                return Err(GitError::new(
+                    GitErrorCode::UnsafeRewrite,
+                    format!("{} is not an ancestor of HEAD", commit),
+                ));
+            }
+        }
+        Ok(head)
+    }
+
+    // Keep the HEAD preceding a rewrite, see undo_rewrite. The name of the
+    // backup ends with the rewritten branch, returns it
+    fn backup_head(&self, head: git2::Oid) -> Result<String, GitError> {
+        let map_err = |e: git2::Error| GitError::repo_op_failed(e.message());
+        let head_ref = self.repo.head().map_err(map_err)?;
+        // check_rewritable makes sure HEAD is a branch
+        let branch = head_ref
+            .name()
+            .and_then(|n| n.strip_prefix("refs/"))
+            .ok_or_else(|| GitError::repo_op_failed("Invalid branch name"))?;
+        let name = format!(
+            "{}{}/{}",
+            BACKUP_REF_PREFIX,
+            chrono::offset::Utc::now().format("%Y%m%d%H%M%S%.9f"),
+            branch
+        );
+        self.repo
+            .reference(&name, head, false, "patchr: backup before rewrite")
+            .map_err(|e| {
+                GitError::repo_op_failed(
+                    format!("Failed to back up HEAD before the rewrite, {}", e.message()).as_str(),
+                )
+            })?;
+        Ok(name)
+    }
+
+    // Names of the backups of HEAD, oldest first
+    fn backup_refs(&self) -> Result<Vec<String>, GitError> {
+        let map_err = |e: git2::Error| GitError::repo_op_failed(e.message());
+        let mut backups = self
+            .repo
+            .references_glob(format!("{}*", BACKUP_REF_PREFIX).as_str())
+            .map_err(map_err)?
+            .names()
+            .map(|n| n.map(String::from))
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(map_err)?;
+        backups.sort();
+        Ok(backups)
+    }
+
+    // Only the last rewrites can be undone, the older backups are deleted
+    fn prune_backups(&self) -> Result<(), GitError> {
+        let backups = self.backup_refs()?;
+        let count = backups.len().saturating_sub(MAX_BACKUPS);
+        for name in &backups[..count] {
+            self.repo
+                .find_reference(name)
+                .and_then(|mut r| r.delete())
+                .map_err(|e| GitError::repo_op_failed(e.message()))?;
+        }
+        Ok(())
+    }
+
+    // Move the rewritten branch back to the HEAD kept by the last rewrite.
+    // A rewrite only changes messages, the branch must be checked out and
+    // the tree of HEAD unchanged so that no later work is lost. Returns the
+    // restored commit
+    pub fn undo_rewrite(&self) -> Result<CommitId, GitError> {
+        let undo_failed = |e: git2::Error| {
+            GitError::new(
+                GitErrorCode::RewriteFailed,
+                format!("Failed to undo the rewrite, {}", e.message()),
+            )
+        };
+        let Some(name) = self.backup_refs()?.pop() else {
+            return Err(GitError::new(
+                GitErrorCode::NothingToUndo,
+                String::from("No rewrite to undo"),
+            ));
+        };
+        // <prefix><date>/<branch without refs/>
+        let branch = name[BACKUP_REF_PREFIX.len()..]
+            .split_once('/')
+            .map(|(_, b)| format!("refs/{}", b))
+            .ok_or_else(|| {
+                GitError::new(GitErrorCode::NothingToUndo, format!("Invalid backup {}", name))
+            })?;
+
+        let mut backup = self.repo.find_reference(&name).map_err(|e| {
+            GitError::new(
+                GitErrorCode::NothingToUndo,
+                format!("{} not found, {}", name, e.message()),
+            )
+        })?;
+        let old_head = backup.peel_to_commit().map_err(|e| {
+            GitError::new(
+                GitErrorCode::CommitNotFound,
+                format!("Failed to find the commit kept in {}, {}", name, e.message()),
+            )
+        })?;
+        let unsafe_rewrite = |msg: String| GitError::new(GitErrorCode::UnsafeRewrite, msg);
+        let head = self
+            .repo
+            .head()
+            .map_err(|e| unsafe_rewrite(format!("No HEAD to restore, {}", e.message())))?;
+        if head.name() != Some(branch.as_str()) {
+            return Err(unsafe_rewrite(format!(
+                "The last rewrite was on {}, checkout it first",
+                branch.trim_start_matches("refs/heads/")
+            )));
+        }
+        let head = head
+            .peel_to_commit()
+            .map_err(|e| unsafe_rewrite(format!("HEAD is not a commit, {}", e.message())))?;
+        if head.tree_id() != old_head.tree_id() {
+            return Err(unsafe_rewrite(format!(
+                "HEAD changed since the rewrite, {} is kept in {}",
+                old_head.id(),
+                name
+            )));
+        }
+
+        // Same tree, only the branch moves
+        self.repo
+            .reset(old_head.as_object(), git2::ResetType::Soft, None)
+            .map_err(undo_failed)?;
+        backup.delete().map_err(undo_failed)?;
+        Ok(CommitId::from_oid(old_head.id()))
+    }
+
+    // Rewrites the messages of the commits in a single pass over HEAD,
+    // returns the old and new ids of every replayed commit.
+    // Unlike a rebase, the commits are replayed with their trees unchanged:
+    // merges keep their (rewritten) parents, root commits can be reworded
+    // and no conflict can happen
+    pub fn reword_commits(
+        &self, messages: &[(CommitId, String)],
+    ) -> Result<Vec<(CommitId, CommitId)>, GitError> {
+        let targets: Vec<_> = messages.iter().map(|(id, _)| id).collect();
+        let head = self.check_rewritable(&targets)?;
+        if messages.is_empty() {
+            return Ok(Vec::new());
+        }
+        let backup = self.backup_head(head)?;
+        match self.replay_with_messages(head, messages) {
+            Ok(rewritten) => {
+                // The rewrite is done, extra backups only take some space
+                if let Err(e) = self.prune_backups() {
+                    warn!("Failed to prune the backups of HEAD, {}", e);
+                }
+                Ok(rewritten)
             }
             Err(e) => {
-                return Err(GitError::repo_op_failed(e.message()))
+                // Nothing to undo, the branch did not move
+                let _ = self
+                    .repo
+                    .find_reference(&backup)
+                    .and_then(|mut r| r.delete());
+                Err(e)
             }
         }
-        
+    }
+
+    // Replays the commits after the targets up to head, see reword_commits
+    fn replay_with_messages(
+        &self, head: git2::Oid, messages: &[(CommitId, String)],
+    ) -> Result<Vec<(CommitId, CommitId)>, GitError> {
+        let rewrite_failed =
+            |e: git2::Error| GitError::new(GitErrorCode::RewriteFailed, String::from(e.message()));
+
+        // The targets and the commits after them, parents first
+        let mut revwalk = self.repo.revwalk().map_err(rewrite_failed)?;
+        revwalk
+            .set_sorting(git2::Sort::TOPOLOGICAL | git2::Sort::REVERSE)
+            .map_err(rewrite_failed)?;
+        revwalk.push(head).map_err(rewrite_failed)?;
+        // Hiding the parents of a target following another one would also
+        // hide the first one
+        for (id, _) in messages {
+            let follows_target = messages.iter().try_fold(false, |found, (other, _)| {
+                self.repo
+                    .graph_descendant_of(id.oid, other.oid)
+                    .map(|d| found || d)
+            });
+            if follows_target.map_err(rewrite_failed)? {
+                continue;
+            }
+            for parent in self.find_commit(id)?.commit.parent_ids() {
+                revwalk.hide(parent).map_err(rewrite_failed)?;
+            }
+        }
+
+        let mut rewritten: Vec<(git2::Oid, git2::Oid)> = Vec::new();
+        let mut new_ids: HashMap<git2::Oid, git2::Oid> = HashMap::new();
+        for oid in revwalk {
+            let commit = oid
+                .and_then(|oid| self.repo.find_commit(oid))
+                .map_err(rewrite_failed)?;
+            let parents = commit
+                .parent_ids()
+                .map(|p| self.repo.find_commit(*new_ids.get(&p).unwrap_or(&p)))
+                .collect::<Result<Vec<_>, _>>()
+                .map_err(rewrite_failed)?;
+            let message = match messages.iter().find(|(id, _)| id.oid == commit.id()) {
+                Some((_, m)) => m.as_str(),
+                None => commit.message_raw().ok_or_else(|| {
+                    GitError::new(
+                        GitErrorCode::RewriteFailed,
+                        format!("The message of {} is not valid UTF-8", commit.id()),
+                    )
+                })?,
+            };
+            let tree = commit.tree().map_err(rewrite_failed)?;
+            let new_oid = self
+                .repo
+                .commit(
+                    None,
+                    &commit.author(),
+                    &commit.committer(),
+                    message,
+                    &tree,
+                    parents.iter().collect::<Vec<_>>().as_slice(),
+                )
+                .map_err(rewrite_failed)?;
+            new_ids.insert(commit.id(), new_oid);
+            rewritten.push((commit.id(), new_oid));
+        }
+
+        // The tree of HEAD is the same, only the branch moves
+        let new_head = *new_ids.get(&head).unwrap_or(&head);
+        self.repo
+            .head()
+            .and_then(|mut r| r.set_target(new_head, "patchr: reword commits"))
+            .map_err(rewrite_failed)?;
+        Ok(rewritten
+            .into_iter()
+            .map(|(old, new)| (CommitId::from_oid(old), CommitId::from_oid(new)))
+            .collect())
+    }
+
+    // Commit designated by a revision, e.g. a sha1 prefix, a branch or HEAD~2
+    pub fn revparse_commit(&self, revision: &str) -> Result<Commit<'_>, GitError> {
+        let commit = self
+            .repo
+            .revparse_single(revision)
+            .and_then(|o| o.peel_to_commit());
+        match commit {
+            Ok(c) => Ok(Commit::new(c, self)),
+            Err(e) => Err(GitError::new(
+                GitErrorCode::CommitNotFound,
+                format!("{}: {}", revision, e.message()),
+            )),
+        }
+    }
+
+    // Id of the commit designated by a revision, see revparse_commit
+    pub fn revparse(&self, revision: &str) -> Result<CommitId, GitError> {
+        self.revparse_commit(revision).map(|c| c.id())
+    }
+
+    // Tips of a branch and of its base, the upstream of the branch by default
+    pub fn branch_and_base(
+        &self, branch: &str, base: Option<&str>,
+    ) -> Result<(CommitId, CommitId), GitError> {
+        let tip = self.revparse(format!("refs/heads/{}", branch).as_str())?;
+        let base = match base {
+            Some(b) => self.revparse(b)?,
+            None => self
+                .revparse(format!("refs/heads/{}@{{upstream}}", branch).as_str())
+                .map_err(|_| {
+                    GitError::new(
+                        GitErrorCode::CommitNotFound,
+                        format!("No base set and {} has no upstream", branch),
+                    )
+                })?,
+        };
+        Ok((tip, base))
+    }
+
+    // Best common ancestor of the commits, see git merge-base
+    pub fn merge_base(&self, one: &CommitId, two: &CommitId) -> Result<CommitId, GitError> {
+        self.repo
+            .merge_base(one.oid, two.oid)
+            .map(CommitId::from_oid)
+            .map_err(|e| GitError::repo_op_failed(e.message()))
+    }
+
+    // Number of commits of local that upstream does not have (ahead) and
+    // of upstream that local does not have (behind)
+    pub fn ahead_behind(
+        &self, local: &CommitId, upstream: &CommitId,
+    ) -> Result<(usize, usize), GitError> {
+        self.repo
+            .graph_ahead_behind(local.oid, upstream.oid)
+            .map_err(|e| GitError::repo_op_failed(e.message()))
+    }
+
+    // True if ancestor is reachable from commit, a commit is not its own ancestor
+    pub fn is_ancestor(&self, ancestor: &CommitId, commit: &CommitId) -> Result<bool, GitError> {
+        self.repo
+            .graph_descendant_of(commit.oid, ancestor.oid)
+            .map_err(|e| GitError::repo_op_failed(e.message()))
+    }
+
+    pub fn find_commit(&self, commit: &CommitId) -> Result<Commit<'_>, GitError> {
+        match self.repo.find_commit(commit.oid) {
+            Ok(c) => Ok(Commit::new(c, self)),
+            Err(e) => Err(GitError::new(GitErrorCode::CommitNotFound, String::from(e.message()))),
+        }
+    }
+
+    // Commit a ref points to and its data file, None if the ref does not exist
+    pub fn read_data(&self, refname: &str) -> Result<Option<(CommitId, String)>, GitError> {
+        let map_err = |e: git2::Error| GitError::repo_op_failed(e.message());
+        let commit = match self.repo.find_reference(refname) {
+            Ok(r) => r.peel_to_commit().map_err(map_err)?,
+            Err(e) if e.code() == git2::ErrorCode::NotFound => return Ok(None),
+            Err(e) => return Err(map_err(e)),
+        };
+        let content = self.data_file_content(&commit)?;
+        Ok(Some((CommitId::from_oid(commit.id()), content)))
+    }
+
+    // Data file of a commit written by write_data
+    pub fn read_data_at(&self, commit: &CommitId) -> Result<String, GitError> {
+        match self.repo.find_commit(commit.oid) {
+            Ok(c) => self.data_file_content(&c),
+            Err(e) => Err(GitError::new(GitErrorCode::CommitNotFound, String::from(e.message()))),
+        }
+    }
+
+    fn data_file_content(&self, commit: &git2::Commit) -> Result<String, GitError> {
+        let map_err = |e: git2::Error| GitError::repo_op_failed(e.message());
+        let entry = commit
+            .tree()
+            .map_err(map_err)?
+            .get_path(Path::new(DATA_FILE_NAME))
+            .map_err(map_err)?;
+        let blob = self.repo.find_blob(entry.id()).map_err(map_err)?;
+        String::from_utf8(blob.content().to_vec())
+            .map_err(|e| GitError::new(GitErrorCode::StringFormatError, e.to_string()))
+    }
+
+    // Commit the content as the data file on top of the parents and move the
+    // ref to it, see move_ref
+    pub fn write_data(
+        &self, refname: &str, content: &str, parents: &[&CommitId], current: Option<&CommitId>,
+        message: &str,
+    ) -> Result<CommitId, GitError> {
+        let map_err = |e: git2::Error| GitError::repo_op_failed(e.message());
+        let blob = self.repo.blob(content.as_bytes()).map_err(map_err)?;
+        let mut builder = self.repo.treebuilder(None).map_err(map_err)?;
+        builder
+            .insert(DATA_FILE_NAME, blob, git2::FileMode::Blob.into())
+            .map_err(map_err)?;
+        let tree = self
+            .repo
+            .find_tree(builder.write().map_err(map_err)?)
+            .map_err(map_err)?;
+        let parents = parents
+            .iter()
+            .map(|p| self.repo.find_commit(p.oid))
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(map_err)?;
+        let parent_refs: Vec<&git2::Commit> = parents.iter().collect();
+        // the data may be written in a repo without identity
+        let sig = self
+            .repo
+            .signature()
+            .or_else(|_| git2::Signature::now("patchr", "patchr@localhost"))
+            .map_err(map_err)?;
+        let oid = self
+            .repo
+            .commit(None, &sig, &sig, message, &tree, parent_refs.as_slice())
+            .map_err(map_err)?;
+        let commit = CommitId::from_oid(oid);
+        self.move_ref(refname, &commit, current, message)?;
+        Ok(commit)
+    }
+
+    // Point the ref to the commit. The ref must still point to current, or
+    // not exist if None, so that a concurrent update is not overwritten
+    pub fn move_ref(
+        &self, refname: &str, commit: &CommitId, current: Option<&CommitId>, message: &str,
+    ) -> Result<(), GitError> {
+        let res = match current {
+            Some(c) => self
+                .repo
+                .reference_matching(refname, commit.oid, true, c.oid, message),
+            None => self.repo.reference(refname, commit.oid, false, message),
+        };
+        res.map(|_| ()).map_err(|e| match e.code() {
+            git2::ErrorCode::Modified | git2::ErrorCode::Exists => GitError::new(
+                GitErrorCode::ConcurrentUpdate,
+                format!("{} was updated meanwhile, {}", refname, e.message()),
+            ),
+            _ => GitError::repo_op_failed(
+                format!("Failed to update {}, {}", refname, e.message()).as_str(),
+            ),
+        })
+    }
+
+    // Fetch a ref of the remote into a local one, whatever the previous value
+    // of the local ref since the fetched data is merged afterwards
+    pub fn fetch_ref(&self, remote: &str, src: &str, dst: &str) -> Result<(), GitError> {
+        self.run_git(&["fetch", remote, format!("+{}:{}", src, dst).as_str()])
+    }
+
+    // The push is rejected if the remote ref is not an ancestor of the local one
+    pub fn push_ref(&self, remote: &str, refname: &str) -> Result<(), GitError> {
+        self.run_git(&["push", remote, format!("{}:{}", refname, refname).as_str()])
+    }
+
+    // git itself deals with the remotes and their credentials
+    fn run_git(&self, args: &[&str]) -> Result<(), GitError> {
+        let status = process::Command::new(GIT_COMMAND)
+            .arg("--git-dir")
+            .arg(self.repo.path())
+            .args(args)
+            .status()
+            .map_err(|e| GitError::new(GitErrorCode::CommandExecutionFailed, e.to_string()))?;
+        if !status.success() {
+            return Err(GitError::new(
+                GitErrorCode::CommandExecutionFailed,
+                format!("git {} failed", args.join(" ")),
+            ));
+        }
+        Ok(())
     }
 }
 
@@ -123,6 +629,30 @@ impl<'a> Commit<'a> {
         self.commit.summary().unwrap_or("")
     }
 
+    // Value of a Fixes trailer naming this commit, e.g.
+    // 54a4f0239f2e ("Subject of the commit")
+    pub fn fixes_reference(&self, abbrev: usize) -> String {
+        let id = self.commit.id().to_string();
+        let abbrev = abbrev.clamp(4, id.len());
+        format!("{} (\"{}\")", &id[..abbrev], self.short_name())
+    }
+
+    // Stable id of the changes introduced by the commit, see git patch-id
+    pub fn patch_id(&self) -> Result<String, GitError> {
+        let map_err = |e: git2::Error| GitError::repo_op_failed(e.message());
+        let tree = self.commit.tree().map_err(map_err)?;
+        let parent_tree = match self.commit.parent(0) {
+            Ok(p) => Some(p.tree().map_err(map_err)?),
+            Err(_) => None, // root commit
+        };
+        let diff = self
+            .repo
+            .repo
+            .diff_tree_to_tree(parent_tree.as_ref(), Some(&tree), None)
+            .map_err(map_err)?;
+        diff.patchid(None).map(|id| id.to_string()).map_err(map_err)
+    }
+
     pub fn message(&self) -> Result<&str, GitError> {
         match self.commit.message_raw() {
             Some(m) => Ok(m),
@@ -133,102 +663,65 @@ impl<'a> Commit<'a> {
     }
 
     pub fn set_message(&self, message: &str) -> Result<CommitId, GitError> {
-        let parent = self.commit.parent_id(0)
-            .map_err(|e| GitError::repo_op_failed(e.message()))?;
-        let annotated = self.repo.repo.find_annotated_commit(parent)
-            .map_err(|e | GitError::repo_op_failed(e.message()))?;
-        let mut rebase = self.repo.repo.rebase(None, Some(&annotated), None, None)
-            .map_err(|e| GitError::repo_op_failed(e.message()))?;
-
-        let mut result: Result<CommitId, _> = Err(GitError::repo_op_failed("Commit unreachable"));
-        while let Some(op) = rebase.next() {
-            match op {
-                Ok(r) => {
-                    let current_commit = self.repo.repo.find_commit(r.id()).unwrap();
-                    let found = r.id() == self.commit.id();
-                    let commit_res = if found {
-                        rebase.commit(None, &current_commit.committer(), Some(message))
-                    } else {
-                        rebase.commit(None, &current_commit.committer(), None)
-                    };
-
-                    match commit_res {
-                        Ok(oid) => {
-                            if found {
-                                result = Ok(CommitId::from_oid(oid))
-                            }
-                        }
-                        Err(e) => {
-                            result = Err(GitError::repo_op_failed(e.message()));
-                            break;
-                        }
-                    }
-                }
-                Err(e) => {
-                    result = Err(GitError::repo_op_failed(e.message()));
-                    break;
-                }
-            }
-        };
-
-        match result {
-            Ok(commit_id) => {
-                match rebase.finish(None) {
-                    Ok(_) => Ok(commit_id),
-                    Err(e) => Err(GitError::repo_op_failed(e.message()))
-                }
-            }
-            Err(_) => {
-                // Try our best to clean up and return the error
-                let _ = rebase.abort();
-                result
-            }
-        }
-    }
-
-    fn prepare_tag(tag: &CommitTag, tag_value: Option<&str>) -> String {
-        let mut tag = tag.to_string();
-        if let Some(v) = tag_value {
-            tag.push_str(format!(": {}", v).as_str());
-        }
-        tag
+        let rewritten = self
+            .repo
+            .reword_commits(&[(self.id(), String::from(message))])?;
+        // reword_commits fails if the commit is not replayed
+        let (_, new_id) = rewritten
+            .into_iter()
+            .find(|(old, _)| old.oid == self.commit.id())
+            .unwrap();
+        Ok(new_id)
     }
 
+    // Adds the trailer to the trailer block of the message, see add_trailer
     pub fn add_tag(
-        &self, tag: &CommitTag, tag_value: Option<&str>,
+        &self, tag: &CommitTag, tag_value: &str, order: &TrailerOrder,
     ) -> Result<CommitId, GitError> {
-        let full_tag = Self::prepare_tag(tag, tag_value);
-        let message = self.message()?;
-        if message.lines().any(|l| l == full_tag) {
-            return Ok(self.id()); // Commit message already contains the tag
+        match add_trailer(self.message()?, &tag.to_string(), tag_value, order) {
+            Some(message) => self.set_message(message.as_str()),
+            None => Ok(self.id()), // Commit message already contains the tag
         }
+    }
 
-        let mut message = String::from(message);
-        message.push_str(format!("{}{}", LINE_SEP, full_tag).as_str());
-        self.set_message(message.as_str())
+    // Message with the tags added, the ones already present are skipped
+    pub fn message_with_tags(
+        &self, tags: &[(CommitTag, String)], order: &TrailerOrder,
+    ) -> Result<String, GitError> {
+        let mut message = String::from(self.message()?);
+        for (tag, value) in tags {
+            if let Some(m) = add_trailer(&message, &tag.to_string(), value, order) {
+                message = m;
+            }
+        }
+        Ok(message)
     }
 
     pub fn remove_tag(&self, tag: &CommitTag, tag_value: Option<&str>) -> Result<CommitId, GitError> {
-        let full_tag = Self::prepare_tag(tag, tag_value).to_lowercase();
-        let message = self.message()?;
-        let new_message: String = message.lines().filter_map(|l| {
-            if l.to_lowercase() == full_tag {
-                None
-            } else {
-                Some(format!("{}{}", l, LINE_SEP))
-            }
-        }).collect();
-        self.set_message(new_message.as_str())
+        match remove_trailers(self.message()?, &tag.to_string(), tag_value) {
+            Some(message) => self.set_message(message.as_str()),
+            None => Ok(self.id()), // No such tag
+        }
     }
 
     pub fn remove_tag_all(&self, tag: &CommitTag) -> Result<CommitId, GitError> {
-        let full_tag = Self::prepare_tag(tag, Some("")).to_lowercase();
-        let message = self.message()?;
-        let new_message: String = message.lines().filter(|&l| {
-            !l.to_lowercase().starts_with(&full_tag)
-        }).collect();
-        self.set_message(new_message.as_str())
+        self.remove_tag(tag, None)
+    }
+}
+
+// The bundled libgit2 is built without SHA-256 support, the repos using
+// this object format cannot be opened. Their format is read from the config
+// of the repo, linked worktrees share the config of the main repo
+pub fn is_sha256_repo(path: &str) -> bool {
+    let Ok(mut git_dir) = git2::Repository::discover_path(path, Vec::<&str>::new()) else {
+        return false;
+    };
+    if let Ok(common_dir) = std::fs::read_to_string(git_dir.join("commondir")) {
+        git_dir = git_dir.join(common_dir.trim_end());
     }
+    git2::Config::open(&git_dir.join("config"))
+        .and_then(|c| c.get_string("extensions.objectformat"))
+        .is_ok_and(|f| f.eq_ignore_ascii_case("sha256"))
 }
 
 pub fn find_repo_root(path: &str) -> Option<PathBuf> {
@@ -12,19 +12,37 @@ pub mod delete_revision;
 pub mod delete_series;
 pub mod edit_revision;
 pub mod edit_series;
+pub mod export;
 pub mod help;
+pub mod history;
 pub mod list_repos;
 pub mod list_series;
+pub mod migrate;
+pub mod range_diff;
 pub mod register_repo;
 pub mod send_series;
 pub mod set_verbose;
 pub mod show_series;
+pub mod shortcuts;
+pub mod storage;
+pub mod sync_data;
 pub mod tag_untag;
+pub mod trailers;
+pub mod undo;
 pub mod edit_cv_skel;
 
 use cleantmp::CleanTmp;
 use edit_cv_skel::EditCVSkel;
+use export::Export;
+use history::History;
+use migrate::Migrate;
+use range_diff::RangeDiff;
+use shortcuts::Shortcut;
+use storage::Storage;
+use sync_data::SyncData;
 use tag_untag::{Tag, UnTag};
+use trailers::Trailers;
+use undo::Undo;
 
 use crate::user_data::user_data::UserData;
 
@@ -72,9 +90,32 @@ declare_command!(CLEAN_TMP, cleantmp);
 declare_command!(TAG, tag);
 declare_command!(UNTAG, untag);
 declare_command!(EDIT_CV_SKEL, cvskel);
+declare_command!(RANGE_DIFF, rangediff);
+declare_command!(HISTORY, history);
+declare_command!(EXPORT, export);
+declare_command!(TRAILERS, trailers);
+declare_command!(UNDO, undo);
+declare_command!(ALIAS, alias);
+declare_command!(PERSON, person);
+declare_command!(MIGRATE, migrate);
+declare_command!(STORAGE, storage);
+declare_command!(PUSH_DATA, pushdata);
+declare_command!(FETCH_DATA, fetchdata);
 
 pub trait Command {
     fn exec(&self, user_data: &mut UserData) -> ControlFlow<()>;
+
+    // Commands working on the data files themselves run instead of loading
+    // them, None for the commands that need the user data
+    fn exec_without_user_data(&self) -> Option<ControlFlow<()>> {
+        None
+    }
+
+    // The data is not saved when only read-only commands are given, other
+    // patchr processes can read it at the same time
+    fn read_only(&self) -> bool {
+        false
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -169,6 +210,17 @@ pub fn get_command_builder(name: &str) -> Option<Box<dyn CommandBuilder>> {
         TAG => Some(Tag::builder()),
         UNTAG => Some(UnTag::builder()),
         EDIT_CV_SKEL => Some(EditCVSkel::builder()),
+        RANGE_DIFF => Some(RangeDiff::builder()),
+        HISTORY => Some(History::builder()),
+        EXPORT => Some(Export::builder()),
+        TRAILERS => Some(Trailers::builder()),
+        UNDO => Some(Undo::builder()),
+        ALIAS => Some(Shortcut::alias_builder()),
+        PERSON => Some(Shortcut::person_builder()),
+        MIGRATE => Some(Migrate::builder()),
+        STORAGE => Some(Storage::builder()),
+        PUSH_DATA => Some(SyncData::push_builder()),
+        FETCH_DATA => Some(SyncData::fetch_builder()),
         _ => None,
     }
 }
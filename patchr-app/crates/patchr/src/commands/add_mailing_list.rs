@@ -6,22 +6,31 @@ use log::debug;
 
 use crate::{cli_print, cli_print_error, user_data::user_data::UserData};
 
-use super::{Command, CommandBuilder, CommandBuilderError, ADD_LIST};
+use super::{Command, CommandBuilder, CommandBuilderError, CommandBuilderErrorCode, ADD_LIST};
+
+const THREAD_FLAG: &str = "t";
+const BASE_INFO_FLAG: &str = "b";
 
 pub struct AddMailingList {
     name: String,
     email: String,
+    thread_revisions: bool,
+    base_info: bool,
 }
 pub struct AddMailingListBuilder {
     name: Option<String>,
     email: Option<String>,
+    thread_revisions: bool,
+    base_info: bool,
 }
 
 impl AddMailingList {
-    fn new(name: &str, email: &str) -> Self {
+    fn new(name: &str, email: &str, thread_revisions: bool, base_info: bool) -> Self {
         AddMailingList {
             name: String::from(name),
             email: String::from(email),
+            thread_revisions,
+            base_info,
         }
     }
 
@@ -35,6 +44,8 @@ impl AddMailingListBuilder {
         Self {
             name: None,
             email: None,
+            thread_revisions: false,
+            base_info: false,
         }
     }
 }
@@ -43,10 +54,14 @@ impl Command for AddMailingList {
     fn exec(&self, user_data: &mut UserData) -> ControlFlow<()> {
         debug!("Adding a new list : {} {}", self.name, self.email);
         result_to_control_flow(
-            user_data.add_mailing_list(self.name.as_str(), self.email.as_str()),
+            user_data.add_mailing_list(
+                self.name.as_str(),
+                self.email.as_str(),
+                self.thread_revisions,
+                self.base_info,
+            ),
             |e| {
                 cli_print_error!("{}", e);
-                ()
             },
         )?;
         cli_print!("List added");
@@ -69,16 +84,48 @@ impl CommandBuilder for AddMailingListBuilder {
         Err(CommandBuilderError::unexpected_value(value))
     }
 
+    fn add_flag(&mut self, flag: &str) -> Result<(), CommandBuilderError> {
+        if flag == THREAD_FLAG {
+            self.thread_revisions = true;
+            return Ok(());
+        };
+        if flag == BASE_INFO_FLAG {
+            self.base_info = true;
+            return Ok(());
+        };
+
+        Err(CommandBuilderError::new(
+            CommandBuilderErrorCode::UnknownFlag,
+            String::from(flag),
+        ))
+    }
+
+    fn requires_value(&self, flag: &str) -> Result<bool, CommandBuilderError> {
+        if flag == THREAD_FLAG || flag == BASE_INFO_FLAG {
+            Ok(false)
+        } else {
+            Err(CommandBuilderError::new(
+                CommandBuilderErrorCode::UnknownFlag,
+                String::from(flag),
+            ))
+        }
+    }
+
     fn name(&self) -> &str {
         ADD_LIST
     }
 
     fn build(&self) -> Result<Box<dyn Command>, CommandBuilderError> {
         if let (Some(name), Some(email)) = (self.name.as_ref(), self.email.as_ref()) {
-            Ok(Box::new(AddMailingList::new(name.as_str(), email.as_str())))
+            Ok(Box::new(AddMailingList::new(
+                name.as_str(),
+                email.as_str(),
+                self.thread_revisions,
+                self.base_info,
+            )))
         } else {
             Err(CommandBuilderError::new(
-                super::CommandBuilderErrorCode::MissingValue,
+                CommandBuilderErrorCode::MissingValue,
                 String::from("Missing arguments"),
             ))
         }
@@ -1,15 +1,20 @@
 use std::{fs, process};
 
+use git::{
+    repo::RepoData,
+    series::{RevisionRange, Series},
+    util::{Commit, GitRepo},
+};
 use log::debug;
 
 use crate::{
-    cli_print_error,
+    cli_print, cli_print_error,
     user_data::{root_file::UserConfig, user_data::new_root_tmp_child_path},
 };
 
 fn extract_editor_args(editor: &str) -> Option<(String, Vec<String>)> {
     let mut res = shlex::split(editor)?;
-    if res.len() < 1 {
+    if res.is_empty() {
         return None;
     }
     let editor_bin = res.remove(0); // res.len() >= 1
@@ -53,6 +58,107 @@ pub fn edit_in_text_editor(user_config: &UserConfig, content: &str) -> Option<St
     }
 }
 
+// Commit of a revision given by the user (sha1, branch, HEAD~2, @{u}...),
+// the resolved id is printed when it differs from the revision
+pub fn resolve_commit<'a>(git_repo: &'a GitRepo, revision: &str) -> Option<Commit<'a>> {
+    match git_repo.revparse_commit(revision) {
+        Ok(c) => {
+            if c.id().to_string() != revision {
+                cli_print!("{} is {}", revision, c.id());
+            }
+            Some(c)
+        }
+        Err(e) => {
+            cli_print_error!("Unknown commit: {}", e);
+            None
+        }
+    }
+}
+
+// merge-base(base, branch)..branch for a series kept on a branch
+pub fn branch_range(repo: &RepoData, branch: &str, base: Option<&str>) -> Option<(String, String)> {
+    let Some(git_repo) = repo.open_git_repo() else {
+        cli_print_error!("Repo cannot be opened");
+        return None;
+    };
+    let range = git_repo
+        .branch_and_base(branch, base)
+        .and_then(|(tip, base)| Ok((git_repo.merge_base(&tip, &base)?, tip)));
+    match range {
+        Ok((fork, tip)) => {
+            cli_print!("Using the commits of {}: {}..{}", branch, fork, tip);
+            Some((fork.to_string(), tip.to_string()))
+        }
+        Err(e) => {
+            cli_print_error!("Cannot find the commits of {}: {}", branch, e);
+            None
+        }
+    }
+}
+
+// Resolve first_commit..last_commit, the range must not be empty.
+// The range keeps the resolved ids and the branch, the current one
+// if not given
+pub fn build_revision_range(
+    repo: &RepoData, first_commit: &str, last_commit: &str, branch: Option<&str>,
+) -> Option<RevisionRange> {
+    let Some(git_repo) = repo.open_git_repo() else {
+        cli_print_error!("Repo cannot be opened");
+        return None;
+    };
+    let base = resolve_commit(&git_repo, first_commit)?.id();
+    let last = resolve_commit(&git_repo, last_commit)?.id();
+    let commits = match git_repo.commit_range(&base, &last) {
+        Ok(c) => c,
+        Err(e) => {
+            cli_print_error!("Invalid commit range: {}", e);
+            return None;
+        }
+    };
+    let Some(first) = commits.first() else {
+        cli_print_error!("No commit in {}..{}", first_commit, last_commit);
+        return None;
+    };
+    Some(RevisionRange::new(
+        base.to_string().as_str(),
+        first.id().to_string().as_str(),
+        last.to_string().as_str(),
+        branch
+            .map(String::from)
+            .or_else(|| git_repo.current_branch())
+            .as_deref(),
+    ))
+}
+
+// Commit announced as base-commit in the mails: the fork point of the
+// series from its base, or the base of the range. git format-patch adds
+// the commits in between as prerequisite patches
+pub fn tree_base(repo: &RepoData, series: &Series, range: &RevisionRange) -> Option<String> {
+    let Some(git_repo) = repo.open_git_repo() else {
+        cli_print_error!("Repo cannot be opened");
+        return None;
+    };
+    let range_base = git_repo.revparse(range.base_commit()).ok()?;
+    let last = git_repo.revparse(range.last_commit()).ok()?;
+    let base_tip = match (series.branch(), series.base()) {
+        (Some(branch), base) => git_repo.branch_and_base(branch, base).map(|(_, b)| b).ok(),
+        (None, Some(base)) => git_repo.revparse(base).ok(),
+        (None, None) => None,
+    };
+    let fork = base_tip.and_then(|tip| git_repo.merge_base(&tip, &last).ok());
+    // The base must be an ancestor of the first patch
+    let tree_base = match fork {
+        Some(f)
+            if f.to_string() == range_base.to_string()
+                || git_repo.is_ancestor(&f, &range_base).unwrap_or(false) =>
+        {
+            f
+        }
+        _ => range_base,
+    };
+    Some(tree_base.to_string())
+}
+
 #[macro_export]
 macro_rules! get_repo_or_fail {
     ($user_data:ident, $err:expr) => {
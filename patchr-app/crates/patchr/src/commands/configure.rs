@@ -1,8 +1,12 @@
 use std::{fmt::Display, ops::ControlFlow};
 
+use git::trailers::TrailerOrder;
 use log::debug;
 
-use crate::{cli_print, cli_print_error, user_data::user_data::UserData};
+use crate::{
+    cli_print, cli_print_error,
+    user_data::{root_file::TRANSPORTS, user_data::UserData},
+};
 
 use super::{Command, CommandBuilder, CommandBuilderError, CommandBuilderErrorCode, CONFIGURE};
 
@@ -17,6 +21,10 @@ enum ConfigOption {
     SmtpUser,
     SmtpPort,
     SmtpEncryption,
+    Transport,
+    Maildir,
+    TrailerOrder,
+    AbbrevLength,
 }
 
 pub struct Configure {
@@ -42,6 +50,10 @@ impl TryFrom<&str> for ConfigOption {
             "smtpuser" => Ok(ConfigOption::SmtpUser),
             "smtpport" => Ok(ConfigOption::SmtpPort),
             "smtpenc" => Ok(ConfigOption::SmtpEncryption),
+            "transport" => Ok(ConfigOption::Transport),
+            "maildir" => Ok(ConfigOption::Maildir),
+            "trailerorder" => Ok(ConfigOption::TrailerOrder),
+            "abbrev" => Ok(ConfigOption::AbbrevLength),
             _ => Err(()),
         }
     }
@@ -57,6 +69,10 @@ impl Display for ConfigOption {
             ConfigOption::SmtpUser => "smtp user",
             ConfigOption::SmtpPort => "smtp port",
             ConfigOption::SmtpEncryption => "smtp encryption",
+            ConfigOption::Transport => "transport",
+            ConfigOption::Maildir => "maildir",
+            ConfigOption::TrailerOrder => "trailer order",
+            ConfigOption::AbbrevLength => "abbreviation length",
         };
         f.write_str(name)
     }
@@ -98,13 +114,13 @@ impl Command for Configure {
                     config.set_from_email(value.as_str());
                 }
                 ConfigOption::SendCommand => {
-                    config.set_send_command(self.value.as_ref().map(String::as_str));
+                    config.set_send_command(self.value.as_deref());
                 }
                 ConfigOption::SmtpServer => {
-                    config.set_smtp_server(self.value.as_ref().map(String::as_str));
+                    config.set_smtp_server(self.value.as_deref());
                 }
                 ConfigOption::SmtpUser => {
-                    config.set_smtp_user(self.value.as_ref().map(String::as_str));
+                    config.set_smtp_user(self.value.as_deref());
                 }
                 ConfigOption::SmtpPort => {
                     if let Ok(target) = value.parse::<u16>() {
@@ -112,7 +128,35 @@ impl Command for Configure {
                     }
                 }
                 ConfigOption::SmtpEncryption => {
-                    config.set_smtp_encryption(self.value.as_ref().map(String::as_str));
+                    config.set_smtp_encryption(self.value.as_deref());
+                }
+                ConfigOption::Transport => {
+                    if !TRANSPORTS.contains(&value.as_str()) {
+                        cli_print_error!("Unknown transport, expected one of {:?}", TRANSPORTS);
+                        return ControlFlow::Break(());
+                    }
+                    config.set_transport(self.value.as_deref());
+                }
+                ConfigOption::Maildir => {
+                    config.set_maildir(self.value.as_deref());
+                }
+                ConfigOption::TrailerOrder => {
+                    if TrailerOrder::parse(value).is_none() {
+                        cli_print_error!("Invalid trailer order, e.g. 'Fixes,*,Signed-off-by'");
+                        return ControlFlow::Break(());
+                    }
+                    config.set_trailer_order(self.value.as_deref());
+                }
+                ConfigOption::AbbrevLength => {
+                    match value.parse::<usize>() {
+                        Ok(length) if (4..=40).contains(&length) => {
+                            config.set_abbrev_length(Some(length))
+                        }
+                        _ => {
+                            cli_print_error!("The abbreviation length must be between 4 and 40");
+                            return ControlFlow::Break(());
+                        }
+                    }
                 }
             }
             cli_print!("New value for {}: '{}'", self.option, value.as_str());
@@ -133,6 +177,18 @@ impl Command for Configure {
                 ConfigOption::SmtpEncryption => {
                     config.set_smtp_encryption(None);
                 }
+                ConfigOption::Transport => {
+                    config.set_transport(None);
+                }
+                ConfigOption::Maildir => {
+                    config.set_maildir(None);
+                }
+                ConfigOption::TrailerOrder => {
+                    config.set_trailer_order(None);
+                }
+                ConfigOption::AbbrevLength => {
+                    config.set_abbrev_length(None);
+                }
                 _ => {
                     cli_print_error!("{} cannot be unset", self.option);
                     return ControlFlow::Break(());
@@ -204,7 +260,7 @@ impl CommandBuilder for ConfigureBuilder {
                     format!("Cannot provide a value whith -{}", DEL_FLAG),
                 ));
             }
-            return Ok(Box::new(Configure::new(option, self.value.as_ref().map(String::as_str))));
+            return Ok(Box::new(Configure::new(option, self.value.as_deref())));
         };
 
         if self.delete {
@@ -10,17 +10,23 @@ use super::{Command, CommandBuilder, CommandBuilderError, CREATE_SERIES};
 pub struct CreateSeries {
     name: String,
     title: String,
+    branch: Option<String>,
+    base: Option<String>,
 }
 pub struct CreateSeriesBuilder {
     name: Option<String>,
     title: Option<String>,
+    branch: Option<String>,
+    base: Option<String>,
 }
 
 impl CreateSeries {
-    fn new(name: &str, title: &str) -> Self {
+    fn new(name: &str, title: &str, branch: Option<&str>, base: Option<&str>) -> Self {
         CreateSeries {
             name: String::from(name),
             title: String::from(title),
+            branch: branch.map(String::from),
+            base: base.map(String::from),
         }
     }
 
@@ -34,6 +40,8 @@ impl CreateSeriesBuilder {
         Self {
             name: None,
             title: None,
+            branch: None,
+            base: None,
         }
     }
 }
@@ -53,12 +61,15 @@ impl Command for CreateSeries {
         if short_name.is_none() {
             cli_print!("The repo name cannot be used as a short name")
         }
-        match repo.repo_mut().add_series(
+        let res = repo.repo_mut().add_series(
             self.name.as_str(),
             self.title.as_str(),
             short_name,
-            cv_skel.as_ref().map(String::as_str),
-        ) {
+            cv_skel.as_deref(),
+            self.branch.as_deref(),
+            self.base.as_deref(),
+        );
+        match res {
             Ok(_) => {
                 cli_print!("Series created");
                 ControlFlow::Continue(())
@@ -84,6 +95,16 @@ impl CommandBuilder for CreateSeriesBuilder {
             return Ok(());
         }
 
+        if self.branch.is_none() {
+            self.branch = Some(String::from(value));
+            return Ok(());
+        }
+
+        if self.base.is_none() {
+            self.base = Some(String::from(value));
+            return Ok(());
+        }
+
         Err(CommandBuilderError::unexpected_value(value))
     }
 
@@ -93,7 +114,12 @@ impl CommandBuilder for CreateSeriesBuilder {
 
     fn build(&self) -> Result<Box<dyn Command>, CommandBuilderError> {
         if let (Some(name), Some(title)) = (&self.name, &self.title) {
-            Ok(Box::new(CreateSeries::new(name.as_str(), title.as_str())))
+            Ok(Box::new(CreateSeries::new(
+                name.as_str(),
+                title.as_str(),
+                self.branch.as_deref(),
+                self.base.as_deref(),
+            )))
         } else {
             Err(CommandBuilderError::new(
                 super::CommandBuilderErrorCode::MissingValue,
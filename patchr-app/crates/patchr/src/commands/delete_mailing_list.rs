@@ -38,7 +38,6 @@ impl Command for DeleteMailingList {
         debug!("Deleting a list : {}", self.name);
         result_to_control_flow(user_data.delete_mailing_list(self.name.as_str()), |e| {
             cli_print_error!("{}", e);
-            ()
         })?;
         cli_print!("List deleted");
         ControlFlow::Continue(())
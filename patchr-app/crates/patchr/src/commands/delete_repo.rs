@@ -32,7 +32,6 @@ impl Command for DeleteRepo {
         debug!("Delete repo");
         result_to_control_flow(user_data.delete_repo(), |e| {
             cli_print_error!("{}", e);
-            ()
         })?;
         cli_print!("Repo deleted");
         ControlFlow::Continue(())
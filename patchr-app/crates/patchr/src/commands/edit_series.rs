@@ -1,5 +1,6 @@
 use std::ops::ControlFlow;
 
+use git::{GitError, GitErrorCode};
 use log::debug;
 
 use crate::{
@@ -15,16 +16,25 @@ enum EditSeriesTarget {
     Cv,
     ShortName,
     Cc,
+    Thread,
+    SubjectPrefix,
+    DropV1,
+    Branch,
+    Base,
+    BaseInfo,
 }
 
+// Without a value, the current one is edited in the text editor
 pub struct EditSeries {
     target: EditSeriesTarget,
     series_name: String,
+    value: Option<String>,
 }
 
 pub struct EditSeriesBuilder {
     target: Option<EditSeriesTarget>,
     series_name: Option<String>,
+    value: Option<String>,
 }
 
 impl TryFrom<&str> for EditSeriesTarget {
@@ -37,16 +47,44 @@ impl TryFrom<&str> for EditSeriesTarget {
             "cv" => Ok(EditSeriesTarget::Cv),
             "short" => Ok(EditSeriesTarget::ShortName),
             "cc" => Ok(EditSeriesTarget::Cc),
+            "thread" => Ok(EditSeriesTarget::Thread),
+            "prefix" => Ok(EditSeriesTarget::SubjectPrefix),
+            "v1" => Ok(EditSeriesTarget::DropV1),
+            "branch" => Ok(EditSeriesTarget::Branch),
+            "base" => Ok(EditSeriesTarget::Base),
+            "baseinfo" => Ok(EditSeriesTarget::BaseInfo),
             _ => Err(()),
         }
     }
 }
 
+fn bool_setting_to_string(value: Option<bool>) -> &'static str {
+    match value {
+        Some(true) => "yes",
+        Some(false) => "no",
+        None => "",
+    }
+}
+
+// An empty value unsets the setting
+fn parse_bool_setting(value: &str) -> Result<Option<bool>, GitError> {
+    match value.trim().to_lowercase().as_str() {
+        "" => Ok(None),
+        "yes" | "y" | "true" => Ok(Some(true)),
+        "no" | "n" | "false" => Ok(Some(false)),
+        _ => Err(GitError::new(
+            GitErrorCode::StringFormatError,
+            String::from("Expected 'yes', 'no' or an empty value"),
+        )),
+    }
+}
+
 impl EditSeries {
-    fn new(target: EditSeriesTarget, series_name: &str) -> Self {
+    fn new(target: EditSeriesTarget, series_name: &str, value: Option<&str>) -> Self {
         EditSeries {
             target,
             series_name: String::from(series_name),
+            value: value.map(String::from),
         }
     }
 
@@ -60,6 +98,7 @@ impl EditSeriesBuilder {
         Self {
             target: None,
             series_name: None,
+            value: None,
         }
     }
 }
@@ -79,16 +118,27 @@ impl Command for EditSeries {
             return ControlFlow::Break(());
         };
 
-        let content = match self.target {
-            EditSeriesTarget::Name => series.name(),
-            EditSeriesTarget::Title => series.title(),
-            EditSeriesTarget::Cv => series.cover_letter(),
-            EditSeriesTarget::ShortName => series.short_name(),
-            EditSeriesTarget::Cc => series.cc(),
-        };
-
-        let Some(new_content) = edit_in_text_editor(&user_config, content) else {
-            return ControlFlow::Break(());
+        let new_content = if let Some(value) = self.value.as_ref() {
+            value.clone()
+        } else {
+            let content = match self.target {
+                EditSeriesTarget::Name => series.name(),
+                EditSeriesTarget::Title => series.title(),
+                EditSeriesTarget::Cv => series.cover_letter(),
+                EditSeriesTarget::ShortName => series.short_name(),
+                EditSeriesTarget::Cc => series.cc(),
+                EditSeriesTarget::Thread => bool_setting_to_string(series.thread_revisions()),
+                EditSeriesTarget::SubjectPrefix => series.subject_tag(),
+                EditSeriesTarget::DropV1 => bool_setting_to_string(Some(!series.drop_v1())),
+                EditSeriesTarget::Branch => series.branch().unwrap_or(""),
+                EditSeriesTarget::Base => series.base().unwrap_or(""),
+                EditSeriesTarget::BaseInfo => bool_setting_to_string(series.base_info()),
+            };
+
+            let Some(new_content) = edit_in_text_editor(&user_config, content) else {
+                return ControlFlow::Break(());
+            };
+            new_content
         };
 
         let update_res = match self.target {
@@ -97,6 +147,17 @@ impl Command for EditSeries {
             EditSeriesTarget::Cv => series.set_cover_letter(new_content.as_str()),
             EditSeriesTarget::ShortName => series.set_short_name(new_content.as_str()),
             EditSeriesTarget::Cc => series.set_cc(new_content.as_str()),
+            EditSeriesTarget::Thread => parse_bool_setting(new_content.as_str())
+                .map(|v| series.set_thread_revisions(v)),
+            EditSeriesTarget::SubjectPrefix => series.set_subject_tag(new_content.as_str()),
+            // v1 is in the subjects unless disabled
+            EditSeriesTarget::DropV1 => parse_bool_setting(new_content.as_str())
+                .map(|v| series.set_drop_v1(v == Some(false))),
+            EditSeriesTarget::Branch => series.set_branch(new_content.as_str()),
+            EditSeriesTarget::Base => series.set_base(new_content.as_str()),
+            EditSeriesTarget::BaseInfo => {
+                parse_bool_setting(new_content.as_str()).map(|v| series.set_base_info(v))
+            }
         };
 
         match update_res {
@@ -127,6 +188,11 @@ impl CommandBuilder for EditSeriesBuilder {
             return Ok(());
         }
 
+        if self.value.is_none() {
+            self.value = Some(String::from(value));
+            return Ok(());
+        }
+
         Err(CommandBuilderError::unexpected_value(value))
     }
 
@@ -136,7 +202,7 @@ impl CommandBuilder for EditSeriesBuilder {
 
     fn build(&self) -> Result<Box<dyn Command>, CommandBuilderError> {
         if let (Some(target), Some(series_name)) = (self.target, &self.series_name) {
-            Ok(Box::new(EditSeries::new(target, series_name.as_str())))
+            Ok(Box::new(EditSeries::new(target, series_name.as_str(), self.value.as_deref())))
         } else {
             Err(CommandBuilderError::new(
                 super::CommandBuilderErrorCode::MissingValue,
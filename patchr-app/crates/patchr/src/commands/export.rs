@@ -0,0 +1,250 @@
+use std::{fs, ops::ControlFlow, path::PathBuf};
+
+use common::util::rust::result_to_control_flow;
+use git::{
+    export::{export_mails, ExportFormat},
+    patch_sender::format_series,
+    series::{ExportRecord, SeriesLog},
+};
+use log::debug;
+
+use crate::{
+    cli_print, cli_print_error, get_repo_mut_or_fail,
+    user_data::user_data::{root_tmp_dir_path, UserData},
+};
+
+use super::{
+    common::{branch_range, build_revision_range, tree_base},
+    Command, CommandBuilder, CommandBuilderError, CommandBuilderErrorCode, EXPORT,
+};
+
+const OUTPUT_FLAG: &str = "o";
+const DIRECTORY_FLAG: &str = "d";
+
+pub struct Export {
+    series_name: String,
+    commits: Option<(String, String)>,
+    output: Option<String>,
+    format: ExportFormat,
+}
+
+pub struct ExportBuilder {
+    values: Vec<String>,
+    output: Option<String>,
+    format: ExportFormat,
+}
+
+impl Export {
+    fn new(
+        series_name: &str, commits: Option<(&str, &str)>, output: Option<&str>,
+        format: ExportFormat,
+    ) -> Self {
+        Export {
+            series_name: String::from(series_name),
+            commits: commits.map(|(f, l)| (String::from(f), String::from(l))),
+            output: output.map(String::from),
+            format,
+        }
+    }
+
+    pub fn builder() -> Box<dyn CommandBuilder> {
+        Box::new(ExportBuilder::new())
+    }
+
+    // <series>-v<n>.mbox or <series>-v<n> in the current directory
+    fn default_output(&self, revision: u32) -> String {
+        match self.format {
+            ExportFormat::Mbox => format!("{}-v{}.mbox", self.series_name, revision),
+            ExportFormat::Directory => format!("{}-v{}", self.series_name, revision),
+        }
+    }
+}
+
+impl ExportBuilder {
+    fn new() -> Self {
+        Self {
+            values: Vec::new(),
+            output: None,
+            format: ExportFormat::Mbox,
+        }
+    }
+}
+
+impl Command for Export {
+    fn exec(&self, user_data: &mut UserData) -> ControlFlow<()> {
+        debug!("Export series : {}", self.series_name);
+        let rtmp = result_to_control_flow(root_tmp_dir_path(), |e| {
+            cli_print_error!("{}", e.to_string());
+        })?;
+        // Same default as send, the setting of the list the series was last sent to
+        let list_base_info = user_data
+            .repo()
+            .and_then(|r| r.repo().get_series_by_name(self.series_name.as_str()))
+            .and_then(|s| s.sends().last())
+            .and_then(|s| user_data.find_mailing_list_by_email(s.to()))
+            .is_some_and(|l| l.base_info());
+        let repo = get_repo_mut_or_fail!(user_data);
+
+        let Some(series) = repo.repo().get_series_by_name(self.series_name.as_str()) else {
+            cli_print_error!("Unknown series : {}", self.series_name.as_str());
+            return ControlFlow::Break(());
+        };
+
+        let (first_commit, last_commit) = if let Some((f, l)) = &self.commits {
+            (f.clone(), l.clone())
+        } else if let Some(branch) = series.branch() {
+            let Some(range) = branch_range(repo, branch, series.base()) else {
+                return ControlFlow::Break(());
+            };
+            range
+        } else if let Some(range) = series.current_range() {
+            cli_print!("Using the recorded range {}", range);
+            (String::from(range.base_commit()), String::from(range.last_commit()))
+        } else {
+            cli_print_error!(
+                "No commit range provided and none recorded for v{}",
+                series.current_revision()
+            );
+            return ControlFlow::Break(());
+        };
+        let Some(mut range) =
+            build_revision_range(repo, &first_commit, &last_commit, series.branch())
+        else {
+            return ControlFlow::Break(());
+        };
+        let mut extra_args = Vec::new();
+        if series.base_info().unwrap_or(list_base_info) {
+            let Some(base) = tree_base(repo, series, &range) else {
+                return ControlFlow::Break(());
+            };
+            cli_print!("Base commit: {}", base);
+            extra_args.push(format!("--base={}", base));
+            range.set_tree_base(Some(&base));
+        }
+
+        let output = self
+            .output
+            .clone()
+            .unwrap_or_else(|| self.default_output(series.current_revision()));
+        let output = PathBuf::from(output);
+
+        let exported = series
+            .subject_prefix(None, false, false)
+            .and_then(|prefix| {
+                format_series(
+                    series,
+                    &rtmp,
+                    range.base_commit(),
+                    range.last_commit(),
+                    &prefix,
+                    &extra_args,
+                )
+            })
+            .and_then(|dir| {
+                let res = export_mails(&dir, &output, self.format);
+                let _ = fs::remove_dir_all(&dir);
+                res
+            });
+        let subjects = match exported {
+            Ok(s) => s,
+            Err(e) => {
+                cli_print_error!("Failed to export the series, {}", e);
+                return ControlFlow::Break(());
+            }
+        };
+
+        // the file exists now, canonicalize cannot fail unless it was removed
+        let output = fs::canonicalize(&output).unwrap_or(output);
+        let output = output.to_string_lossy();
+        cli_print!("{} mails exported to {}", subjects.len(), output);
+
+        SeriesLog::export(series, &output);
+        let record =
+            ExportRecord::new(series.current_revision(), self.format, &output, range, subjects);
+        // The series exists, it was found above
+        repo.repo_mut()
+            .get_series_by_name_mut(self.series_name.as_str())
+            .unwrap()
+            .add_export_record(record);
+        ControlFlow::Continue(())
+    }
+}
+
+impl CommandBuilder for ExportBuilder {
+    fn add_value(&mut self, value: &str) -> Result<(), CommandBuilderError> {
+        // series, [first commit, last commit]
+        if self.values.len() >= 3 {
+            return Err(CommandBuilderError::unexpected_value(value));
+        }
+        self.values.push(String::from(value));
+        Ok(())
+    }
+
+    fn add_flag(&mut self, flag: &str) -> Result<(), CommandBuilderError> {
+        if flag == DIRECTORY_FLAG {
+            self.format = ExportFormat::Directory;
+            return Ok(());
+        }
+        Err(CommandBuilderError::new(
+            CommandBuilderErrorCode::UnknownFlag,
+            String::from(flag),
+        ))
+    }
+
+    fn add_flag_and_value(&mut self, flag: &str, value: &str) -> Result<(), CommandBuilderError> {
+        if flag != OUTPUT_FLAG {
+            return Err(CommandBuilderError::new(
+                CommandBuilderErrorCode::UnknownFlag,
+                String::from(flag),
+            ));
+        }
+        if self.output.is_some() {
+            return Err(CommandBuilderError::new(
+                CommandBuilderErrorCode::UnexpectedValue,
+                format!("reused flag -{}", flag),
+            ));
+        }
+        self.output = Some(String::from(value));
+        Ok(())
+    }
+
+    fn requires_value(&self, flag: &str) -> Result<bool, CommandBuilderError> {
+        match flag {
+            OUTPUT_FLAG => Ok(true),
+            DIRECTORY_FLAG => Ok(false),
+            _ => Err(CommandBuilderError::new(
+                CommandBuilderErrorCode::UnknownFlag,
+                String::from(flag),
+            )),
+        }
+    }
+
+    fn name(&self) -> &str {
+        EXPORT
+    }
+
+    fn build(&self) -> Result<Box<dyn Command>, CommandBuilderError> {
+        let commits = match self.values.as_slice() {
+            [_] => None,
+            [_, first_commit, last_commit] => Some((first_commit.as_str(), last_commit.as_str())),
+            [_, _] => {
+                return Err(CommandBuilderError::new(
+                    CommandBuilderErrorCode::MissingValue,
+                    String::from("Missing first or last commit sha1"),
+                ))
+            }
+            _ => {
+                return Err(CommandBuilderError::new(
+                    CommandBuilderErrorCode::MissingValue,
+                    String::from("Missing series name"),
+                ))
+            }
+        };
+        Ok(Box::new(Export::new(
+            self.values[0].as_str(),
+            commits,
+            self.output.as_deref(),
+            self.format,
+        )))
+    }
+}
@@ -28,6 +28,7 @@ patchr is a git patch series management program.
 Repo:
     - Register a new repo
         patchr register <name>
+            the repos using SHA-256 object ids are not supported
 
     - List repos
         patchr repos
@@ -35,38 +36,87 @@ Repo:
     - Delete the current repo
         patchr delrepo
 
+    - Store the series of the current repo in the repo itself, or in the data directory
+        patchr storage <git|file>
+            git: the series are committed to refs/patchr/data, merged with the
+            series already there
+
+    - Push the series stored in git, the remote is origin by default
+        patchr pushdata [remote]
+
+    - Fetch the series stored in git and merge them with the local ones
+        patchr fetchdata [remote]
+            the series changed on one side take this version, the local version
+            of the series changed on both sides is kept and the remote one is
+            copied to <series>-theirs
+
 Commits:
+    The commits can be given as any git revision (sha1, branch, HEAD~2, @{{u}}...),
+    the resolved ids are printed
+
     - Tag a commit
-        patchr tag <commit> <tag> <value>
-            tag: if the value is 'rb' (Reviewed-by) or 'so' (Signed-off-by),
+        patchr tag <commit> <tag> [value]
+            tag: if the value is an alias, 'rb' (Reviewed-by) or 'so' (Signed-off-by),
                  it will be replaced with the expanded tag name.
                  Otherwise, the tag is used as is.
+            value: a person nickname is replaced with its identity, the from
+                   address is used for 'so' if not provided
+            fixes: the value is the fixed commit (sha1, branch, HEAD~2...), which
+                   must be an ancestor, the tag is 'Fixes: <sha1> ("<subject>")'
+                   with the sha1 abbreviated to the abbrev configuration
+            the tag is added to the trailer block of the message like
+            git interpret-trailers does, see the trailerorder configuration
+
+    - Tag a range of commits
+        patchr tag <c1> <c2> <tag> <value>
+        patchr tag <c1>..<c2> <tag> [value]
+        patchr tag --range <c1>..<c2> <tag> [value]
+            c1, c2: the commits after c1 up to c2 are tagged, like patchr send
+            the commits are rewritten at once, the new c2 is printed
 
     - Un-tag a commit
         patchr untag <commit> <tag> [value]
             tag: see the tagging command
             value: if not provided, remove all the lines that match the tag
 
+    - Undo the last rewrite of tag, untag or trailers
+        patchr undo
+            the commits are rewritten only with a clean worktree, on a branch,
+            and the previous HEAD is kept in refs/patchr/backup/<date>/<branch>
+            undo restores it on the same branch unless commits were added
+            since the rewrite, the last 10 rewrites are kept
+
 Series:
     The following commands can only be called from a registered repo
 
     - List series
         patchr list
+            the series kept on a branch are listed with the number of commits
+            ahead and behind their base
 
     - Show a series
         patchr show [-v] <series>
             -v verbose
 
     - Create a new series
-        patchr create <name> <title>
+        patchr create <name> <title> [branch [base]]
+            branch: branch holding the commits of the series
+            base: revision the branch is based on, e.g. origin/main, the
+                  upstream of the branch if not provided
 
     - Delete a series
         patchr delete <name>
 
     - Edit a series
-        patchr edit <target> <series>
-            target: 'cv', 'cc' (see send command for format), 'title', 'name' or 'short'
+        patchr edit <target> <series> [value]
+            target: 'cv', 'cc' (see send command for format), 'title', 'name', 'short',
+                    'thread' ('yes', 'no' or empty to use the mailing list setting),
+                    'prefix' (start of the subject prefix, e.g. 'PATCH net-next',
+                    empty for PATCH), 'v1' ('no' to leave v1 out of the subjects),
+                    'branch' or 'base' (see create, empty to unset),
+                    'baseinfo' ('yes', 'no' or empty to use the mailing list setting)
             series: series name
+            value: new value, edited in the text editor if not provided
 
     - Add a revision
         patchr addrev <series>
@@ -80,21 +130,96 @@ Series:
             rev: number of the revision to edit
 
     - Send a series
-        patchr send <series> <c1> <c2> <to> [-c email1,...]
+        patchr send <series> [<c1> <c2>] <to> [-c email1,...] [-i] [--dry-run|--preview]
+                    [--prefix <prefix>] [--rfc] [--resend]
             c1: initial commit
             c2: last commit
+                if not provided, merge-base(base, branch)..branch is used for
+                a series kept on a branch, otherwise the range recorded when the
+                current revision was last sent
             to: target mailing list or mailing list name
             -c: allows to add addresses to the CC field (separated by commas)
+            -i: select the commits interactively
+            --dry-run: generate the mails, edit the cover letter and print
+                       their headers without sending them, the mails are kept
+                       in the tmp directory
+            --preview: print the headers and edit the cover letter, then
+                       ask for a confirmation before sending
+            --prefix: replaces the subject prefix of the series for this send
+            --rfc: adds RFC before the subject prefix
+            --resend: adds RESEND after the subject prefix
+                without these options, a revision sent again keeps the prefix
+                it was last sent with
+            when a send fails, the mails are kept and the delivered ones recorded
+            with baseinfo, the cover letter ends with the base-commit the series
+            applies to, the fork point from the base of the series or c1, and
+            the prerequisite-patch-id of the commits in between
+
+    - Resume a failed send
+        patchr send --resume <series>
+            sends the mails that were not delivered, with the recipients and
+            message ids of the failed send
+
+    - Compare two revisions of a series
+        patchr rangediff <series> [<old> <new>]
+            old, new: revision number (uses the range recorded when it was sent)
+                      or commit range '<c1>..<c2>'
+            Without arguments, compare the last two revisions that were sent
+
+    - Export the current revision instead of sending it
+        patchr export <series> [<c1> <c2>] [-o output] [-d]
+            c1, c2: commit range, the branch of the series or the recorded range
+                    is used if not provided, see send
+            -o: output path, <series>-v<revision>.mbox by default
+            -d: write a directory of numbered .patch files instead of an mbox
+            the base-commit is given when the series enables baseinfo, or by default
+            when the list the series was last sent to does
+
+    - Add the review trailers given in the replies to the last sent revision
+        patchr trailers <series> <mailbox> [<c1> <c2>] [--dry-run]
+            mailbox: mbox file or Maildir with the replies
+            c1, c2: commits to update, the range recorded when the revision
+                    was sent is used and moved to the updated commits if not provided
+            --dry-run: print the trailers without updating the commits
+            Reviewed-by, Acked-by and Tested-by lines are matched to a patch by
+            the In-Reply-To header or the subject, the ones given to the cover
+            letter apply to every patch
+
+    - Show the send and export history of a series
+        patchr history [-j] <series>
+            -j: print the history as JSON
 
 User configuration:
     - Edit global configuration
         patchr config [-d] <target> [value]
-            target: editor, sendcmd, from, smtpserver, smtpuser, smtpport, smtpenc
+            target: editor, sendcmd, from, smtpserver, smtpuser, smtpport, smtpenc,
+                    transport, maildir, trailerorder, abbrev
             value: new value (not compatible with -d)
             -d: delete the current value
+            transport: sendemail (git send-email, default), smtp (built-in client
+                       using the smtp settings, smtpenc is ssl or tls for STARTTLS),
+                       sendmail (pipes each mail to sendcmd, sendmail by default)
+                       or maildir (writes each mail to the maildir directory)
+                       the smtp password is read from PATCHR_SMTP_PASSWORD or prompted
+            trailerorder: position of the tags added to the trailer block of the
+                          commits, e.g. 'Fixes,*,Signed-off-by' where * stands for
+                          the other tags, by default they are added at the end
+            abbrev: length of the commit ids of the Fixes tags, 12 by default
+
+    - Manage the trailer aliases, e.g. 'ack' for 'Acked-by'
+        patchr alias [-d] [<alias> [tag]]
+            without arguments, list the aliases
+            -d: delete the alias
+
+    - Manage the people directory, e.g. 'alice' for 'Alice Example <alice@example.org>'
+        patchr person [-d] [<nickname> [identity]]
+            without arguments, list the people
+            -d: delete the person
 
     - Register a mailing list
-        patchr addlist <listname> <email address>
+        patchr addlist <listname> <email address> [-t] [-b]
+            -t: send new revisions as replies to the cover letter of the previous one
+            -b: give the base-commit and prerequisite patches of the series in the mails
 
     - Delete a mailing list
         patchr dellist <list name>
@@ -103,9 +228,23 @@ User configuration:
         patchr cvskel
 
 Patchr operations:
+    The data is kept in $XDG_DATA_HOME/patchr (~/.local/share/patchr), the
+    configuration in $XDG_CONFIG_HOME/patchr (~/.config/patchr) and the
+    temporary files in $XDG_RUNTIME_DIR/patchr or $TMPDIR. PATCHR_HOME puts
+    them all in one directory. The data of ~/.patchr is moved on the first run
+
+    The data is locked while a command runs, list, repos, show, history and
+    rangediff share it. Another command waits up to PATCHR_LOCK_TIMEOUT
+    seconds (10 by default) for the data to be released
+
     - Delete temporary files
         patchr cleantmp
 
+    - Update the data written by an older version
+        patchr migrate [--dry-run]
+            the files are saved in a tar archive of the backups directory first
+            --dry-run: list the files to update without writing them
+
 Global flags:
     -v verbose (increase verbosity, can be used multiple times)
 
@@ -115,4 +254,8 @@ Global flags:
         );
         ControlFlow::Break(()) // ignore the other commands
     }
+
+    fn read_only(&self) -> bool {
+        true
+    }
 }
@@ -0,0 +1,198 @@
+use std::ops::ControlFlow;
+
+use git::series::{RevisionRange, Series};
+use log::debug;
+use serde_json::json;
+
+use crate::{cli_print, cli_print_error, get_repo_or_fail, user_data::user_data::UserData};
+
+use super::{Command, CommandBuilder, CommandBuilderError, CommandBuilderErrorCode, HISTORY};
+
+const JSON_FLAG: &str = "j";
+
+pub struct History {
+    series_name: String,
+    json: bool,
+}
+
+pub struct HistoryBuilder {
+    series_name: Option<String>,
+    json: bool,
+}
+
+impl History {
+    fn new(series_name: &str, json: bool) -> Self {
+        History {
+            series_name: String::from(series_name),
+            json,
+        }
+    }
+
+    pub fn builder() -> Box<dyn CommandBuilder> {
+        Box::new(HistoryBuilder::new())
+    }
+
+    // Sends and exports sorted by date, each followed by its subjects
+    fn print_table(series: &Series) {
+        let mut rows: Vec<HistoryRow> = series
+            .sends()
+            .iter()
+            .map(|r| HistoryRow {
+                date: r.date(),
+                local_date: r.local_date(),
+                revision: r.revision(),
+                kind: String::from("send"),
+                from: r.from(),
+                to: r.to(),
+                cc: r.cc().unwrap_or("-"),
+                patches: r.patch_ids().len(),
+                range: r.range(),
+                subjects: r.subjects(),
+            })
+            .chain(series.exports().iter().map(|r| HistoryRow {
+                date: r.date(),
+                local_date: r.local_date(),
+                revision: r.revision(),
+                kind: format!("export ({})", r.format()),
+                from: "-",
+                to: r.path(),
+                cc: "-",
+                // the cover letter is always exported
+                patches: r.subjects().len().saturating_sub(1),
+                range: r.range(),
+                subjects: r.subjects(),
+            }))
+            .collect();
+        rows.sort_by(|a, b| a.date.cmp(b.date));
+
+        cli_print!(
+            "{:<20} {:<4} {:<18} {:<30} {:<30} {:<30} {:<7} Range",
+            "Date",
+            "Rev",
+            "Kind",
+            "From",
+            "To",
+            "Cc",
+            "Patches"
+        );
+        rows.iter().for_each(HistoryRow::print);
+    }
+}
+
+struct HistoryRow<'a> {
+    date: &'a str,
+    local_date: String,
+    revision: u32,
+    kind: String,
+    from: &'a str,
+    to: &'a str,
+    cc: &'a str,
+    patches: usize,
+    range: &'a RevisionRange,
+    subjects: &'a [String],
+}
+
+impl HistoryRow<'_> {
+    fn print(&self) {
+        cli_print!(
+            "{:<20} v{:<3} {:<18} {:<30} {:<30} {:<30} {:<7} {}",
+            self.local_date,
+            self.revision,
+            self.kind,
+            self.from,
+            self.to,
+            self.cc,
+            self.patches,
+            self.range
+        );
+        self.subjects
+            .iter()
+            .for_each(|s| cli_print!("    {}", s));
+    }
+}
+
+impl HistoryBuilder {
+    fn new() -> Self {
+        Self {
+            series_name: None,
+            json: false,
+        }
+    }
+}
+
+impl Command for History {
+    fn exec(&self, user_data: &mut UserData) -> ControlFlow<()> {
+        debug!("History : {}", self.series_name);
+        let repo = get_repo_or_fail!(user_data);
+
+        let Some(series) = repo.repo().get_series_by_name(self.series_name.as_str()) else {
+            cli_print_error!("Unknown series : {}", self.series_name.as_str());
+            return ControlFlow::Break(());
+        };
+
+        if self.json {
+            let history = json!({"sends": series.sends(), "exports": series.exports()});
+            match serde_json::to_string_pretty(&history) {
+                Ok(s) => cli_print!("{}", s),
+                Err(e) => {
+                    cli_print_error!("Failed to serialize the history : {}", e);
+                    return ControlFlow::Break(());
+                }
+            }
+        } else if series.sends().is_empty() && series.exports().is_empty() {
+            cli_print!("{} has not been sent or exported yet", self.series_name);
+        } else {
+            Self::print_table(series);
+        }
+        ControlFlow::Continue(())
+    }
+
+    fn read_only(&self) -> bool {
+        true
+    }
+}
+
+impl CommandBuilder for HistoryBuilder {
+    fn add_value(&mut self, value: &str) -> Result<(), CommandBuilderError> {
+        if self.series_name.is_some() {
+            return Err(CommandBuilderError::unexpected_value(value));
+        }
+        self.series_name = Some(String::from(value));
+        Ok(())
+    }
+
+    fn add_flag(&mut self, flag: &str) -> Result<(), CommandBuilderError> {
+        if flag == JSON_FLAG {
+            self.json = true;
+            return Ok(());
+        }
+        Err(CommandBuilderError::new(
+            CommandBuilderErrorCode::UnknownFlag,
+            String::from(flag),
+        ))
+    }
+
+    fn requires_value(&self, flag: &str) -> Result<bool, CommandBuilderError> {
+        if flag == JSON_FLAG {
+            return Ok(false);
+        }
+        Err(CommandBuilderError::new(
+            CommandBuilderErrorCode::UnknownFlag,
+            String::from(flag),
+        ))
+    }
+
+    fn name(&self) -> &str {
+        HISTORY
+    }
+
+    fn build(&self) -> Result<Box<dyn Command>, CommandBuilderError> {
+        let Some(series_name) = self.series_name.as_deref() else {
+            return Err(CommandBuilderError::new(
+                CommandBuilderErrorCode::MissingValue,
+                String::from("Missing series name"),
+            ));
+        };
+        Ok(Box::new(History::new(series_name, self.json)))
+    }
+}
@@ -39,6 +39,10 @@ impl Command for ListRepos {
         });
         ControlFlow::Continue(())
     }
+
+    fn read_only(&self) -> bool {
+        true
+    }
 }
 
 impl CommandBuilder for ListReposBuilder {
@@ -30,11 +30,35 @@ impl Command for ListSeries {
         debug!("List series");
 
         let repo = get_repo_or_fail!(user_data);
+        let git_repo = repo.open_git_repo();
         repo.repo().series().iter().for_each(|s| {
-            cli_print!("- {} (v{})", s.name(), s.current_revision());
+            let Some(branch) = s.branch() else {
+                cli_print!("- {} (v{})", s.name(), s.current_revision());
+                return;
+            };
+            // The series is listed even if its branch cannot be found
+            let status = git_repo.as_ref().map(|r| {
+                r.branch_and_base(branch, s.base())
+                    .and_then(|(tip, base)| r.ahead_behind(&tip, &base))
+            });
+            let status = match status {
+                Some(Ok((ahead, behind))) => format!(
+                    "{} ahead, {} behind {}",
+                    ahead,
+                    behind,
+                    s.base().unwrap_or("upstream")
+                ),
+                Some(Err(e)) => e.to_string(),
+                None => String::from("repo cannot be opened"),
+            };
+            cli_print!("- {} (v{}) {}: {}", s.name(), s.current_revision(), branch, status);
         });
         ControlFlow::Continue(())
     }
+
+    fn read_only(&self) -> bool {
+        true
+    }
 }
 
 impl CommandBuilder for ListSeriesBuilder {
@@ -0,0 +1,110 @@
+use std::ops::ControlFlow;
+
+use common::{constants::PROJECT_VERSION, util::rust::result_to_control_flow};
+use log::debug;
+
+use crate::{
+    cli_print, cli_print_error,
+    user_data::{
+        lock::{lock_data_dir, LockMode},
+        migration::{backup_data_files, outdated_data_files, write_data_files},
+        user_data::UserData,
+    },
+};
+
+use super::{Command, CommandBuilder, CommandBuilderError, CommandBuilderErrorCode, MIGRATE};
+
+const DRY_RUN_FLAG: &str = "dry-run";
+
+// Update the data files written by an older version, it runs before the
+// data is loaded since it cannot be read until then
+pub struct Migrate {
+    dry_run: bool,
+}
+
+pub struct MigrateBuilder {
+    dry_run: bool,
+}
+
+impl Migrate {
+    pub fn builder() -> Box<dyn CommandBuilder> {
+        Box::new(MigrateBuilder { dry_run: false })
+    }
+
+    fn migrate(&self) -> ControlFlow<()> {
+        debug!("Migrate");
+        let _lock = result_to_control_flow(lock_data_dir(LockMode::Exclusive), |e| {
+            cli_print_error!("{}", e);
+        })?;
+        let files = result_to_control_flow(outdated_data_files(), |e| {
+            cli_print_error!("{}", e);
+        })?;
+        if files.is_empty() {
+            cli_print!("Already up to date");
+            return ControlFlow::Continue(());
+        }
+        for f in files.iter() {
+            cli_print!("{}: {} to {}", f.path().to_string_lossy(), f.version(), PROJECT_VERSION);
+        }
+        if self.dry_run {
+            cli_print!("Nothing was written");
+            return ControlFlow::Continue(());
+        }
+
+        let backup = result_to_control_flow(backup_data_files(&files), |e| {
+            cli_print_error!("Failed to create the backup, {}", e);
+        })?;
+        cli_print!("Backup created : {}", backup.to_string_lossy());
+        result_to_control_flow(write_data_files(&files), |e| {
+            cli_print_error!("{}", e);
+            cli_print_error!("Migration failed, consider restoring from the backup");
+        })?;
+        cli_print!("Files updated");
+        ControlFlow::Continue(())
+    }
+}
+
+impl Command for Migrate {
+    fn exec(&self, _user_data: &mut UserData) -> ControlFlow<()> {
+        self.migrate()
+    }
+
+    fn exec_without_user_data(&self) -> Option<ControlFlow<()>> {
+        Some(self.migrate())
+    }
+}
+
+impl CommandBuilder for MigrateBuilder {
+    fn add_flag(&mut self, flag: &str) -> Result<(), CommandBuilderError> {
+        if flag == DRY_RUN_FLAG {
+            self.dry_run = true;
+            return Ok(());
+        };
+
+        Err(CommandBuilderError::new(
+            CommandBuilderErrorCode::UnknownFlag,
+            String::from(flag),
+        ))
+    }
+
+    fn requires_value(&self, flag: &str) -> Result<bool, CommandBuilderError> {
+        if flag == DRY_RUN_FLAG {
+            Ok(false)
+        } else {
+            Err(CommandBuilderError::new(
+                CommandBuilderErrorCode::UnknownFlag,
+                String::from(flag),
+            ))
+        }
+    }
+
+    fn name(&self) -> &str {
+        MIGRATE
+    }
+
+    fn build(&self) -> Result<Box<dyn Command>, CommandBuilderError> {
+        Ok(Box::new(Migrate {
+            dry_run: self.dry_run,
+        }))
+    }
+}
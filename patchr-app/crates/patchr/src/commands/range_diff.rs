@@ -0,0 +1,183 @@
+use std::ops::ControlFlow;
+
+use git::{range_diff::range_diff, series::Series, util::CommitId};
+use log::debug;
+
+use crate::{
+    cli_print, cli_print_error, get_repo_or_fail, open_git_repo_or_fail,
+    user_data::user_data::UserData,
+};
+
+use super::{common::resolve_commit, Command, CommandBuilder, CommandBuilderError, RANGE_DIFF};
+
+// A side of the comparison, either a revision of the
+// series (its recorded range is used) or a commit range
+#[derive(Clone)]
+enum RangeSpec {
+    Revision(usize),
+    Commits(String, String),
+}
+
+pub struct RangeDiff {
+    series_name: String,
+    ranges: Option<(RangeSpec, RangeSpec)>,
+}
+
+pub struct RangeDiffBuilder {
+    series_name: Option<String>,
+    ranges: Vec<RangeSpec>,
+}
+
+impl TryFrom<&str> for RangeSpec {
+    type Error = ();
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        if let Some((first, last)) = value.split_once("..") {
+            if first.is_empty() || last.is_empty() {
+                return Err(());
+            }
+            return Ok(RangeSpec::Commits(String::from(first), String::from(last)));
+        }
+        value.parse::<usize>().map(RangeSpec::Revision).map_err(|_| ())
+    }
+}
+
+impl RangeSpec {
+    fn resolve(&self, series: &Series) -> Option<(String, String)> {
+        match self {
+            RangeSpec::Commits(first, last) => Some((first.clone(), last.clone())),
+            RangeSpec::Revision(n) => {
+                let Some(revision) = series.revision(*n) else {
+                    cli_print_error!("Unknown revision : {}", n);
+                    return None;
+                };
+                let Some(range) = revision.range() else {
+                    cli_print_error!("No range recorded for v{}, provide the commits instead", n);
+                    return None;
+                };
+                Some((String::from(range.base_commit()), String::from(range.last_commit())))
+            }
+        }
+    }
+}
+
+impl RangeDiff {
+    fn new(series_name: &str, ranges: Option<(RangeSpec, RangeSpec)>) -> Self {
+        RangeDiff {
+            series_name: String::from(series_name),
+            ranges,
+        }
+    }
+
+    pub fn builder() -> Box<dyn CommandBuilder> {
+        Box::new(RangeDiffBuilder::new())
+    }
+
+    // Compare the last two revisions that have a recorded range
+    fn default_ranges(series: &Series) -> Option<(RangeSpec, RangeSpec)> {
+        let mut recorded = (1..=series.current_revision() as usize)
+            .rev()
+            .filter(|&n| series.revision(n).is_some_and(|r| r.range().is_some()));
+        let new = recorded.next()?;
+        let old = recorded.next()?;
+        Some((RangeSpec::Revision(old), RangeSpec::Revision(new)))
+    }
+}
+
+impl RangeDiffBuilder {
+    fn new() -> Self {
+        Self {
+            series_name: None,
+            ranges: Vec::new(),
+        }
+    }
+}
+
+impl Command for RangeDiff {
+    fn exec(&self, user_data: &mut UserData) -> ControlFlow<()> {
+        debug!("Range diff {}", self.series_name);
+        let repo = get_repo_or_fail!(user_data);
+
+        let Some(series) = repo.repo().get_series_by_name(self.series_name.as_str()) else {
+            cli_print_error!("Unknown series : {}", self.series_name.as_str());
+            return ControlFlow::Break(());
+        };
+
+        let Some((old, new)) = self.ranges.clone().or_else(|| Self::default_ranges(series)) else {
+            cli_print_error!("Less than two revisions have a recorded range, provide the ranges");
+            return ControlFlow::Break(());
+        };
+        let (Some(old), Some(new)) = (old.resolve(series), new.resolve(series)) else {
+            return ControlFlow::Break(());
+        };
+
+        let git_repo = open_git_repo_or_fail!(repo);
+        let Some(ids) = [&old.0, &old.1, &new.0, &new.1]
+            .iter()
+            .map(|c| resolve_commit(&git_repo, c).map(|c| c.id()))
+            .collect::<Option<Vec<CommitId>>>()
+        else {
+            return ControlFlow::Break(());
+        };
+
+        match range_diff(&git_repo, (&ids[0], &ids[1]), (&ids[2], &ids[3])) {
+            Ok(entries) => {
+                cli_print!("old: {}..{}", ids[0], ids[1]);
+                cli_print!("new: {}..{}", ids[2], ids[3]);
+                entries.iter().for_each(|e| cli_print!("{}", e));
+                ControlFlow::Continue(())
+            }
+            Err(e) => {
+                cli_print_error!("Failed to compare the ranges: {}", e);
+                ControlFlow::Break(())
+            }
+        }
+    }
+
+    fn read_only(&self) -> bool {
+        true
+    }
+}
+
+impl CommandBuilder for RangeDiffBuilder {
+    fn add_value(&mut self, value: &str) -> Result<(), CommandBuilderError> {
+        if self.series_name.is_none() {
+            self.series_name = Some(String::from(value));
+            return Ok(());
+        }
+
+        if self.ranges.len() < 2 {
+            let Ok(range) = RangeSpec::try_from(value) else {
+                return Err(CommandBuilderError::new(
+                    super::CommandBuilderErrorCode::InvalidValues,
+                    format!("Expected a revision number or a <c1>..<c2> range, found '{}'", value),
+                ));
+            };
+            self.ranges.push(range);
+            return Ok(());
+        }
+
+        Err(CommandBuilderError::unexpected_value(value))
+    }
+
+    fn name(&self) -> &str {
+        RANGE_DIFF
+    }
+
+    fn build(&self) -> Result<Box<dyn Command>, CommandBuilderError> {
+        let Some(series_name) = self.series_name.as_ref() else {
+            return Err(CommandBuilderError::new(
+                super::CommandBuilderErrorCode::MissingValue,
+                String::from("Missing series name"),
+            ));
+        };
+        match self.ranges.as_slice() {
+            [] => Ok(Box::new(RangeDiff::new(series_name, None))),
+            [old, new] => Ok(Box::new(RangeDiff::new(series_name, Some((old.clone(), new.clone()))))),
+            _ => Err(CommandBuilderError::new(
+                super::CommandBuilderErrorCode::MissingValue,
+                String::from("Missing new range"),
+            )),
+        }
+    }
+}
@@ -1,24 +1,55 @@
-use std::{io, ops::ControlFlow};
+use std::{
+    env, fs, io,
+    ops::ControlFlow,
+    path::{Path, PathBuf},
+};
 
 use common::util::{input::sanitize_cc_list, misc::LINE_SEP, rust::result_to_control_flow};
 use git::{
-    patch_sender::{GitPatchSender, PatchSender},
+    mail::{mail_files, MailHeaders},
+    maildir_sender::MaildirPatchSender,
+    patch_sender::{
+        delivered_mails, GitPatchSender, PatchSender, SendParams, SentMessages,
+        COVER_LETTER_FILE_NAME,
+    },
     repo::RepoData,
-    series::SeriesLog,
+    sendmail_sender::SendmailPatchSender,
+    series::{PendingSend, SendRecord, Series, SeriesLog},
+    smtp_sender::SmtpPatchSender,
+    GitError, GitErrorCode,
 };
 use log::debug;
 
 use crate::{
-    cli_print, cli_print_error, get_repo_or_fail, user_data::user_data::{root_tmp_dir_path, UserData}
+    cli_print, cli_print_error,
+    environment::PATCHR_SMTP_PASSWORD,
+    get_repo_mut_or_fail,
+    user_data::{
+        root_file::{
+            UserConfig, TRANSPORT_MAILDIR, TRANSPORT_SENDMAIL, TRANSPORT_SEND_EMAIL, TRANSPORT_SMTP,
+        },
+        user_data::{pending_sends_dir_path, root_tmp_dir_path, UserData},
+    },
 };
 
-use super::{Command, CommandBuilder, CommandBuilderError, SEND_SERIES};
+use super::{
+    common::{branch_range, build_revision_range, edit_in_text_editor, tree_base},
+    Command, CommandBuilder, CommandBuilderError, SEND_SERIES,
+};
 
 const CC_FLAG: &str = "c";
 const INTERACTIVE_FLAG: &str = "i";
+const DRY_RUN_FLAG: &str = "dry-run";
+const PREVIEW_FLAG: &str = "preview";
+const RESUME_FLAG: &str = "resume";
+const PREFIX_FLAG: &str = "prefix";
+const RFC_FLAG: &str = "rfc";
+const RESEND_FLAG: &str = "resend";
 
 const YES_KEY: &str = "y";
 
+const DEFAULT_SENDMAIL_COMMAND: &str = "sendmail";
+
 pub struct SendSeries {
     series_name: String,
     first_commit: Option<String>,
@@ -26,26 +57,52 @@ pub struct SendSeries {
     to_email: String,
     cc: Option<String>,
     interactive: bool,
+    mode: SendMode,
+    subject_tag: Option<String>,
+    rfc: bool,
+    resend: bool,
 }
 
+#[derive(Clone, Copy, PartialEq)]
+enum SendMode {
+    Send,
+    // keep the generated mails without sending them
+    DryRun,
+    // review and edit the mails before sending them
+    Preview,
+    // send the remaining mails of a failed send
+    Resume,
+}
+
+// The commits are optional, the values are only
+// assigned once all of them are known
 pub struct SendSeriesBuilder {
-    series_name: Option<String>,
-    first_commit: Option<String>,
-    last_commit: Option<String>,
-    to_email: Option<String>,
+    values: Vec<String>,
     cc: Option<String>,
     interactive: bool,
+    dry_run: bool,
+    preview: bool,
+    resume: bool,
+    subject_tag: Option<String>,
+    rfc: bool,
+    resend: bool,
 }
 
 impl SendSeries {
-    fn new(series_name: &str, to_email: &str, cc: Option<&str>, interactive: bool) -> Self {
+    fn new(
+        series_name: &str, to_email: &str, cc: Option<&str>, interactive: bool, mode: SendMode,
+    ) -> Self {
         SendSeries {
             series_name: String::from(series_name),
             first_commit: None,
             last_commit: None,
             to_email: String::from(to_email),
-            cc: cc.map(|c| String::from(c)),
+            cc: cc.map(String::from),
             interactive,
+            mode,
+            subject_tag: None,
+            rfc: false,
+            resend: false,
         }
     }
 
@@ -58,14 +115,96 @@ impl SendSeries {
         self.last_commit = Some(String::from(last_commit));
     }
 
-    fn get_to_email<'a>(&'a self, user_data: &'a UserData) -> &'a str {
+    fn set_subject_prefix_options(&mut self, subject_tag: Option<&str>, rfc: bool, resend: bool) {
+        self.subject_tag = subject_tag.map(String::from);
+        self.rfc = rfc;
+        self.resend = resend;
+    }
+
+    // Returns the address, whether revisions should be threaded and whether
+    // the mails give the base commit
+    fn get_to_email<'a>(&'a self, user_data: &'a UserData) -> (&'a str, bool, bool) {
         if let Some(list) = user_data.find_mailing_list(self.to_email.as_str()) {
             cli_print!("Found a mailing list : {} {}", list.name(), list.email());
-            list.email()
+            (list.email(), list.thread_revisions(), list.base_info())
         } else {
             // it's up to the sender to check if the address is valid
-            self.to_email.as_str()
+            (self.to_email.as_str(), false, false)
+        }
+    }
+
+    // Without prefix options, sending a revision again reuses the prefix
+    // it was last sent with
+    fn subject_prefix(&self, series: &Series) -> Option<String> {
+        if self.subject_tag.is_none() && !self.rfc && !self.resend {
+            let current = series.current_revision();
+            if let Some(r) = series
+                .sends()
+                .iter()
+                .rev()
+                .find(|r| r.revision() == current)
+            {
+                cli_print!("Using the recorded subject prefix [{}]", r.subject_prefix());
+                return Some(String::from(r.subject_prefix()));
+            }
+        }
+        match series.subject_prefix(self.subject_tag.as_deref(), self.rfc, self.resend) {
+            Ok(p) => Some(p),
+            Err(e) => {
+                cli_print_error!("{}", e);
+                None
+            }
+        }
+    }
+
+    // The From header of the generated mails is the commit author and
+    // send-email adds the recipients, they are printed as sent
+    fn print_mails(dir: &Path, params: &SendParams, from_email: &str) -> Result<(), io::Error> {
+        for f in mail_files(dir)? {
+            let content = fs::read_to_string(&f)?;
+            let headers = MailHeaders::parse(&content);
+            cli_print!("{}", f.display());
+            cli_print!("    From: {}", from_email);
+            cli_print!("    To: {}", params.to_email());
+            if let Some(cc) = params.cc() {
+                cli_print!("    Cc: {}", cc);
+            }
+            if let Some(subject) = headers.get("Subject") {
+                cli_print!("    Subject: {}", subject);
+            }
         }
+        Ok(())
+    }
+
+    // Opens the cover letter in the editor then prints the mails, None if
+    // the editor failed
+    fn edit_and_print(
+        dir: &Path, params: &SendParams, from_email: &str, user_config: &UserConfig,
+    ) -> Option<io::Result<()>> {
+        let cv_path = dir.join(COVER_LETTER_FILE_NAME);
+        let cv_content = match fs::read_to_string(&cv_path) {
+            Ok(c) => c,
+            Err(e) => return Some(Err(e)),
+        };
+        let cv_content = edit_in_text_editor(user_config, &cv_content)?;
+        Some(fs::write(&cv_path, cv_content).and_then(|_| Self::print_mails(dir, params, from_email)))
+    }
+
+    // Returns None if the user does not confirm the send
+    fn preview_and_send(
+        sender: &dyn PatchSender, params: &SendParams, dir: &Path, from_email: &str,
+        user_config: &UserConfig,
+    ) -> Option<Result<SentMessages, GitError>> {
+        if let Err(e) = Self::edit_and_print(dir, params, from_email, user_config)? {
+            return Some(Err(GitError::new(GitErrorCode::SendSeriesFailed, e.to_string())));
+        }
+
+        cli_print!("Press y to send the series, any other key to abort");
+        let mut k = String::new();
+        if io::stdin().read_line(&mut k).is_err() || !k.trim().eq_ignore_ascii_case(YES_KEY) {
+            return None;
+        }
+        Some(sender.send_formatted(params, dir))
     }
 
     fn select_range_interactively(&self, repo: &RepoData) -> Option<(String, String)> {
@@ -116,17 +255,261 @@ impl SendSeries {
             }
         }
     }
+
+    // Send the mails of the pending send that were not delivered yet,
+    // they keep their message ids and threading headers
+    fn resume(&self, user_data: &mut UserData) -> ControlFlow<()> {
+        let user_config = user_data.config().clone();
+        let repo = get_repo_mut_or_fail!(user_data);
+        let pending_dir = result_to_control_flow(pending_sends_dir_path(), |e| {
+            cli_print_error!("{}", e.to_string());
+        })?;
+
+        let Some(series) = repo.repo().get_series_by_name(self.series_name.as_str()) else {
+            cli_print_error!("Unknown series : {}", self.series_name.as_str());
+            return ControlFlow::Break(());
+        };
+        let Some(pending) = series.pending_send().cloned() else {
+            cli_print_error!("No pending send for {}", self.series_name);
+            return ControlFlow::Break(());
+        };
+        if pending.revision() != series.current_revision() {
+            cli_print_error!(
+                "The pending send is for v{} but the series is at v{}",
+                pending.revision(),
+                series.current_revision()
+            );
+            return ControlFlow::Break(());
+        }
+
+        let dir = PathBuf::from(pending.dir());
+        let counts = mail_files(&dir).and_then(|f| Ok((delivered_mails(&dir)?.len(), f.len())));
+        let (delivered, total) = match counts {
+            Ok(c) => c,
+            Err(e) => {
+                cli_print_error!("Failed to read the mails kept in {}, {}", dir.display(), e);
+                return ControlFlow::Break(());
+            }
+        };
+        cli_print!(
+            "Resuming the send of v{} to {}, {}/{} mails already delivered",
+            pending.revision(),
+            pending.to(),
+            delivered,
+            total
+        );
+
+        // the sender borrows the pending send, it must be dropped before the end
+        let send_res = {
+            let password = match smtp_password(&user_config) {
+                Ok(p) => p,
+                Err(e) => {
+                    cli_print_error!("Failed to read the smtp password, {}", e);
+                    return ControlFlow::Break(());
+                }
+            };
+            let Some(sender) = build_sender(&user_config, pending.from(), password.as_deref())
+            else {
+                return ControlFlow::Break(());
+            };
+            let range = pending.range();
+            let mut params_builder = SendParams::builder(
+                series,
+                pending.to(),
+                &pending_dir,
+                range.base_commit(),
+                range.last_commit(),
+            );
+            if let Some(c) = pending.cc() {
+                params_builder.set_cc(c);
+            }
+            sender.send_formatted(&params_builder.build(), &dir)
+        };
+        self.end_send(user_data, pending, send_res)
+    }
+
+    // Record a successful send, or keep the mails of a failed one to resume it
+    // later. The data is saved right away in that case, the error would drop it
+    fn end_send(
+        &self, user_data: &mut UserData, pending: PendingSend,
+        send_res: Result<SentMessages, GitError>,
+    ) -> ControlFlow<()> {
+        let repo = get_repo_mut_or_fail!(user_data);
+        // The series exists, it was found by the caller
+        let series = repo
+            .repo_mut()
+            .get_series_by_name_mut(self.series_name.as_str())
+            .unwrap();
+        if let Some(previous) = series.pending_send() {
+            if previous.dir() != pending.dir() {
+                cli_print!("Discarding the pending send of v{}", previous.revision());
+                let _ = fs::remove_dir_all(previous.dir());
+            }
+        }
+
+        let dir = PathBuf::from(pending.dir());
+        match send_res {
+            Ok(sent_messages) => {
+                let _ = fs::remove_dir_all(&dir);
+                SeriesLog::send(series, pending.to(), pending.subject_prefix());
+                let record = SendRecord::new(
+                    pending.revision(),
+                    pending.from(),
+                    pending.to(),
+                    pending.cc(),
+                    pending.range().clone(),
+                    pending.subject_prefix(),
+                    &sent_messages,
+                );
+                series.add_send_record(record);
+                series.set_current_range(pending.range().clone());
+                series.set_current_message_ids(
+                    sent_messages.cover_letter_id(),
+                    sent_messages.patch_ids(),
+                );
+                series.set_pending_send(None);
+                ControlFlow::Continue(())
+            }
+            Err(e) => {
+                cli_print_error!("Failed to send the series, {}", e);
+                let delivered = delivered_mails(&dir).map_or(0, |d| d.len());
+                let total = mail_files(&dir).map_or(0, |f| f.len());
+                SeriesLog::send_interrupted(series, pending.to(), delivered, total);
+                cli_print!(
+                    "{}/{} mails delivered, the mails are kept in {}",
+                    delivered,
+                    total,
+                    dir.display()
+                );
+                cli_print!(
+                    "Run patchr send --{} {} to send the remaining ones",
+                    RESUME_FLAG,
+                    self.series_name
+                );
+                series.set_pending_send(Some(pending));
+                if let Err(e) = user_data.save_repo() {
+                    cli_print_error!("Failed to save the pending send, {}", e);
+                }
+                ControlFlow::Break(())
+            }
+        }
+    }
+}
+
+// Only the smtp transport needs a password, when a user is configured
+fn smtp_password(user_config: &UserConfig) -> Result<Option<String>, io::Error> {
+    if user_config.transport() != Some(TRANSPORT_SMTP) {
+        return Ok(None);
+    }
+    let Some(user) = user_config.smtp_user() else {
+        return Ok(None);
+    };
+    if let Ok(password) = env::var(PATCHR_SMTP_PASSWORD) {
+        return Ok(Some(password));
+    }
+    rpassword::prompt_password(format!("SMTP password for {}: ", user)).map(Some)
+}
+
+fn build_sender<'a>(
+    user_config: &'a UserConfig, from_email: &'a str, password: Option<&'a str>,
+) -> Option<Box<dyn PatchSender + 'a>> {
+    match user_config.transport().unwrap_or(TRANSPORT_SEND_EMAIL) {
+        TRANSPORT_SMTP => {
+            let Some(smtp_server) = user_config.smtp_server() else {
+                cli_print_error!("The smtp transport requires a smtp server");
+                return None;
+            };
+            let mut sender_builder = SmtpPatchSender::builder(from_email, smtp_server);
+            if let Some(smtp_port) = user_config.smtp_port() {
+                sender_builder.set_port(smtp_port);
+            };
+            if let (Some(smtp_user), Some(password)) = (user_config.smtp_user(), password) {
+                sender_builder.set_credentials(smtp_user, password);
+            };
+            if let Some(smtp_encryption) = user_config.smtp_encryption() {
+                sender_builder.set_encryption(smtp_encryption);
+            };
+            Some(Box::new(sender_builder.build()))
+        }
+        TRANSPORT_SENDMAIL => {
+            let command = user_config
+                .send_command()
+                .unwrap_or(DEFAULT_SENDMAIL_COMMAND);
+            Some(Box::new(SendmailPatchSender::new(from_email, command)))
+        }
+        TRANSPORT_MAILDIR => {
+            let Some(maildir) = user_config.maildir() else {
+                cli_print_error!("The maildir transport requires a maildir");
+                return None;
+            };
+            Some(Box::new(MaildirPatchSender::new(from_email, Path::new(maildir))))
+        }
+        _ => {
+            let mut sender_builder = GitPatchSender::builder(from_email);
+            if let Some(send_command) = user_config.send_command() {
+                sender_builder.set_send_command(send_command);
+            };
+            if let Some(smtp_server) = user_config.smtp_server() {
+                sender_builder.set_smtp_server(smtp_server);
+            };
+            if let Some(smtp_port) = user_config.smtp_port() {
+                sender_builder.set_smtp_port(smtp_port);
+            };
+            if let Some(smtp_user) = user_config.smtp_user() {
+                sender_builder.set_smtp_user(smtp_user);
+            };
+            if let Some(smtp_encryption) = user_config.smtp_encryption() {
+                sender_builder.set_smtp_encryption(smtp_encryption);
+            };
+            Some(Box::new(sender_builder.build()))
+        }
+    }
 }
 
 impl SendSeriesBuilder {
     fn new() -> Self {
         Self {
-            series_name: None,
-            first_commit: None,
-            last_commit: None,
-            to_email: None,
+            values: Vec::new(),
             cc: None,
             interactive: false,
+            dry_run: false,
+            preview: false,
+            resume: false,
+            subject_tag: None,
+            rfc: false,
+            resend: false,
+        }
+    }
+
+    // The recipients and the range are those of the pending send
+    fn build_resume(&self) -> Result<Box<dyn Command>, CommandBuilderError> {
+        if self.cc.is_some()
+            || self.interactive
+            || self.dry_run
+            || self.preview
+            || self.subject_tag.is_some()
+            || self.rfc
+            || self.resend
+        {
+            return Err(CommandBuilderError::new(
+                super::CommandBuilderErrorCode::IncompatibleValues,
+                format!("--{} does not take other options", RESUME_FLAG),
+            ));
+        }
+        match self.values.as_slice() {
+            // the address is recorded in the pending send
+            [series_name] => Ok(Box::new(SendSeries::new(
+                series_name.as_str(),
+                "",
+                None,
+                false,
+                SendMode::Resume,
+            ))),
+            [] => Err(CommandBuilderError::new(
+                super::CommandBuilderErrorCode::MissingValue,
+                String::from("Missing series name"),
+            )),
+            [_, value, ..] => Err(CommandBuilderError::unexpected_value(value)),
         }
     }
 }
@@ -134,53 +517,87 @@ impl SendSeriesBuilder {
 impl Command for SendSeries {
     fn exec(&self, user_data: &mut UserData) -> ControlFlow<()> {
         debug!("Send series");
+        if self.mode == SendMode::Resume {
+            return self.resume(user_data);
+        }
 
         let user_config = user_data.config().clone();
-        let repo = get_repo_or_fail!(user_data);
-
-        let Some(series) = repo.repo().get_series_by_name(self.series_name.as_str()) else {
-            cli_print_error!("Unknown series : {}", self.series_name.as_str());
-            return ControlFlow::Break(());
-        };
+        let (to_email, list_thread_revisions, list_base_info) = self.get_to_email(user_data);
+        let to_email = String::from(to_email);
+        let repo = get_repo_mut_or_fail!(user_data);
 
         let Some(from_email) = user_config.from_email() else {
             cli_print_error!("Missing source email");
             return ControlFlow::Break(());
         };
 
-        let mut sender_builder = GitPatchSender::builder(from_email);
-
-        if let Some(send_command) = user_config.send_command() {
-            sender_builder.set_send_command(send_command);
+        // a dry run never connects to the server
+        let password = if self.mode != SendMode::DryRun {
+            match smtp_password(&user_config) {
+                Ok(p) => p,
+                Err(e) => {
+                    cli_print_error!("Failed to read the smtp password, {}", e);
+                    return ControlFlow::Break(());
+                }
+            }
+        } else {
+            None
         };
-        if let Some(smtp_server) = user_config.smtp_server() {
-            sender_builder.set_smtp_server(smtp_server);
+        let Some(sender) = build_sender(&user_config, from_email, password.as_deref()) else {
+            return ControlFlow::Break(());
         };
-        if let Some(smtp_port) = user_config.smtp_port() {
-            sender_builder.set_smtp_port(smtp_port);
+        // the mails of a dry run are not resumed, they go to tmp
+        let out_dir = if self.mode == SendMode::DryRun {
+            root_tmp_dir_path()
+        } else {
+            pending_sends_dir_path()
         };
-        if let Some(smtp_user) = user_config.smtp_user() {
-            sender_builder.set_smtp_user(smtp_user);
+        let out_dir = result_to_control_flow(out_dir, |e| {
+            cli_print_error!("{}", e.to_string());
+        })?;
+
+        let Some(series) = repo.repo().get_series_by_name(self.series_name.as_str()) else {
+            cli_print_error!("Unknown series : {}", self.series_name.as_str());
+            return ControlFlow::Break(());
         };
-        if let Some(smtp_encryption) = user_config.smtp_encryption() {
-            sender_builder.set_smtp_encryption(smtp_encryption);
+
+        let (first_commit, last_commit) = if self.interactive {
+            let Some(range) = self.select_range_interactively(repo) else {
+                return ControlFlow::Break(());
+            };
+            range
+        } else if let (Some(f), Some(l)) = (&self.first_commit, &self.last_commit) {
+            (f.clone(), l.clone())
+        } else if let Some(branch) = series.branch() {
+            let Some(range) = branch_range(repo, branch, series.base()) else {
+                return ControlFlow::Break(());
+            };
+            range
+        } else if let Some(range) = series.current_range() {
+            // Resend the commits recorded for the current revision
+            cli_print!("Using the recorded range {}", range);
+            (String::from(range.base_commit()), String::from(range.last_commit()))
+        } else {
+            cli_print_error!(
+                "No commit range provided and none recorded for v{}",
+                series.current_revision()
+            );
+            return ControlFlow::Break(());
         };
 
-        let sender = sender_builder.build();
-        let rtmp = result_to_control_flow(root_tmp_dir_path(), |e| {
-            cli_print_error!("{}", e.to_string());
-            ()
-        })?;
-        let to_email = self.get_to_email(user_data);
-        let mut first_commit = self.first_commit.clone();
-        let mut last_commit = self.last_commit.clone();
-        if self.interactive {
-            if let Some((f, l)) = self.select_range_interactively(repo) {
-                (first_commit, last_commit) = (Some(f), Some(l))
-            } else {
+        let Some(mut range) =
+            build_revision_range(repo, &first_commit, &last_commit, series.branch())
+        else {
+            return ControlFlow::Break(());
+        };
+        if series.base_info().unwrap_or(list_base_info) {
+            let Some(base) = tree_base(repo, series, &range) else {
                 return ControlFlow::Break(());
-            }
+            };
+            cli_print!("Base commit: {}", base);
+            range.set_tree_base(Some(&base));
         }
+
         let cc = match self.cc.as_deref() {
             Some(c) => Some(c), // arg overrides config
             None => {
@@ -191,51 +608,93 @@ impl Command for SendSeries {
                 }
             }
         };
-        let send_res = sender.send(
+        let Some(subject_prefix) = self.subject_prefix(series) else {
+            return ControlFlow::Break(());
+        };
+        let mut params_builder = SendParams::builder(
             series,
-            to_email,
-            &rtmp,
-            first_commit.as_ref().unwrap(),
-            last_commit.as_ref().unwrap(),
-            cc,
+            &to_email,
+            &out_dir,
+            range.base_commit(),
+            range.last_commit(),
         );
-
-        match send_res {
-            Ok(_) => {
-                SeriesLog::send(series, to_email);
-                ControlFlow::Continue(())
+        if let Some(c) = cc {
+            params_builder.set_cc(c);
+        }
+        params_builder.set_subject_prefix(&subject_prefix);
+        if let Some(base) = range.tree_base() {
+            params_builder.set_base_commit(base);
+        }
+        if series.thread_revisions().unwrap_or(list_thread_revisions) {
+            if let Some(id) = series.previous_cover_letter_id() {
+                cli_print!(
+                    "Replying to the cover letter of v{}: {}",
+                    series.current_revision() - 1,
+                    id
+                );
+                params_builder.set_in_reply_to(id);
             }
+        }
+        let params = params_builder.build();
+        let dir = match sender.format(&params) {
+            Ok(d) => d,
             Err(e) => {
-                cli_print_error!("Failed to send the series, {}", e);
-                ControlFlow::Break(())
+                cli_print_error!("Failed to generate the series, {}", e);
+                return ControlFlow::Break(());
             }
-        }
+        };
+        let send_res = match self.mode {
+            SendMode::Send | SendMode::Resume => sender.send_formatted(&params, &dir),
+            SendMode::DryRun => {
+                match Self::edit_and_print(&dir, &params, from_email, &user_config) {
+                    Some(Ok(())) => {}
+                    Some(Err(e)) => {
+                        cli_print_error!("Failed to read the generated mails, {}", e);
+                        return ControlFlow::Break(());
+                    }
+                    None => return ControlFlow::Break(()),
+                }
+                cli_print!("Nothing was sent, the mails are kept in {}", dir.display());
+                return ControlFlow::Continue(());
+            }
+            SendMode::Preview => {
+                let res = Self::preview_and_send(
+                    sender.as_ref(),
+                    &params,
+                    &dir,
+                    from_email,
+                    &user_config,
+                );
+                let Some(res) = res else {
+                    let _ = fs::remove_dir_all(&dir);
+                    cli_print!("Send aborted");
+                    return ControlFlow::Break(());
+                };
+                res
+            }
+        };
+
+        let pending = PendingSend::new(
+            series.current_revision(),
+            from_email,
+            &to_email,
+            cc,
+            range,
+            &subject_prefix,
+            &dir.to_string_lossy(),
+        );
+        self.end_send(user_data, pending, send_res)
     }
 }
 
 impl CommandBuilder for SendSeriesBuilder {
     fn add_value(&mut self, value: &str) -> Result<(), CommandBuilderError> {
-        if self.series_name.is_none() {
-            self.series_name = Some(String::from(value));
-            return Ok(());
-        }
-
-        if !self.interactive && self.first_commit.is_none() {
-            self.first_commit = Some(String::from(value));
-            return Ok(());
+        // series, [first commit, last commit], to
+        if self.values.len() >= 4 {
+            return Err(CommandBuilderError::unexpected_value(value));
         }
-
-        if !self.interactive && self.last_commit.is_none() {
-            self.last_commit = Some(String::from(value));
-            return Ok(());
-        }
-
-        if self.to_email.is_none() {
-            self.to_email = Some(String::from(value));
-            return Ok(());
-        }
-
-        Err(CommandBuilderError::unexpected_value(value))
+        self.values.push(String::from(value));
+        Ok(())
     }
 
     fn add_flag_and_value(&mut self, flag: &str, value: &str) -> Result<(), CommandBuilderError> {
@@ -250,12 +709,22 @@ impl CommandBuilder for SendSeriesBuilder {
                 let Some(value) = sanitize_cc_list(value) else {
                     return Err(CommandBuilderError::new(
                         super::CommandBuilderErrorCode::InvalidValues,
-                        format!("cc list format is invalid"),
+                        String::from("cc list format is invalid"),
                     ));
                 };
                 self.cc = Some(String::from(value));
                 Ok(())
             }
+            PREFIX_FLAG => {
+                if self.subject_tag.is_some() {
+                    return Err(CommandBuilderError::new(
+                        super::CommandBuilderErrorCode::UnexpectedValue,
+                        format!("reused flag --{}", flag),
+                    ));
+                }
+                self.subject_tag = Some(String::from(value));
+                Ok(())
+            }
             _ => Err(CommandBuilderError::new(
                 super::CommandBuilderErrorCode::UnknownFlag,
                 String::from(flag),
@@ -269,6 +738,26 @@ impl CommandBuilder for SendSeriesBuilder {
                 self.interactive = true;
                 Ok(())
             }
+            DRY_RUN_FLAG => {
+                self.dry_run = true;
+                Ok(())
+            }
+            PREVIEW_FLAG => {
+                self.preview = true;
+                Ok(())
+            }
+            RESUME_FLAG => {
+                self.resume = true;
+                Ok(())
+            }
+            RFC_FLAG => {
+                self.rfc = true;
+                Ok(())
+            }
+            RESEND_FLAG => {
+                self.resend = true;
+                Ok(())
+            }
             _ => Err(CommandBuilderError::new(
                 super::CommandBuilderErrorCode::UnknownFlag,
                 String::from(flag),
@@ -278,8 +767,9 @@ impl CommandBuilder for SendSeriesBuilder {
 
     fn requires_value(&self, flag: &str) -> Result<bool, CommandBuilderError> {
         match flag {
-            CC_FLAG => Ok(true),
-            INTERACTIVE_FLAG => Ok(false),
+            CC_FLAG | PREFIX_FLAG => Ok(true),
+            INTERACTIVE_FLAG | DRY_RUN_FLAG | PREVIEW_FLAG | RESUME_FLAG | RFC_FLAG
+            | RESEND_FLAG => Ok(false),
             _ => Err(CommandBuilderError::new(
                 super::CommandBuilderErrorCode::UnknownFlag,
                 String::from(flag),
@@ -292,29 +782,59 @@ impl CommandBuilder for SendSeriesBuilder {
     }
 
     fn build(&self) -> Result<Box<dyn Command>, CommandBuilderError> {
-        if let (Some(series_name), Some(to_email)) = (&self.series_name, &self.to_email) {
-            let mut s = Box::new(SendSeries::new(
+        let mode = match (self.dry_run, self.preview) {
+            (false, false) => SendMode::Send,
+            (true, false) => SendMode::DryRun,
+            (false, true) => SendMode::Preview,
+            (true, true) => {
+                return Err(CommandBuilderError::new(
+                    super::CommandBuilderErrorCode::IncompatibleValues,
+                    format!("--{} and --{} are exclusive", DRY_RUN_FLAG, PREVIEW_FLAG),
+                ))
+            }
+        };
+        if self.resume {
+            return self.build_resume();
+        }
+        let mut s = match self.values.as_slice() {
+            [series_name, to_email] => Box::new(SendSeries::new(
                 series_name.as_str(),
                 to_email.as_str(),
                 self.cc.as_deref(),
                 self.interactive,
-            ));
-            if !self.interactive {
-                if let (Some(f), Some(l)) = (&self.first_commit, &self.last_commit) {
-                    s.set_commit_range(f, l);
-                } else {
+                mode,
+            )),
+            [series_name, first_commit, last_commit, to_email] => {
+                if self.interactive {
                     return Err(CommandBuilderError::new(
-                        super::CommandBuilderErrorCode::MissingValue,
-                        String::from("Missing first or last commit sha1"),
+                        super::CommandBuilderErrorCode::IncompatibleValues,
+                        format!("Cannot provide commits with -{}", INTERACTIVE_FLAG),
                     ));
                 }
+                let mut s = Box::new(SendSeries::new(
+                    series_name.as_str(),
+                    to_email.as_str(),
+                    self.cc.as_deref(),
+                    self.interactive,
+                    mode,
+                ));
+                s.set_commit_range(first_commit, last_commit);
+                s
             }
-            Ok(s)
-        } else {
-            Err(CommandBuilderError::new(
-                super::CommandBuilderErrorCode::MissingValue,
-                String::from("Missing arguments"),
-            ))
-        }
+            [_, _, _] => {
+                return Err(CommandBuilderError::new(
+                    super::CommandBuilderErrorCode::MissingValue,
+                    String::from("Missing first or last commit sha1"),
+                ))
+            }
+            _ => {
+                return Err(CommandBuilderError::new(
+                    super::CommandBuilderErrorCode::MissingValue,
+                    String::from("Missing arguments"),
+                ))
+            }
+        };
+        s.set_subject_prefix_options(self.subject_tag.as_deref(), self.rfc, self.resend);
+        Ok(s)
     }
 }
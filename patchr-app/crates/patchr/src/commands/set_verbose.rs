@@ -19,4 +19,8 @@ impl Command for SetVerbose {
         log::set_max_level(new_level);
         ControlFlow::Continue(())
     }
+
+    fn read_only(&self) -> bool {
+        true
+    }
 }
@@ -0,0 +1,168 @@
+use std::ops::ControlFlow;
+
+use common::util::rust::result_to_control_flow;
+use log::debug;
+
+use crate::{cli_print, cli_print_error, user_data::user_data::UserData};
+
+use super::{Command, CommandBuilder, CommandBuilderError, CommandBuilderErrorCode, ALIAS, PERSON};
+
+const DEL_FLAG: &str = "d";
+
+// Shortcuts expanded by tag and untag: trailer aliases for the tag names
+// and people for the values
+#[derive(Clone, Copy)]
+enum ShortcutKind {
+    Alias,
+    Person,
+}
+
+enum ShortcutAction {
+    List,
+    Set(String, String),
+    Delete(String),
+}
+
+pub struct Shortcut {
+    kind: ShortcutKind,
+    action: ShortcutAction,
+}
+
+pub struct ShortcutBuilder {
+    kind: ShortcutKind,
+    values: Vec<String>,
+    delete: bool,
+}
+
+impl Shortcut {
+    pub fn alias_builder() -> Box<dyn CommandBuilder> {
+        Box::new(ShortcutBuilder::new(ShortcutKind::Alias))
+    }
+
+    pub fn person_builder() -> Box<dyn CommandBuilder> {
+        Box::new(ShortcutBuilder::new(ShortcutKind::Person))
+    }
+}
+
+impl ShortcutBuilder {
+    fn new(kind: ShortcutKind) -> Self {
+        Self {
+            kind,
+            values: Vec::new(),
+            delete: false,
+        }
+    }
+}
+
+impl Command for Shortcut {
+    fn exec(&self, user_data: &mut UserData) -> ControlFlow<()> {
+        let config = user_data.config_mut();
+        match (&self.action, self.kind) {
+            (ShortcutAction::List, kind) => {
+                let shortcuts = match kind {
+                    ShortcutKind::Alias => config.trailer_aliases(),
+                    ShortcutKind::Person => config.people(),
+                };
+                let mut shortcuts: Vec<_> = shortcuts.iter().collect();
+                shortcuts.sort();
+                for (name, value) in shortcuts {
+                    cli_print!("- {} : {}", name, value);
+                }
+            }
+            (ShortcutAction::Set(name, value), ShortcutKind::Alias) => {
+                debug!("Set alias {} : {}", name, value);
+                result_to_control_flow(config.set_trailer_alias(name, value), |e| {
+                    cli_print_error!("{}", e);
+                })?;
+                cli_print!("Alias {} set", name);
+            }
+            (ShortcutAction::Set(name, value), ShortcutKind::Person) => {
+                debug!("Set person {} : {}", name, value);
+                result_to_control_flow(config.set_person(name, value), |e| {
+                    cli_print_error!("{}", e);
+                })?;
+                cli_print!("Person {} set", name);
+            }
+            (ShortcutAction::Delete(name), ShortcutKind::Alias) => {
+                debug!("Delete alias {}", name);
+                result_to_control_flow(config.delete_trailer_alias(name), |e| {
+                    cli_print_error!("{}", e);
+                })?;
+                cli_print!("Alias {} deleted", name);
+            }
+            (ShortcutAction::Delete(name), ShortcutKind::Person) => {
+                debug!("Delete person {}", name);
+                result_to_control_flow(config.delete_person(name), |e| {
+                    cli_print_error!("{}", e);
+                })?;
+                cli_print!("Person {} deleted", name);
+            }
+        }
+        ControlFlow::Continue(())
+    }
+}
+
+impl CommandBuilder for ShortcutBuilder {
+    fn add_value(&mut self, value: &str) -> Result<(), CommandBuilderError> {
+        // name, value
+        if self.values.len() >= 2 {
+            return Err(CommandBuilderError::unexpected_value(value));
+        }
+        self.values.push(String::from(value));
+        Ok(())
+    }
+
+    fn add_flag(&mut self, flag: &str) -> Result<(), CommandBuilderError> {
+        if flag == DEL_FLAG {
+            self.delete = true;
+            return Ok(());
+        };
+
+        Err(CommandBuilderError::new(
+            CommandBuilderErrorCode::UnknownFlag,
+            String::from(flag),
+        ))
+    }
+
+    fn requires_value(&self, flag: &str) -> Result<bool, CommandBuilderError> {
+        if flag == DEL_FLAG {
+            Ok(false)
+        } else {
+            Err(CommandBuilderError::new(
+                CommandBuilderErrorCode::UnknownFlag,
+                String::from(flag),
+            ))
+        }
+    }
+
+    fn name(&self) -> &str {
+        match self.kind {
+            ShortcutKind::Alias => ALIAS,
+            ShortcutKind::Person => PERSON,
+        }
+    }
+
+    fn build(&self) -> Result<Box<dyn Command>, CommandBuilderError> {
+        let action = match (self.values.as_slice(), self.delete) {
+            ([], false) => ShortcutAction::List,
+            ([name, value], false) => ShortcutAction::Set(name.clone(), value.clone()),
+            ([name], true) => ShortcutAction::Delete(name.clone()),
+            ([_, _], true) => {
+                return Err(CommandBuilderError::new(
+                    CommandBuilderErrorCode::IncompatibleValues,
+                    format!("Cannot provide a value whith -{}", DEL_FLAG),
+                ))
+            }
+            _ => {
+                return Err(CommandBuilderError::new(
+                    CommandBuilderErrorCode::MissingValue,
+                    String::from("Invalid arguments"),
+                ))
+            }
+        };
+        Ok(Box::new(Shortcut {
+            kind: self.kind,
+            action,
+        }))
+    }
+}
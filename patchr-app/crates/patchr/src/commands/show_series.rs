@@ -60,6 +60,10 @@ impl Command for ShowSeries {
         }
         ControlFlow::Continue(())
     }
+
+    fn read_only(&self) -> bool {
+        true
+    }
 }
 
 impl CommandBuilder for ShowSeriesBuilder {
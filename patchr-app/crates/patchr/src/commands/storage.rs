@@ -0,0 +1,83 @@
+use std::ops::ControlFlow;
+
+use common::util::rust::result_to_control_flow;
+use git::util::DATA_REF;
+use log::debug;
+
+use crate::{cli_print, cli_print_error, user_data::user_data::UserData};
+
+use super::{Command, CommandBuilder, CommandBuilderError, CommandBuilderErrorCode, STORAGE};
+
+const GIT_STORAGE: &str = "git";
+const FILE_STORAGE: &str = "file";
+
+// Where the series of the current repo are kept: in the data directory, or
+// in the git repo itself so that they can be pushed and fetched
+pub struct Storage {
+    git: bool,
+}
+
+pub struct StorageBuilder {
+    storage: Option<String>,
+}
+
+impl Storage {
+    pub fn builder() -> Box<dyn CommandBuilder> {
+        Box::new(StorageBuilder { storage: None })
+    }
+}
+
+impl Command for Storage {
+    fn exec(&self, user_data: &mut UserData) -> ControlFlow<()> {
+        debug!("Store the data in git: {}", self.git);
+        let conflicts = result_to_control_flow(user_data.set_git_storage(self.git), |e| {
+            cli_print_error!("{}", e);
+        })?;
+        for c in conflicts {
+            cli_print!(
+                "{} differs from the version in {}, the local one is kept",
+                c.name(),
+                DATA_REF
+            );
+            if let Some(copy) = c.copy() {
+                cli_print!("The version in {} is kept in {}", DATA_REF, copy);
+            }
+        }
+        if self.git {
+            cli_print!("The series are stored in {}", DATA_REF);
+        } else {
+            cli_print!("The series are stored in the data directory");
+        }
+        ControlFlow::Continue(())
+    }
+}
+
+impl CommandBuilder for StorageBuilder {
+    fn add_value(&mut self, value: &str) -> Result<(), CommandBuilderError> {
+        if self.storage.is_none() {
+            self.storage = Some(String::from(value));
+            return Ok(());
+        }
+
+        Err(CommandBuilderError::unexpected_value(value))
+    }
+
+    fn name(&self) -> &str {
+        STORAGE
+    }
+
+    fn build(&self) -> Result<Box<dyn Command>, CommandBuilderError> {
+        match self.storage.as_deref() {
+            Some(GIT_STORAGE) => Ok(Box::new(Storage { git: true })),
+            Some(FILE_STORAGE) => Ok(Box::new(Storage { git: false })),
+            Some(s) => Err(CommandBuilderError::new(
+                CommandBuilderErrorCode::InvalidValues,
+                format!("Unknown storage {}, use {} or {}", s, GIT_STORAGE, FILE_STORAGE),
+            )),
+            None => Err(CommandBuilderError::new(
+                CommandBuilderErrorCode::MissingValue,
+                String::from("Missing storage"),
+            )),
+        }
+    }
+}
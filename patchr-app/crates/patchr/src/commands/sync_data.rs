@@ -0,0 +1,119 @@
+use std::ops::ControlFlow;
+
+use common::util::rust::result_to_control_flow;
+use git::util::DATA_REF;
+use log::debug;
+
+use crate::{
+    cli_print, cli_print_error,
+    user_data::{git_storage::FetchResult, user_data::UserData},
+};
+
+use super::{Command, CommandBuilder, CommandBuilderError, FETCH_DATA, PUSH_DATA};
+
+const DEFAULT_REMOTE: &str = "origin";
+
+// Exchange the series stored in git with the co-maintainers, see storage.
+// The fetched data is merged series by series
+#[derive(Clone, Copy)]
+enum SyncKind {
+    Push,
+    Fetch,
+}
+
+pub struct SyncData {
+    kind: SyncKind,
+    remote: String,
+}
+
+pub struct SyncDataBuilder {
+    kind: SyncKind,
+    remote: Option<String>,
+}
+
+impl SyncData {
+    pub fn push_builder() -> Box<dyn CommandBuilder> {
+        Box::new(SyncDataBuilder {
+            kind: SyncKind::Push,
+            remote: None,
+        })
+    }
+
+    pub fn fetch_builder() -> Box<dyn CommandBuilder> {
+        Box::new(SyncDataBuilder {
+            kind: SyncKind::Fetch,
+            remote: None,
+        })
+    }
+
+    fn fetch(&self, user_data: &mut UserData) -> ControlFlow<()> {
+        let res = result_to_control_flow(user_data.fetch_git_data(&self.remote), |e| {
+            cli_print_error!("{}", e);
+        })?;
+        match res {
+            FetchResult::UpToDate => cli_print!("Already up to date"),
+            FetchResult::FastForward => cli_print!("Series updated from {}", self.remote),
+            FetchResult::Merged(conflicts) => {
+                for c in conflicts {
+                    cli_print!("{} changed on both sides, the local version is kept", c.name());
+                    if let Some(copy) = c.copy() {
+                        cli_print!("The version of {} is kept in {}", self.remote, copy);
+                    }
+                }
+                cli_print!("Series merged with {}", self.remote);
+            }
+        }
+        ControlFlow::Continue(())
+    }
+}
+
+impl Command for SyncData {
+    fn exec(&self, user_data: &mut UserData) -> ControlFlow<()> {
+        match self.kind {
+            SyncKind::Push => {
+                debug!("Push {} to {}", DATA_REF, self.remote);
+                result_to_control_flow(user_data.push_git_data(&self.remote), |e| {
+                    cli_print_error!("{}", e);
+                })?;
+                cli_print!("Series pushed to {}", self.remote);
+                ControlFlow::Continue(())
+            }
+            SyncKind::Fetch => {
+                debug!("Fetch {} from {}", DATA_REF, self.remote);
+                self.fetch(user_data)
+            }
+        }
+    }
+
+    fn read_only(&self) -> bool {
+        matches!(self.kind, SyncKind::Push)
+    }
+}
+
+impl CommandBuilder for SyncDataBuilder {
+    fn add_value(&mut self, value: &str) -> Result<(), CommandBuilderError> {
+        if self.remote.is_none() {
+            self.remote = Some(String::from(value));
+            return Ok(());
+        }
+
+        Err(CommandBuilderError::unexpected_value(value))
+    }
+
+    fn name(&self) -> &str {
+        match self.kind {
+            SyncKind::Push => PUSH_DATA,
+            SyncKind::Fetch => FETCH_DATA,
+        }
+    }
+
+    fn build(&self) -> Result<Box<dyn Command>, CommandBuilderError> {
+        Ok(Box::new(SyncData {
+            kind: self.kind,
+            remote: self
+                .remote
+                .clone()
+                .unwrap_or_else(|| String::from(DEFAULT_REMOTE)),
+        }))
+    }
+}
@@ -1,45 +1,55 @@
 use std::marker::PhantomData;
 use std::ops::ControlFlow;
 
-use git::util::CommitId;
-use ::git::util::{Commit, CommitTag, GitRepo};
+use ::git::util::{Commit, CommitTag, GitRepo, FIXES_TAG};
+use git::trailers::TrailerOrder;
 use log::debug;
 
 use crate::{cli_print, get_repo_or_fail, open_git_repo_or_fail};
-use crate::{cli_print_error, user_data::user_data::UserData};
+use crate::{cli_print_error, user_data::{root_file::UserConfig, user_data::UserData}};
 
-use super::{Command, CommandBuilder, CommandBuilderError, TAG, UNTAG};
+use super::common::resolve_commit;
+use super::{Command, CommandBuilder, CommandBuilderError, CommandBuilderErrorCode, TAG, UNTAG};
 
+const RANGE_FLAG: &str = "range";
+
+// A single commit, or the commits of base..last as given to patchr send
+enum TagTarget {
+    Commit(String),
+    Range(String, String),
+}
+
+// The tag and the value are expanded with the aliases and the people of
+// the configuration when the command is run
 pub struct Tag {
-    tag: CommitTag,
-    value: String,
-    commit: String,
+    tag: String,
+    value: Option<String>,
+    target: TagTarget,
 }
 
 pub struct UnTag {
-    tag: CommitTag,
+    tag: String,
     value: Option<String>,
     commit: String,
 }
 
 trait TaggingCommand: Command {
     fn name() -> &'static str;
+    #[allow(clippy::new_ret_no_self)]
     fn new<C: TaggingCommand>(builder: &TaggingBuilder<C>) -> Result<Box<dyn Command>, CommandBuilderError>;
 }
 
 struct TaggingBuilder<C: TaggingCommand> {
-    tag: Option<CommitTag>,
-    value: Option<String>,
-    commmit: Option<String>,
+    values: Vec<String>,
+    range: Option<String>,
     phantom: PhantomData<C>,
 }
 
 impl<C: TaggingCommand> TaggingBuilder<C> {
     fn new() -> Self {
         Self {
-            tag: None,
-            value: None,
-            commmit: None,
+            values: Vec::new(),
+            range: None,
             phantom: PhantomData,
         }
     }
@@ -63,17 +73,23 @@ impl TaggingCommand for UnTag {
     }
 
     fn new<C: TaggingCommand>(builder: &TaggingBuilder<C>) -> Result<Box<dyn Command>, CommandBuilderError> {
-        if let (Some(tag), Some(commit)) = (&builder.tag, &builder.commmit) {
-            Ok(Box::new(Self {
+        if builder.range.is_some() {
+            return Err(CommandBuilderError::new(
+                CommandBuilderErrorCode::IncompatibleValues,
+                format!("--{} is only supported when tagging", RANGE_FLAG),
+            ));
+        }
+        match builder.values.as_slice() {
+            [commit, tag] | [commit, tag, _] => Ok(Box::new(Self {
                 tag: tag.clone(),
-                value: builder.value.clone(),
+                value: builder.values.get(2).cloned(),
                 commit: commit.clone(),
-            }))
-        } else {
-            Err(CommandBuilderError::new(
-                super::CommandBuilderErrorCode::MissingValue,
+            })),
+            [_, _, _, v, ..] => Err(CommandBuilderError::unexpected_value(v)),
+            _ => Err(CommandBuilderError::new(
+                CommandBuilderErrorCode::MissingValue,
                 String::from("Missing values"),
-            ))
+            )),
         }
     }
 }
@@ -84,50 +100,102 @@ impl TaggingCommand for Tag {
     }
 
     fn new<C: TaggingCommand>(builder: &TaggingBuilder<C>) -> Result<Box<dyn Command>, CommandBuilderError> {
-        if let (Some(tag), Some(value), Some(commit)) = (&builder.tag, &builder.value, &builder.commmit) {
-            Ok(Box::new(Self {
-                tag: tag.clone(),
-                value: value.clone(),
-                commit: commit.clone(),
-            }))
-        } else {
-            Err(CommandBuilderError::new(
-                super::CommandBuilderErrorCode::MissingValue,
-                String::from("Missing values"),
-            ))
-        }
+        // Four values are a range with its tag value, otherwise a range is
+        // explicit since the value of a tag is optional
+        let (range, values) = match (&builder.range, builder.values.as_slice()) {
+            (Some(range), values) => (Some(range.as_str()), values),
+            (None, [first, values @ ..]) if first.contains("..") => (Some(first.as_str()), values),
+            (None, values) => (None, values),
+        };
+        let (target, tag, value) = match (range, values) {
+            (Some(range), [tag, value @ ..]) if value.len() <= 1 => {
+                let Some((base, last)) = range.split_once("..") else {
+                    // a value of --range without ..
+                    return Err(CommandBuilderError::new(
+                        CommandBuilderErrorCode::InvalidValues,
+                        format!("Invalid range '{}', expected <c1>..<c2>", range),
+                    ));
+                };
+                (TagTarget::Range(String::from(base), String::from(last)), tag, value.first())
+            }
+            (None, [commit, tag, value @ ..]) if value.len() <= 1 => {
+                (TagTarget::Commit(commit.clone()), tag, value.first())
+            }
+            (None, [base, last, tag, value]) => {
+                (TagTarget::Range(base.clone(), last.clone()), tag, Some(value))
+            }
+            (Some(_), [_, _, v, ..]) | (None, [_, _, _, _, v, ..]) => {
+                return Err(CommandBuilderError::unexpected_value(v))
+            }
+            _ => {
+                return Err(CommandBuilderError::new(
+                    CommandBuilderErrorCode::MissingValue,
+                    String::from("Missing values"),
+                ))
+            }
+        };
+        Ok(Box::new(Self {
+            tag: tag.clone(),
+            value: value.cloned(),
+            target,
+        }))
     }
 }
 
-fn find_commit<'a>(repo: &'a GitRepo, commit: &str) -> Option<Commit<'a>> {
-    let commit_id = match CommitId::new(commit) {
-        Ok(c) => c,
-        Err(e) => {
-            cli_print_error!("Failed to decode the commit hash: {}", e);
-            return None;
-        }
-    };
-    match repo.find_commit(&commit_id) {
-        Ok(c) => Some(c),
-        Err(e) => {
-            cli_print_error!("{}", e);
-            None
+impl Tag {
+    // Signed-off-by defaults to the from address of the configuration
+    fn tag_value(&self, tag: &CommitTag, config: &UserConfig) -> Option<String> {
+        match (&self.value, tag) {
+            (Some(value), _) => Some(String::from(config.identity(value))),
+            (None, CommitTag::SignedOffBy) => config.from_email().map(String::from),
+            (None, _) => None,
         }
     }
-}
 
-impl Command for Tag {
-    fn exec(&self, user_data: &mut UserData) -> ControlFlow<()> {
-        debug!("tag : {} {} {}", self.commit, self.tag, self.value);
+    // The value of a Fixes trailer is the reference of the culprit commit,
+    // returned with the value
+    fn fixes_value<'a>(
+        &self, git_repo: &'a GitRepo, config: &UserConfig,
+    ) -> Option<(String, Commit<'a>)> {
+        let Some(culprit) = &self.value else {
+            cli_print_error!("Missing the commit fixed by the patch");
+            return None;
+        };
+        let culprit = resolve_commit(git_repo, culprit)?;
+        Some((culprit.fixes_reference(config.abbrev_length()), culprit))
+    }
 
-        let repo = get_repo_or_fail!(user_data);
-        let git_repo = open_git_repo_or_fail!(repo);
-        let commit = match find_commit(&git_repo, self.commit.as_str()) {
+    // A patch can only fix one of its ancestors
+    fn check_culprit(
+        git_repo: &GitRepo, culprit: Option<&Commit>, commit: &Commit,
+    ) -> ControlFlow<()> {
+        let Some(culprit) = culprit else {
+            return ControlFlow::Continue(());
+        };
+        match git_repo.is_ancestor(&culprit.id(), &commit.id()) {
+            Ok(true) => ControlFlow::Continue(()),
+            Ok(false) => {
+                cli_print_error!("{} is not an ancestor of {}", culprit.id(), commit.id());
+                ControlFlow::Break(())
+            }
+            Err(e) => {
+                cli_print_error!("{}", e);
+                ControlFlow::Break(())
+            }
+        }
+    }
+
+    fn tag_commit(
+        git_repo: &GitRepo, commit: &str, tag: &CommitTag, value: &str, culprit: Option<&Commit>,
+        order: &TrailerOrder,
+    ) -> ControlFlow<()> {
+        let commit = match resolve_commit(git_repo, commit) {
             Some(c) => c,
             None => return ControlFlow::Break(())
         };
+        Self::check_culprit(git_repo, culprit, &commit)?;
 
-        match commit.add_tag(&self.tag, Some(self.value.as_str())) {
+        match commit.add_tag(tag, value, order) {
             Ok(id) => {
                 cli_print!("Tag added");
                 cli_print!("New commit: {}", id);
@@ -139,23 +207,123 @@ impl Command for Tag {
             }
         }
     }
+
+    // All the commits are rewritten at once
+    fn tag_range(
+        git_repo: &GitRepo, base: &str, last: &str, tag: &CommitTag, value: &str,
+        culprit: Option<&Commit>, order: &TrailerOrder,
+    ) -> ControlFlow<()> {
+        let Some(base_id) = resolve_commit(git_repo, base).map(|c| c.id()) else {
+            return ControlFlow::Break(());
+        };
+        let Some(last_id) = resolve_commit(git_repo, last).map(|c| c.id()) else {
+            return ControlFlow::Break(());
+        };
+        let commits = match git_repo.commit_range(&base_id, &last_id) {
+            Ok(c) if c.is_empty() => {
+                cli_print_error!("No commit in {}..{}", base, last);
+                return ControlFlow::Break(());
+            }
+            Ok(c) => c,
+            Err(e) => {
+                cli_print_error!("Invalid commit range: {}", e);
+                return ControlFlow::Break(());
+            }
+        };
+
+        let tags = [(tag.clone(), String::from(value))];
+        let mut messages = Vec::new();
+        for commit in commits.iter() {
+            Self::check_culprit(git_repo, culprit, commit)?;
+            let message = match commit.message_with_tags(&tags, order) {
+                Ok(m) => m,
+                Err(e) => {
+                    cli_print_error!("Failed to add the tag: {}", e);
+                    return ControlFlow::Break(());
+                }
+            };
+            if commit.message().is_ok_and(|m| m != message) {
+                messages.push((commit.id(), message));
+            }
+        }
+        if messages.is_empty() {
+            cli_print!("All the commits already have the tag");
+            return ControlFlow::Continue(());
+        }
+
+        match git_repo.reword_commits(&messages) {
+            Ok(rewritten) => {
+                let new_last = rewritten
+                    .iter()
+                    .find(|(old, _)| old.to_string() == last_id.to_string())
+                    .map_or(last_id.to_string(), |(_, new)| new.to_string());
+                cli_print!("Tag added to {} commits", messages.len());
+                cli_print!("New range: {}..{}", base_id, new_last);
+                ControlFlow::Continue(())
+            }
+            Err(e) => {
+                cli_print_error!("Failed to add the tag: {}", e);
+                ControlFlow::Break(())
+            }
+        }
+    }
+}
+
+impl Command for Tag {
+    fn exec(&self, user_data: &mut UserData) -> ControlFlow<()> {
+        let config = user_data.config();
+        let order = config.trailer_order();
+        let tag = config.tag(self.tag.as_str());
+        let repo = get_repo_or_fail!(user_data);
+        let git_repo = open_git_repo_or_fail!(repo);
+        let (value, culprit) = if tag.to_string().eq_ignore_ascii_case(FIXES_TAG) {
+            let Some((value, culprit)) = self.fixes_value(&git_repo, config) else {
+                return ControlFlow::Break(());
+            };
+            (value, Some(culprit))
+        } else {
+            let Some(value) = self.tag_value(&tag, config) else {
+                match tag {
+                    CommitTag::SignedOffBy => {
+                        cli_print_error!("No value given and no from address configured")
+                    }
+                    _ => cli_print_error!("Missing value for {}", tag),
+                }
+                return ControlFlow::Break(());
+            };
+            (value, None)
+        };
+        match &self.target {
+            TagTarget::Commit(commit) => {
+                debug!("tag : {} {} {}", commit, tag, value);
+                Self::tag_commit(&git_repo, commit, &tag, &value, culprit.as_ref(), &order)
+            }
+            TagTarget::Range(base, last) => {
+                debug!("tag : {}..{} {} {}", base, last, tag, value);
+                Self::tag_range(&git_repo, base, last, &tag, &value, culprit.as_ref(), &order)
+            }
+        }
+    }
 }
 
 impl Command for UnTag {
     fn exec(&self, user_data: &mut UserData) -> ControlFlow<()> {
         // TODO: implement display and add a debug trace
+        let config = user_data.config();
+        let tag = config.tag(self.tag.as_str());
+        let value = self.value.as_deref().map(|v| String::from(config.identity(v)));
         let repo = get_repo_or_fail!(user_data);
         let git_repo = open_git_repo_or_fail!(repo);
-        let commit = match find_commit(&git_repo, self.commit.as_str()) {
+        let commit = match resolve_commit(&git_repo, self.commit.as_str()) {
             Some(c) => c,
             None => return ControlFlow::Break(())
         };
-        let result = match &self.value {
+        let result = match &value {
             Some(v) => {
-                commit.remove_tag(&self.tag, Some(v))
+                commit.remove_tag(&tag, Some(v))
             }
             None => {
-                commit.remove_tag_all(&self.tag)
+                commit.remove_tag_all(&tag)
             }
         };
 
@@ -175,22 +343,37 @@ impl Command for UnTag {
 
 impl<C: TaggingCommand> CommandBuilder for TaggingBuilder<C> {
     fn add_value(&mut self, value: &str) -> Result<(), CommandBuilderError> {
-        if self.commmit.is_none() {
-            self.commmit = Some(String::from(value));
-            return Ok(());
-        }
+        // Checked when building the command, the number of values
+        // depends on the form
+        self.values.push(String::from(value));
+        Ok(())
+    }
 
-        if self.tag.is_none() {
-            self.tag = Some(CommitTag::from(value));
-            return Ok(());
+    fn add_flag_and_value(&mut self, flag: &str, value: &str) -> Result<(), CommandBuilderError> {
+        if flag != RANGE_FLAG {
+            return Err(CommandBuilderError::new(
+                CommandBuilderErrorCode::UnknownFlag,
+                String::from(flag),
+            ));
         }
-
-        if self.value.is_none() {
-            self.value = Some(String::from(value));
-            return Ok(());
+        if self.range.is_some() {
+            return Err(CommandBuilderError::new(
+                CommandBuilderErrorCode::UnexpectedValue,
+                format!("reused flag --{}", flag),
+            ));
         }
+        self.range = Some(String::from(value));
+        Ok(())
+    }
 
-        Err(CommandBuilderError::unexpected_value(value))
+    fn requires_value(&self, flag: &str) -> Result<bool, CommandBuilderError> {
+        match flag {
+            RANGE_FLAG => Ok(true),
+            _ => Err(CommandBuilderError::new(
+                CommandBuilderErrorCode::UnknownFlag,
+                String::from(flag),
+            )),
+        }
     }
 
     fn build(&self) -> Result<Box<dyn Command>, CommandBuilderError> {
@@ -200,4 +383,4 @@ impl<C: TaggingCommand> CommandBuilder for TaggingBuilder<C> {
     fn name(&self) -> &str {
         C::name()
     }
-}
\ No newline at end of file
+}
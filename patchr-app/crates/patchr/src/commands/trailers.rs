@@ -0,0 +1,234 @@
+use std::{ops::ControlFlow, path::Path};
+
+use git::{
+    series::{RevisionRange, Series, SeriesLog},
+    trailers::{collect_trailers, read_mailbox},
+};
+use log::debug;
+
+use crate::{
+    cli_print, cli_print_error, get_repo_mut_or_fail, open_git_repo_or_fail,
+    user_data::user_data::UserData,
+};
+
+use super::{
+    common::resolve_commit, Command, CommandBuilder, CommandBuilderError, CommandBuilderErrorCode,
+    TRAILERS,
+};
+
+const DRY_RUN_FLAG: &str = "dry-run";
+
+pub struct Trailers {
+    series_name: String,
+    mailbox: String,
+    commits: Option<(String, String)>,
+    dry_run: bool,
+}
+
+pub struct TrailersBuilder {
+    values: Vec<String>,
+    dry_run: bool,
+}
+
+impl Trailers {
+    pub fn builder() -> Box<dyn CommandBuilder> {
+        Box::new(TrailersBuilder {
+            values: Vec::new(),
+            dry_run: false,
+        })
+    }
+
+    // The replies are matched against the last revision that was sent
+    fn last_sent_revision(series: &Series) -> Option<u32> {
+        (1..=series.current_revision()).rev().find(|&n| {
+            series
+                .revision(n as usize)
+                .is_some_and(|r| !r.patch_ids().is_empty())
+        })
+    }
+}
+
+impl Command for Trailers {
+    fn exec(&self, user_data: &mut UserData) -> ControlFlow<()> {
+        debug!("Trailers {} from {}", self.series_name, self.mailbox);
+        let order = user_data.config().trailer_order();
+        let repo = get_repo_mut_or_fail!(user_data);
+
+        let Some(series) = repo.repo().get_series_by_name(self.series_name.as_str()) else {
+            cli_print_error!("Unknown series : {}", self.series_name.as_str());
+            return ControlFlow::Break(());
+        };
+        let Some(rev) = Self::last_sent_revision(series) else {
+            cli_print_error!("No revision of {} was sent", self.series_name);
+            return ControlFlow::Break(());
+        };
+        // Found above
+        let revision = series.revision(rev as usize).unwrap();
+
+        let (first_commit, last_commit) = if let Some((f, l)) = &self.commits {
+            (f.clone(), l.clone())
+        } else if let Some(range) = revision.range() {
+            (String::from(range.base_commit()), String::from(range.last_commit()))
+        } else {
+            cli_print_error!("No commit range provided and none recorded for v{}", rev);
+            return ControlFlow::Break(());
+        };
+
+        let git_repo = open_git_repo_or_fail!(repo);
+        let Some(base) = resolve_commit(&git_repo, &first_commit).map(|c| c.id()) else {
+            return ControlFlow::Break(());
+        };
+        let Some(last) = resolve_commit(&git_repo, &last_commit).map(|c| c.id()) else {
+            return ControlFlow::Break(());
+        };
+        let commits = match git_repo.commit_range(&base, &last) {
+            Ok(c) => c,
+            Err(e) => {
+                cli_print_error!("Invalid commit range: {}", e);
+                return ControlFlow::Break(());
+            }
+        };
+        if commits.len() != revision.patch_ids().len() {
+            cli_print_error!(
+                "v{} was sent with {} patches but {}..{} has {} commits",
+                rev,
+                revision.patch_ids().len(),
+                first_commit,
+                last_commit,
+                commits.len()
+            );
+            return ControlFlow::Break(());
+        }
+
+        let mails = match read_mailbox(Path::new(&self.mailbox)) {
+            Ok(m) => m,
+            Err(e) => {
+                cli_print_error!("Failed to read {}: {}", self.mailbox, e);
+                return ControlFlow::Break(());
+            }
+        };
+        let trailers =
+            collect_trailers(&mails, rev, revision.cover_letter_id(), revision.patch_ids());
+
+        let mut messages = Vec::new();
+        for (commit, trailers) in commits.iter().zip(trailers.iter()) {
+            let message = match commit.message_with_tags(trailers, &order) {
+                Ok(m) => m,
+                Err(e) => {
+                    cli_print_error!("{}", e);
+                    return ControlFlow::Break(());
+                }
+            };
+            if commit.message().is_ok_and(|m| m == message) {
+                continue; // No new trailer
+            }
+            cli_print!("{}", commit.short_name());
+            for (tag, value) in trailers {
+                cli_print!("    {}: {}", tag, value);
+            }
+            messages.push((commit.id(), message));
+        }
+
+        if messages.is_empty() {
+            cli_print!("No new trailer in the replies to v{}", rev);
+            return ControlFlow::Continue(());
+        }
+        if self.dry_run {
+            return ControlFlow::Continue(());
+        }
+        let rewritten = match git_repo.reword_commits(&messages) {
+            Ok(r) => r,
+            Err(e) => {
+                cli_print_error!("Failed to apply the trailers: {}", e);
+                return ControlFlow::Break(());
+            }
+        };
+        cli_print!("{} commits updated", messages.len());
+        SeriesLog::trailers(series, rev, messages.len());
+        if self.commits.is_some() {
+            return ControlFlow::Continue(());
+        }
+
+        // Move the recorded range, used above, to the commits with the trailers
+        let range = revision.range().unwrap();
+        let new_id = |commit: &str| {
+            rewritten
+                .iter()
+                .find(|(old, _)| old.to_string() == commit)
+                .map_or(String::from(commit), |(_, new)| new.to_string())
+        };
+        let range = RevisionRange::new(
+            range.base_commit(),
+            &new_id(range.first_commit()),
+            &new_id(range.last_commit()),
+            range.branch(),
+        );
+        // The series exists, it was found above
+        repo.repo_mut()
+            .get_series_by_name_mut(self.series_name.as_str())
+            .unwrap()
+            .set_revision_range(rev as usize, range);
+        ControlFlow::Continue(())
+    }
+}
+
+impl CommandBuilder for TrailersBuilder {
+    fn add_value(&mut self, value: &str) -> Result<(), CommandBuilderError> {
+        // series, mailbox, [first commit, last commit]
+        if self.values.len() >= 4 {
+            return Err(CommandBuilderError::unexpected_value(value));
+        }
+        self.values.push(String::from(value));
+        Ok(())
+    }
+
+    fn add_flag(&mut self, flag: &str) -> Result<(), CommandBuilderError> {
+        if flag != DRY_RUN_FLAG {
+            return Err(CommandBuilderError::new(
+                CommandBuilderErrorCode::UnknownFlag,
+                String::from(flag),
+            ));
+        }
+        self.dry_run = true;
+        Ok(())
+    }
+
+    fn requires_value(&self, flag: &str) -> Result<bool, CommandBuilderError> {
+        match flag {
+            DRY_RUN_FLAG => Ok(false),
+            _ => Err(CommandBuilderError::new(
+                CommandBuilderErrorCode::UnknownFlag,
+                String::from(flag),
+            )),
+        }
+    }
+
+    fn name(&self) -> &str {
+        TRAILERS
+    }
+
+    fn build(&self) -> Result<Box<dyn Command>, CommandBuilderError> {
+        let commits = match self.values.as_slice() {
+            [_, _] => None,
+            [_, _, first_commit, last_commit] => Some((first_commit.clone(), last_commit.clone())),
+            [_, _, _] => {
+                return Err(CommandBuilderError::new(
+                    CommandBuilderErrorCode::MissingValue,
+                    String::from("Missing first or last commit sha1"),
+                ))
+            }
+            _ => {
+                return Err(CommandBuilderError::new(
+                    CommandBuilderErrorCode::MissingValue,
+                    String::from("Missing series name or mailbox"),
+                ))
+            }
+        };
+        Ok(Box::new(Trailers {
+            series_name: self.values[0].clone(),
+            mailbox: self.values[1].clone(),
+            commits,
+            dry_run: self.dry_run,
+        }))
+    }
+}
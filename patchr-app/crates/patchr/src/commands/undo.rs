@@ -0,0 +1,48 @@
+use std::ops::ControlFlow;
+
+use log::debug;
+
+use crate::user_data::user_data::UserData;
+use crate::{cli_print, cli_print_error, get_repo_or_fail, open_git_repo_or_fail};
+
+use super::{Command, CommandBuilder, CommandBuilderError, UNDO};
+
+// Restores the branch as it was before the last tag, untag
+// or trailers rewrite
+pub struct Undo;
+
+pub struct UndoBuilder;
+
+impl Undo {
+    pub fn builder() -> Box<dyn CommandBuilder> {
+        Box::new(UndoBuilder)
+    }
+}
+
+impl Command for Undo {
+    fn exec(&self, user_data: &mut UserData) -> ControlFlow<()> {
+        debug!("Undo");
+        let repo = get_repo_or_fail!(user_data);
+        let git_repo = open_git_repo_or_fail!(repo);
+        match git_repo.undo_rewrite() {
+            Ok(id) => {
+                cli_print!("HEAD restored to {}", id);
+                ControlFlow::Continue(())
+            }
+            Err(e) => {
+                cli_print_error!("Failed to undo the last rewrite: {}", e);
+                ControlFlow::Break(())
+            }
+        }
+    }
+}
+
+impl CommandBuilder for UndoBuilder {
+    fn name(&self) -> &str {
+        UNDO
+    }
+
+    fn build(&self) -> Result<Box<dyn Command>, CommandBuilderError> {
+        Ok(Box::new(Undo))
+    }
+}
@@ -6,3 +6,17 @@ macro_rules! declare_env {
 
 // Set max debug level
 declare_env!(PATCHR_DBG);
+
+// Password of the smtp transport, prompted if not set
+declare_env!(PATCHR_SMTP_PASSWORD);
+
+// Directory of all the patchr files, replaces the XDG base directories
+declare_env!(PATCHR_HOME);
+
+// XDG base directories of the data, the config and the scratch files
+declare_env!(XDG_DATA_HOME);
+declare_env!(XDG_CONFIG_HOME);
+declare_env!(XDG_RUNTIME_DIR);
+
+// Seconds to wait for another patchr process to release the data
+declare_env!(PATCHR_LOCK_TIMEOUT);
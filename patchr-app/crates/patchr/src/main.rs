@@ -4,7 +4,13 @@ use log::trace;
 use parser::parse_command_line;
 use util::init_logger;
 
-use crate::{error_code::ErrorCode, user_data::user_data::UserData};
+use crate::{
+    error_code::ErrorCode,
+    user_data::{
+        lock::LockMode,
+        user_data::{move_legacy_data_dir, UserData},
+    },
+};
 
 mod cli_output;
 mod commands;
@@ -25,8 +31,8 @@ fn read_environment() {
     }
 }
 
-fn load_user_data_or_die() -> UserData {
-    match UserData::load() {
+fn load_user_data_or_die(lock_mode: LockMode) -> UserData {
+    match UserData::load(lock_mode) {
         Ok(user_data) => user_data,
         Err(e) => {
             cli_print_error!("Failed to load user data");
@@ -39,6 +45,7 @@ fn load_user_data_or_die() -> UserData {
 fn save_user_data_or_die(user_data: &UserData) {
     if let Err(e) = user_data
         .save_root_file()
+        .and_then(|_| user_data.save_config_file())
         .and_then(|_| user_data.save_repo())
     {
         cli_print_error!("Failed to write data to disk {}", e);
@@ -54,7 +61,27 @@ fn main() {
         exit(ErrorCode::ParsingError.code());
     };
 
-    let mut user_data = load_user_data_or_die();
+    if let Err(e) = move_legacy_data_dir() {
+        cli_print_error!("{}", e);
+        exit(ErrorCode::CannotReadUserData.code());
+    }
+
+    // e.g. migrate, the data may not be readable before it runs
+    if let Some(result) = commands.iter().find_map(|c| c.exec_without_user_data()) {
+        if result.is_break() {
+            exit(ErrorCode::CommandError.code());
+        }
+        return;
+    }
+
+    // Without any command, the data directory is initialized
+    let read_only = !commands.is_empty() && commands.iter().all(|c| c.read_only());
+    let lock_mode = if read_only {
+        LockMode::Shared
+    } else {
+        LockMode::Exclusive
+    };
+    let mut user_data = load_user_data_or_die(lock_mode);
     let result = commands.iter().try_for_each(|c| c.exec(&mut user_data));
 
     if result.is_break() {
@@ -62,5 +89,7 @@ fn main() {
         exit(ErrorCode::CommandError.code());
     }
 
-    save_user_data_or_die(&user_data);
+    if !read_only {
+        save_user_data_or_die(&user_data);
+    }
 }
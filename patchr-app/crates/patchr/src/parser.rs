@@ -18,6 +18,8 @@ pub fn parse_command_line(command_line: Vec<String>) -> Option<Vec<Box<dyn Comma
 struct Lexer {
     preview: LinkedList<Token>,
     it: Box<dyn Iterator<Item = String>>,
+    // set by a bare "--", the next arguments are values
+    options_ended: bool,
 }
 
 struct Parser;
@@ -70,10 +72,11 @@ impl Lexer {
         Self {
             preview: LinkedList::new(),
             it,
+            options_ended: false,
         }
     }
 
-    fn update_preview(&mut self, c: &String) {
+    fn update_preview(&mut self, c: &str) {
         c[1..].chars().for_each(|f| {
             self.preview
                 .push_back(Token::new(TokenType::Flag, String::from(f)));
@@ -89,10 +92,15 @@ impl Iterator for Lexer {
             return self.preview.pop_front();
         }
 
-        let Some(c) = self.it.next() else {
-            return None;
-        };
-        if c.starts_with("-") {
+        let c = self.it.next()?;
+        if self.options_ended {
+            Some(Token::new(TokenType::Value, c))
+        } else if c == "--" {
+            self.options_ended = true;
+            self.next()
+        } else if let Some(long_flag) = c.strip_prefix("--") {
+            Some(Token::new(TokenType::Flag, String::from(long_flag)))
+        } else if c.starts_with("-") {
             self.update_preview(&c);
             self.preview.pop_front()
         } else {
@@ -121,7 +129,7 @@ impl Parser {
             VERBOSE => Ok(Box::new(SetVerbose::new())),
             HELP => Ok(Box::new(Help::new())),
             _ => Err(ParsingError::new(
-                String::from(format!("Parsing failed due to unknown option",)),
+                String::from("Parsing failed due to unknown option"),
                 token,
             )),
         }
@@ -148,7 +156,7 @@ impl Parser {
                 return Ok(None);
             }
         }
-        self.parse_global_option(token).map(|r| Some(r))
+        self.parse_global_option(token).map(Some)
     }
 
     fn parse_value(
@@ -224,7 +232,7 @@ impl Parser {
                     Err(e) => {
                         cli_print_error!("Cannot execute command {}", builder.name());
                         cli_print_error!("{}", e.message());
-                        return None;
+                        None
                     }
                 }
             }
@@ -1,3 +1,7 @@
 pub mod root_file;
+#[allow(clippy::module_inception)]
 pub mod user_data;
-pub mod mailing_list;
\ No newline at end of file
+pub mod mailing_list;
+pub mod lock;
+pub mod migration;
+pub mod git_storage;
\ No newline at end of file
@@ -0,0 +1,298 @@
+use std::{collections::BTreeMap, path::Path};
+
+use common::constants::PROJECT_VERSION;
+use git::{
+    repo::{MergeConflict, Repo, RepoMetadata},
+    series::PendingSend,
+    util::{CommitId, GitRepo, DATA_REF},
+    GitError,
+};
+use log::debug;
+use serde_json::Value;
+
+use super::user_data::{UserDataError, UserDataErrorCode};
+
+// The data ref of a remote is fetched under this prefix, then merged
+const REMOTE_DATA_REF_PREFIX: &str = "refs/patchr/remotes/";
+
+// Pending sends by series. They refer to the mails generated on this
+// machine, they are kept in the data directory rather than in the data ref
+pub type PendingSends = BTreeMap<String, PendingSend>;
+
+// Data of a repo as last read from or written to its data ref, the next
+// commit of the data is based on it
+pub struct GitData {
+    commit: Option<String>,
+    content: String,
+}
+
+pub enum FetchResult {
+    UpToDate,
+    FastForward,
+    // series changed on both sides, the local version is kept
+    Merged(Vec<MergeConflict>),
+}
+
+fn open_git_repo(path: &str) -> Result<GitRepo, UserDataError> {
+    GitRepo::open(path).ok_or_else(|| {
+        UserDataError::new_with_message(
+            UserDataErrorCode::NotAGitRepo,
+            format!("Cannot open the repo {}", path),
+        )
+    })
+}
+
+fn read_err(e: impl ToString) -> UserDataError {
+    UserDataError::new_with_message(
+        UserDataErrorCode::FailedToReadData,
+        format!("{}: {}", DATA_REF, e.to_string()),
+    )
+}
+
+fn save_err(e: impl ToString) -> UserDataError {
+    UserDataError::new_with_message(
+        UserDataErrorCode::FailedToSaveData,
+        format!("Failed to commit the data to {}, {}", DATA_REF, e.to_string()),
+    )
+}
+
+fn commit_id(commit: Option<&str>) -> Result<Option<CommitId>, GitError> {
+    commit.map(CommitId::new).transpose()
+}
+
+// Content of the data ref, without the pending sends
+fn to_json(repo: &Repo) -> Result<String, UserDataError> {
+    let mut value = serde_json::to_value(repo).map_err(save_err)?;
+    if let Some(series) = value.get_mut("series").and_then(Value::as_array_mut) {
+        for s in series.iter_mut().filter_map(Value::as_object_mut) {
+            s.remove("pending_send");
+        }
+    }
+    serde_json::to_string_pretty(&value).map_err(save_err)
+}
+
+pub fn pending_sends(repo: &Repo) -> PendingSends {
+    repo.series()
+        .iter()
+        .filter_map(|s| s.pending_send().map(|p| (String::from(s.name()), p.clone())))
+        .collect()
+}
+
+fn take_pending_sends(repo: &mut Repo) -> PendingSends {
+    let pending = pending_sends(repo);
+    for name in pending.keys() {
+        if let Some(series) = repo.get_series_by_name_mut(name) {
+            series.set_pending_send(None);
+        }
+    }
+    pending
+}
+
+// The sends of the series that no longer exist are dropped
+pub fn restore_pending_sends(repo: &mut Repo, pending: PendingSends) {
+    for (name, send) in pending {
+        if let Some(series) = repo.get_series_by_name_mut(&name) {
+            series.set_pending_send(Some(send));
+        }
+    }
+}
+
+// The pending sends take no part in a merge with the data in git, they are
+// restored in the repo once merged, or left unchanged on failure
+fn merge_without_pending_sends<T>(
+    repo: &mut Repo, merge: impl FnOnce(&mut Repo) -> Result<T, UserDataError>,
+) -> Result<T, UserDataError> {
+    let pending = take_pending_sends(repo);
+    let res = merge(repo);
+    restore_pending_sends(repo, pending);
+    res
+}
+
+// Data written by another patchr, it cannot be merged in another version.
+// The pending sends committed by older versions are not ours
+fn parse_data(content: &str, origin: &str) -> Result<Repo, UserDataError> {
+    let mut repo: Repo = serde_json::from_str(content).map_err(read_err)?;
+    take_pending_sends(&mut repo);
+    if repo.version() != PROJECT_VERSION {
+        return Err(read_err(format!(
+            "the data {} is in version {}, patchr is in version {}",
+            origin,
+            repo.version(),
+            PROJECT_VERSION
+        )));
+    }
+    Ok(repo)
+}
+
+// Data of a repo stored in git, empty if nothing was committed yet. Its
+// version is checked along with the data files
+pub fn read_git_data(meta: &RepoMetadata) -> Result<(Repo, GitData), UserDataError> {
+    let git_repo = open_git_repo(meta.path())?;
+    let Some((commit, content)) = git_repo.read_data(DATA_REF).map_err(read_err)? else {
+        // nothing to commit until a series is added
+        let repo = Repo::new();
+        let data = GitData {
+            commit: None,
+            content: to_json(&repo)?,
+        };
+        return Ok((repo, data));
+    };
+    let mut repo = serde_json::from_str(&content).map_err(read_err)?;
+    take_pending_sends(&mut repo);
+    let data = GitData {
+        commit: Some(commit.to_string()),
+        content,
+    };
+    Ok((repo, data))
+}
+
+// Content of the data ref of a repo, for the migration
+pub fn read_git_data_value(repo_path: &Path) -> Result<Option<Value>, UserDataError> {
+    let git_repo = open_git_repo(&repo_path.to_string_lossy())?;
+    let Some((_, content)) = git_repo.read_data(DATA_REF).map_err(read_err)? else {
+        return Ok(None);
+    };
+    serde_json::from_str(&content).map(Some).map_err(read_err)
+}
+
+pub fn write_git_data_value(repo_path: &Path, content: &Value) -> Result<(), UserDataError> {
+    let git_repo = open_git_repo(&repo_path.to_string_lossy())?;
+    let current = git_repo
+        .read_data(DATA_REF)
+        .map_err(read_err)?
+        .map(|(c, _)| c);
+    let content = serde_json::to_string_pretty(content).map_err(save_err)?;
+    let message = format!("patchr: migrate the data to {}", PROJECT_VERSION);
+    let parents: Vec<&CommitId> = current.iter().collect();
+    git_repo
+        .write_data(DATA_REF, &content, &parents, current.as_ref(), &message)
+        .map_err(save_err)?;
+    Ok(())
+}
+
+// New commit of the data if it changed since it was read
+pub fn commit_git_data(
+    meta: &RepoMetadata, repo: &Repo, git_data: &GitData, message: &str,
+) -> Result<(), UserDataError> {
+    let content = to_json(repo)?;
+    if content == git_data.content {
+        return Ok(());
+    }
+    debug!("Commit the data to {}", DATA_REF);
+    let git_repo = open_git_repo(meta.path())?;
+    let current = commit_id(git_data.commit.as_deref()).map_err(save_err)?;
+    let parents: Vec<&CommitId> = current.iter().collect();
+    git_repo
+        .write_data(DATA_REF, &content, &parents, current.as_ref(), message)
+        .map_err(save_err)?;
+    Ok(())
+}
+
+// Merges the data already in the data ref, e.g. fetched from a
+// co-maintainer, into the local data, when the repo starts to be stored in
+// git. Both versions are unrelated, every series differing is a conflict
+pub fn adopt_git_data(
+    meta: &RepoMetadata, repo: &mut Repo,
+) -> Result<(GitData, Vec<MergeConflict>), UserDataError> {
+    let (_, git_data) = read_git_data(meta)?;
+    if git_data.commit.is_none() {
+        return Ok((git_data, Vec::new()));
+    }
+    let stored = parse_data(&git_data.content, "in git")?;
+    let conflicts = merge_without_pending_sends(repo, |repo| {
+        let (merged, conflicts) = Repo::merge(None, repo, &stored).map_err(read_err)?;
+        *repo = merged;
+        Ok(conflicts)
+    })?;
+    Ok((git_data, conflicts))
+}
+
+// Fetches the data ref of the remote and merges it into the local data. The
+// merge is committed right away, from the common version of both sides
+pub fn fetch_git_data(
+    meta: &RepoMetadata, repo: &mut Repo, git_data: &mut GitData, remote: &str,
+) -> Result<FetchResult, UserDataError> {
+    let git_repo = open_git_repo(meta.path())?;
+    let remote_ref = format!("{}{}/data", REMOTE_DATA_REF_PREFIX, remote);
+    git_repo
+        .fetch_ref(remote, DATA_REF, &remote_ref)
+        .map_err(read_err)?;
+    let Some((fetched, content)) = git_repo.read_data(&remote_ref).map_err(read_err)? else {
+        return Err(read_err(format!("{} was not fetched", remote_ref)));
+    };
+    let theirs = parse_data(&content, format!("of {}", remote).as_str())?;
+
+    let local = commit_id(git_data.commit.as_deref()).map_err(read_err)?;
+    let is_ancestor = |a: &CommitId, b: &CommitId| -> Result<bool, UserDataError> {
+        Ok(a.to_string() == b.to_string() || git_repo.is_ancestor(a, b).map_err(read_err)?)
+    };
+    if let Some(l) = local.as_ref() {
+        if is_ancestor(&fetched, l)? {
+            return Ok(FetchResult::UpToDate);
+        }
+    }
+    let unchanged = match local.as_ref() {
+        Some(l) => is_ancestor(l, &fetched)? && to_json(repo)? == git_data.content,
+        None => repo.series().is_empty(),
+    };
+    if unchanged {
+        debug!("Fast-forward {} to {}", DATA_REF, fetched);
+        git_repo
+            .move_ref(DATA_REF, &fetched, local.as_ref(), "patchr: fast-forward")
+            .map_err(save_err)?;
+        let pending = pending_sends(repo);
+        *repo = theirs;
+        restore_pending_sends(repo, pending);
+        *git_data = GitData {
+            commit: Some(fetched.to_string()),
+            content,
+        };
+        return Ok(FetchResult::FastForward);
+    }
+
+    // histories without a common commit, or whose common version cannot be
+    // read, are merged without base
+    let base = match local.as_ref() {
+        Some(l) => git_repo
+            .merge_base(l, &fetched)
+            .and_then(|b| git_repo.read_data_at(&b))
+            .ok()
+            .and_then(|c| parse_data(&c, "of the merge base").ok()),
+        None => None,
+    };
+    merge_without_pending_sends(repo, |repo| {
+        let (merged, conflicts) = Repo::merge(base.as_ref(), repo, &theirs).map_err(read_err)?;
+        let content = to_json(&merged)?;
+        let mut parents: Vec<&CommitId> = local.iter().collect();
+        parents.push(&fetched);
+        let message = format!("patchr: merge the data of {}", remote);
+        let commit = git_repo
+            .write_data(DATA_REF, &content, &parents, local.as_ref(), &message)
+            .map_err(save_err)?;
+        *repo = merged;
+        *git_data = GitData {
+            commit: Some(commit.to_string()),
+            content,
+        };
+        Ok(FetchResult::Merged(conflicts))
+    })
+}
+
+// The remote refuses the push if its data was not fetched and merged first
+pub fn push_git_data(
+    meta: &RepoMetadata, git_data: &GitData, remote: &str,
+) -> Result<(), UserDataError> {
+    if git_data.commit.is_none() {
+        return Err(UserDataError::new_with_message(
+            UserDataErrorCode::InputError,
+            format!("Nothing was committed to {} yet", DATA_REF),
+        ));
+    }
+    let git_repo = open_git_repo(meta.path())?;
+    git_repo.push_ref(remote, DATA_REF).map_err(|e| {
+        UserDataError::new_with_message(
+            UserDataErrorCode::FailedToSaveData,
+            format!("{}, fetch the data of {} first if it changed", e, remote),
+        )
+    })
+}
@@ -0,0 +1,95 @@
+use std::{
+    env,
+    fs::{self, File},
+    io,
+    os::fd::AsRawFd,
+    thread,
+    time::{Duration, Instant},
+};
+
+use log::debug;
+
+use crate::{cli_print, environment::PATCHR_LOCK_TIMEOUT};
+
+use super::user_data::{root_file_dir_path, UserDataError, UserDataErrorCode};
+
+pub const LOCK_FILE_NAME: &str = "lock";
+const DEFAULT_LOCK_TIMEOUT_SECS: u64 = 10;
+const LOCK_RETRY_DELAY: Duration = Duration::from_millis(100);
+
+// Commands reading the data share it, the ones writing it need it for
+// themselves from the load to the save
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LockMode {
+    Shared,
+    Exclusive,
+}
+
+// Advisory lock of the data directory, released when dropped or when the
+// process exits
+pub struct DataLock {
+    _file: File,
+}
+
+fn lock_timeout() -> Duration {
+    let secs = env::var(PATCHR_LOCK_TIMEOUT)
+        .ok()
+        .and_then(|t| t.parse().ok())
+        .unwrap_or(DEFAULT_LOCK_TIMEOUT_SECS);
+    Duration::from_secs(secs)
+}
+
+// False if another process holds an incompatible lock
+fn try_lock(file: &File, mode: LockMode) -> Result<bool, io::Error> {
+    let operation = match mode {
+        LockMode::Shared => libc::LOCK_SH,
+        LockMode::Exclusive => libc::LOCK_EX,
+    };
+    if unsafe { libc::flock(file.as_raw_fd(), operation | libc::LOCK_NB) } == 0 {
+        return Ok(true);
+    }
+    let e = io::Error::last_os_error();
+    if e.raw_os_error() == Some(libc::EWOULDBLOCK) {
+        Ok(false)
+    } else {
+        Err(e)
+    }
+}
+
+// Waits for the other patchr processes to release the data, up to
+// PATCHR_LOCK_TIMEOUT seconds
+pub fn lock_data_dir(mode: LockMode) -> Result<DataLock, UserDataError> {
+    let to_err = |e: String| UserDataError::new_with_message(UserDataErrorCode::FsError, e);
+    let dir = root_file_dir_path()?;
+    fs::create_dir_all(&dir).map_err(|e| to_err(e.to_string()))?;
+    let file = File::options()
+        .create(true)
+        .truncate(false)
+        .write(true)
+        .open(dir.join(LOCK_FILE_NAME))
+        .map_err(|e| to_err(format!("Failed to open the lock file, {}", e)))?;
+
+    debug!("Lock the data directory: {:?}", mode);
+    let deadline = Instant::now() + lock_timeout();
+    let mut waiting = false;
+    loop {
+        match try_lock(&file, mode) {
+            Ok(true) => return Ok(DataLock { _file: file }),
+            Ok(false) => (),
+            Err(e) => {
+                return Err(to_err(format!("Failed to lock the data, {}", e)));
+            }
+        }
+        if Instant::now() >= deadline {
+            return Err(UserDataError::new_with_message(
+                UserDataErrorCode::DataBusy,
+                String::from("The data is used by another patchr process"),
+            ));
+        }
+        if !waiting {
+            cli_print!("Waiting for another patchr process to release the data...");
+            waiting = true;
+        }
+        thread::sleep(LOCK_RETRY_DELAY);
+    }
+}
@@ -11,10 +11,14 @@ lazy_static! {
 pub struct MailingList {
     name: String,
     email: String,
+    // New revisions reply to the cover letter of the previous one
+    thread_revisions: bool,
+    // The mails give the base commit and the prerequisite patches
+    base_info: bool,
 }
 
 impl MailingList {
-    pub fn new(name: &str, email: &str) -> Option<Self> {
+    pub fn new(name: &str, email: &str, thread_revisions: bool, base_info: bool) -> Option<Self> {
         if !EmailAddress::is_valid(email) {
             return None;
         }
@@ -26,6 +30,8 @@ impl MailingList {
         Some(MailingList {
             name: String::from(name),
             email: String::from(email),
+            thread_revisions,
+            base_info,
         })
     }
 
@@ -36,4 +42,12 @@ impl MailingList {
     pub fn email(&self) -> &str {
         self.email.as_str()
     }
+
+    pub fn thread_revisions(&self) -> bool {
+        self.thread_revisions
+    }
+
+    pub fn base_info(&self) -> bool {
+        self.base_info
+    }
 }
\ No newline at end of file
@@ -0,0 +1,352 @@
+use std::{
+    fs::{self, File},
+    io::BufReader,
+    path::{Path, PathBuf},
+};
+
+use chrono::Local;
+use common::constants::PROJECT_VERSION;
+use log::debug;
+use serde_json::{json, Value};
+
+use git::util::DATA_REF;
+
+use super::{
+    git_storage::{read_git_data_value, write_git_data_value},
+    root_file::ROOT_FILE_NAME,
+    user_data::{
+        config_file_path, root_file_dir_path, write_data_file, UserDataError, UserDataErrorCode,
+    },
+};
+
+const BACKUP_DIR: &str = "backups";
+
+// The user config moved from the root file to its own file in this version
+const CONFIG_FILE_VERSION: &str = "0.16";
+
+// Changes of the data format from one version to the next, for the root
+// file, the repo files and the config file
+struct Migration {
+    from: &'static str,
+    to: &'static str,
+    root: fn(&mut Value),
+    repo: fn(&mut Value),
+    config: fn(&mut Value),
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum FileKind {
+    Root,
+    Repo,
+    Config,
+    // data of a repo stored in git, see git_storage
+    GitRepo,
+}
+
+// Must be ordered, the versions between two migrations only changed the
+// version of the files
+const MIGRATIONS: [Migration; 2] = [
+    Migration {
+        from: "0.13",
+        to: "0.14",
+        root: no_change,
+        repo: migrate_repo_0_13_to_0_14,
+        config: no_change,
+    },
+    Migration {
+        from: "0.15",
+        to: "0.16",
+        root: migrate_root_0_15_to_0_16,
+        repo: migrate_repo_0_15_to_0_16,
+        config: no_change,
+    },
+];
+
+// Data file in an older version, its content is migrated in memory
+pub struct OutdatedFile {
+    path: PathBuf,
+    version: String,
+    content: Value,
+    // repo whose data ref holds the content
+    git_repo: Option<PathBuf>,
+}
+
+impl OutdatedFile {
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn version(&self) -> &str {
+        &self.version
+    }
+}
+
+fn read_error(path: &Path, message: &str) -> UserDataError {
+    UserDataError::new_with_message(
+        UserDataErrorCode::FailedToReadData,
+        format!("{}: {}", path.to_string_lossy(), message),
+    )
+}
+
+fn read_json(path: &Path) -> Result<Value, UserDataError> {
+    let file = File::open(path).map_err(|e| read_error(path, &e.to_string()))?;
+    serde_json::from_reader(BufReader::new(file)).map_err(|e| read_error(path, &e.to_string()))
+}
+
+// Root file and repo files of the data directory, and the config file
+fn data_files(dir: &Path) -> Result<Vec<(FileKind, PathBuf, Value)>, UserDataError> {
+    let root_path = dir.join(ROOT_FILE_NAME);
+    if !root_path.is_file() {
+        return Ok(Vec::new()); // nothing was saved yet
+    }
+    let root = read_json(&root_path)?;
+    let repos: Vec<&Value> = root
+        .get("repos")
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+        .collect();
+    let in_git = |r: &Value| r.get("git_storage").and_then(Value::as_bool) == Some(true);
+    let repo_paths: Vec<PathBuf> = repos
+        .iter()
+        .filter(|r| !in_git(r))
+        .filter_map(|r| r.get("name")?.as_str())
+        .map(|name| dir.join(name))
+        .filter(|p| p.is_file())
+        .collect();
+    let git_repo_paths: Vec<PathBuf> = repos
+        .iter()
+        .filter(|r| in_git(r))
+        .filter_map(|r| r.get("path")?.as_str())
+        .map(PathBuf::from)
+        .collect();
+
+    let mut files = vec![(FileKind::Root, root_path, root)];
+    let config_path = config_file_path()?;
+    if config_path.is_file() {
+        let content = read_json(&config_path)?;
+        files.push((FileKind::Config, config_path, content));
+    }
+    for path in repo_paths {
+        let content = read_json(&path)?;
+        files.push((FileKind::Repo, path, content));
+    }
+    for path in git_repo_paths {
+        if let Some(content) = read_git_data_value(&path)? {
+            files.push((FileKind::GitRepo, path, content));
+        }
+    }
+    Ok(files)
+}
+
+// Data files that are not in the current version, along with their content
+// in the current version
+pub fn outdated_data_files() -> Result<Vec<OutdatedFile>, UserDataError> {
+    let mut outdated = Vec::new();
+    for (kind, path, mut content) in data_files(&root_file_dir_path()?)? {
+        let Some(version) = content.get("version").and_then(Value::as_str) else {
+            return Err(read_error(&path, "Missing version"));
+        };
+        let version = String::from(version);
+        if version == PROJECT_VERSION {
+            continue;
+        }
+        let start = MIGRATIONS.iter().position(|m| m.from == version).or_else(|| {
+            MIGRATIONS.iter().position(|m| m.to == version).map(|i| i + 1)
+        });
+        let Some(start) = start else {
+            return Err(read_error(&path, &format!("Unknown version {}", version)));
+        };
+        // split from the root file
+        let mut config: Option<Value> = None;
+        for m in &MIGRATIONS[start..] {
+            debug!("Migrate {} to {}", path.to_string_lossy(), m.to);
+            let migrate = match kind {
+                FileKind::Root => m.root,
+                FileKind::Repo | FileKind::GitRepo => m.repo,
+                FileKind::Config => m.config,
+            };
+            migrate(&mut content);
+            set(&mut content, "version", json!(m.to));
+            if let Some(c) = config.as_mut() {
+                (m.config)(c);
+                set(c, "version", json!(m.to));
+            }
+            if kind == FileKind::Root && m.to == CONFIG_FILE_VERSION {
+                config = content
+                    .as_object_mut()
+                    .and_then(|r| r.remove("user_config"))
+                    .map(|c| json!({"version": m.to, "user_config": c}));
+            }
+        }
+        if let Some(content) = config {
+            outdated.push(OutdatedFile {
+                path: config_file_path()?,
+                version: version.clone(),
+                content,
+                git_repo: None,
+            });
+        }
+        let (path, git_repo) = match kind {
+            FileKind::GitRepo => (path.join(DATA_REF), Some(path)),
+            _ => (path, None),
+        };
+        outdated.push(OutdatedFile {
+            path,
+            version,
+            content,
+            git_repo,
+        });
+    }
+    Ok(outdated)
+}
+
+// Tar archive of the files as they are on disk, in the backup directory.
+// The data stored in git keeps its previous versions in the history of
+// the data ref
+pub fn backup_data_files(files: &[OutdatedFile]) -> Result<PathBuf, UserDataError> {
+    let to_err = |e: std::io::Error| {
+        UserDataError::new_with_message(UserDataErrorCode::FsError, e.to_string())
+    };
+    let dir = root_file_dir_path()?.join(BACKUP_DIR);
+    fs::create_dir_all(&dir).map_err(to_err)?;
+    let name = format!("migration_backup_{}.tar", Local::now().format("%Y_%m_%d_%H_%M_%S"));
+    let path = dir.join(name);
+
+    let mut archive = tar::Builder::new(File::create(&path).map_err(to_err)?);
+    for file in files
+        .iter()
+        .filter(|f| f.git_repo.is_none() && f.path.exists())
+    {
+        // the names of the data files and of the config file are distinct
+        let name = file.path.file_name().unwrap_or_default();
+        archive
+            .append_path_with_name(&file.path, name)
+            .map_err(to_err)?;
+    }
+    archive.finish().map_err(to_err)?;
+    Ok(path)
+}
+
+pub fn write_data_files(files: &[OutdatedFile]) -> Result<(), UserDataError> {
+    for file in files {
+        if let Some(repo) = file.git_repo.as_ref() {
+            write_git_data_value(repo, &file.content)?;
+            continue;
+        }
+        if let Some(dir) = file.path.parent() {
+            fs::create_dir_all(dir).map_err(|e| {
+                UserDataError::new_with_message(UserDataErrorCode::FsError, e.to_string())
+            })?;
+        }
+        write_data_file(&file.path, &file.content).map_err(|e| {
+            UserDataError::new_with_message(
+                UserDataErrorCode::FailedToSaveData,
+                format!("{}: {}", file.path.to_string_lossy(), e),
+            )
+        })?;
+    }
+    Ok(())
+}
+
+fn set(value: &mut Value, key: &str, new_value: Value) {
+    if let Some(object) = value.as_object_mut() {
+        object.insert(String::from(key), new_value);
+    }
+}
+
+// Values of the fields of an object, the elements for the arrays
+fn entries<'a>(value: &'a mut Value, keys: &[&str]) -> Vec<&'a mut Value> {
+    let Some(object) = value.as_object_mut() else {
+        return Vec::new();
+    };
+    let mut entries = Vec::new();
+    for (key, value) in object.iter_mut() {
+        if !keys.contains(&key.as_str()) {
+            continue;
+        }
+        match value {
+            Value::Array(values) => entries.extend(values.iter_mut()),
+            Value::Null => (),
+            v => entries.push(v),
+        }
+    }
+    entries
+}
+
+fn series(repo: &mut Value) -> Vec<&mut Value> {
+    entries(repo, &["series"])
+}
+
+fn user_config(root: &mut Value) -> Vec<&mut Value> {
+    entries(root, &["user_config"])
+}
+
+fn lists(root: &mut Value) -> Vec<&mut Value> {
+    root.get_mut("lists")
+        .and_then(Value::as_object_mut)
+        .map(|l| l.values_mut().collect())
+        .unwrap_or_default()
+}
+
+fn no_change(_content: &mut Value) {}
+
+fn migrate_repo_0_13_to_0_14(repo: &mut Value) {
+    // Add an empty cc field to the series
+    for s in series(repo) {
+        set(s, "cc", json!(""));
+    }
+}
+
+fn migrate_root_0_15_to_0_16(root: &mut Value) {
+    for l in lists(root) {
+        // Revisions can be threaded
+        set(l, "thread_revisions", json!(false));
+        // The mails can give their base commit
+        set(l, "base_info", json!(false));
+    }
+    for c in user_config(root) {
+        // Transport used to send the series and directory of the maildir one
+        set(c, "transport", Value::Null);
+        set(c, "maildir", Value::Null);
+        // Position of the trailers added to the commits and shortcuts
+        // expanded by tag and untag
+        set(c, "trailer_order", Value::Null);
+        set(c, "trailer_aliases", json!({}));
+        set(c, "people", json!({}));
+        // Length of the commit ids in the Fixes trailers
+        set(c, "abbrev_length", Value::Null);
+    }
+    // The data of a repo can be stored in git
+    for r in entries(root, &["repos"]) {
+        set(r, "git_storage", json!(false));
+    }
+}
+
+fn migrate_repo_0_15_to_0_16(repo: &mut Value) {
+    for s in series(repo) {
+        // Revisions record the range of commits they were sent with and the
+        // message ids used to thread the next ones
+        set(s, "initial_revision", json!({"content": ""}));
+        for rev in entries(s, &["initial_revision", "revisions"]) {
+            set(rev, "range", Value::Null);
+            set(rev, "cover_letter_id", Value::Null);
+            set(rev, "patch_ids", json!([]));
+        }
+        set(s, "thread_revisions", Value::Null);
+        // Sends and exports are recorded in a structured history, the old
+        // logs do not hold enough information to be converted
+        set(s, "sends", json!([]));
+        set(s, "exports", json!([]));
+        // The mails of a failed send are kept to resume it
+        set(s, "pending_send", Value::Null);
+        // Subject prefix of the sends
+        set(s, "subject_tag", Value::Null);
+        set(s, "drop_v1", json!(false));
+        // Branch of the series, the revision it is based on, and whether the
+        // mails give their base commit
+        set(s, "branch", Value::Null);
+        set(s, "base", Value::Null);
+        set(s, "base_info", Value::Null);
+    }
+}
@@ -1,7 +1,14 @@
 use std::collections::HashMap;
 
 use common::constants::PROJECT_VERSION;
-use git::{repo::RepoMetadata, util::find_repo_root};
+use email_address::EmailAddress;
+use git::{
+    repo::RepoMetadata,
+    trailers::{is_trailer_tag, TrailerOrder},
+    util::{find_repo_root, is_sha256_repo, CommitTag},
+};
+use lazy_static::lazy_static;
+use regex::Regex;
 use serde::{Deserialize, Serialize};
 
 use super::{
@@ -10,6 +17,27 @@ use super::{
 };
 
 pub const ROOT_FILE_NAME: &str = "root.json";
+pub const CONFIG_FILE_NAME: &str = "config.json";
+
+// Ways of sending the series, git send-email is the default
+pub const TRANSPORT_SEND_EMAIL: &str = "sendemail";
+pub const TRANSPORT_SMTP: &str = "smtp";
+pub const TRANSPORT_SENDMAIL: &str = "sendmail";
+pub const TRANSPORT_MAILDIR: &str = "maildir";
+pub const TRANSPORTS: [&str; 4] = [
+    TRANSPORT_SEND_EMAIL,
+    TRANSPORT_SMTP,
+    TRANSPORT_SENDMAIL,
+    TRANSPORT_MAILDIR,
+];
+
+// Commit id length of the Fixes trailers expected by the kernel
+pub const DEFAULT_ABBREV_LENGTH: usize = 12;
+
+lazy_static! {
+    static ref SHORTCUT_REGEX: Regex = Regex::new(r"^[a-zA-Z0-9_]{1,20}$").unwrap();
+    static ref IDENTITY_REGEX: Regex = Regex::new(r"^[^<>]*[^<>\s]\s*<([^<>]+)>$").unwrap();
+}
 
 #[derive(Clone, Serialize, Deserialize, Debug)]
 pub struct UserConfig {
@@ -20,17 +48,32 @@ pub struct UserConfig {
     smtp_user: Option<String>,
     smtp_port: Option<u16>,
     smtp_encryption: Option<String>,
+    transport: Option<String>,
+    maildir: Option<String>,
     cv_skel: Option<String>,
+    trailer_order: Option<String>,
+    // Length of the commit ids in the Fixes trailers
+    abbrev_length: Option<usize>,
+    // Alias -> tag, e.g. ack -> Acked-by
+    trailer_aliases: HashMap<String, String>,
+    // Nickname -> identity, e.g. alice -> Alice Example <alice@example.org>
+    people: HashMap<String, String>,
 }
 
 #[derive(Serialize, Deserialize)]
 pub struct RootFile {
     version: String,
-    user_config: UserConfig,
     lists: HashMap<String, MailingList>,
     repos: Vec<RepoMetadata>,
 }
 
+// The user config is kept apart from the data, in the config directory
+#[derive(Serialize, Deserialize)]
+pub struct ConfigFile {
+    version: String,
+    user_config: UserConfig,
+}
+
 impl UserConfig {
     pub fn new() -> Self {
         Self {
@@ -41,12 +84,18 @@ impl UserConfig {
             smtp_user: None,
             smtp_port: None,
             smtp_encryption: None,
+            transport: None,
+            maildir: None,
             cv_skel: None,
+            trailer_order: None,
+            abbrev_length: None,
+            trailer_aliases: HashMap::new(),
+            people: HashMap::new(),
         }
     }
 
     pub fn editor(&self) -> Option<&str> {
-        self.editor.as_ref().map(String::as_str)
+        self.editor.as_deref()
     }
 
     pub fn set_editor(&mut self, editor: &str) {
@@ -54,15 +103,16 @@ impl UserConfig {
     }
 
     pub fn send_command(&self) -> Option<&str> {
-        self.send_command.as_ref().map(String::as_str)
+        self.send_command.as_deref()
     }
 
     pub fn set_send_command(&mut self, send_command: Option<&str>) {
         self.send_command = send_command.map(String::from);
     }
 
+    #[allow(clippy::wrong_self_convention)]
     pub fn from_email(&self) -> Option<&str> {
-        self.from_email.as_ref().map(String::as_str)
+        self.from_email.as_deref()
     }
 
     pub fn set_from_email(&mut self, from_email: &str) {
@@ -70,7 +120,7 @@ impl UserConfig {
     }
 
     pub fn smtp_server(&self) -> Option<&str> {
-        self.smtp_server.as_ref().map(String::as_str)
+        self.smtp_server.as_deref()
     }
 
     pub fn set_smtp_server(&mut self, smtp_server: Option<&str>) {
@@ -78,7 +128,7 @@ impl UserConfig {
     }
 
     pub fn smtp_user(&self) -> Option<&str> {
-        self.smtp_user.as_ref().map(String::as_str)
+        self.smtp_user.as_deref()
     }
 
     pub fn set_smtp_user(&mut self, smtp_user: Option<&str>) {
@@ -93,8 +143,118 @@ impl UserConfig {
         self.smtp_port = smtp_port;
     }
 
+    pub fn transport(&self) -> Option<&str> {
+        self.transport.as_deref()
+    }
+
+    pub fn set_transport(&mut self, transport: Option<&str>) {
+        self.transport = transport.map(String::from);
+    }
+
+    pub fn maildir(&self) -> Option<&str> {
+        self.maildir.as_deref()
+    }
+
+    pub fn set_maildir(&mut self, maildir: Option<&str>) {
+        self.maildir = maildir.map(String::from);
+    }
+
+    // Validated when set, see TrailerOrder::parse
+    pub fn trailer_order(&self) -> TrailerOrder {
+        self.trailer_order
+            .as_deref()
+            .and_then(TrailerOrder::parse)
+            .unwrap_or_default()
+    }
+
+    pub fn set_trailer_order(&mut self, trailer_order: Option<&str>) {
+        self.trailer_order = trailer_order.map(String::from);
+    }
+
+    pub fn abbrev_length(&self) -> usize {
+        self.abbrev_length.unwrap_or(DEFAULT_ABBREV_LENGTH)
+    }
+
+    pub fn set_abbrev_length(&mut self, abbrev_length: Option<usize>) {
+        self.abbrev_length = abbrev_length;
+    }
+
+    pub fn trailer_aliases(&self) -> &HashMap<String, String> {
+        &self.trailer_aliases
+    }
+
+    pub fn set_trailer_alias(&mut self, alias: &str, tag: &str) -> Result<(), UserDataError> {
+        if !SHORTCUT_REGEX.is_match(alias) || !is_trailer_tag(tag) {
+            return Err(UserDataError::new_with_message(
+                UserDataErrorCode::InputError,
+                String::from(
+                    "The alias must be an alphanumeric string and the tag a valid \
+                     trailer name, e.g. Acked-by",
+                ),
+            ));
+        }
+        self.trailer_aliases
+            .insert(String::from(alias), String::from(tag));
+        Ok(())
+    }
+
+    pub fn delete_trailer_alias(&mut self, alias: &str) -> Result<(), UserDataError> {
+        match self.trailer_aliases.remove(alias) {
+            Some(_) => Ok(()),
+            None => Err(UserDataError::new_with_message(
+                UserDataErrorCode::InputError,
+                format!("Alias {} is not known", alias),
+            )),
+        }
+    }
+
+    // The aliases take precedence over the built-in rb and so
+    pub fn tag(&self, name: &str) -> CommitTag {
+        match self.trailer_aliases.get(name) {
+            Some(tag) => CommitTag::Custom(tag.clone()),
+            None => CommitTag::from(name),
+        }
+    }
+
+    pub fn people(&self) -> &HashMap<String, String> {
+        &self.people
+    }
+
+    pub fn set_person(&mut self, nickname: &str, identity: &str) -> Result<(), UserDataError> {
+        let valid_identity = IDENTITY_REGEX
+            .captures(identity)
+            .is_some_and(|c| EmailAddress::is_valid(&c[1]));
+        if !SHORTCUT_REGEX.is_match(nickname) || !valid_identity {
+            return Err(UserDataError::new_with_message(
+                UserDataErrorCode::InputError,
+                String::from(
+                    "The nickname must be an alphanumeric string and the identity \
+                     'Name <email address>'",
+                ),
+            ));
+        }
+        self.people
+            .insert(String::from(nickname), String::from(identity));
+        Ok(())
+    }
+
+    pub fn delete_person(&mut self, nickname: &str) -> Result<(), UserDataError> {
+        match self.people.remove(nickname) {
+            Some(_) => Ok(()),
+            None => Err(UserDataError::new_with_message(
+                UserDataErrorCode::InputError,
+                format!("Person {} is not known", nickname),
+            )),
+        }
+    }
+
+    // Identity of a nickname, other values are kept as is
+    pub fn identity<'a>(&'a self, value: &'a str) -> &'a str {
+        self.people.get(value).map_or(value, String::as_str)
+    }
+
     pub fn smtp_encryption(&self) -> Option<&str> {
-        self.smtp_encryption.as_ref().map(String::as_str)
+        self.smtp_encryption.as_deref()
     }
 
     pub fn set_smtp_encryption(&mut self, smtp_encryption: Option<&str>) {
@@ -102,7 +262,7 @@ impl UserConfig {
     }
 
     pub fn cv_skel(&self) -> Option<&str> {
-        self.cv_skel.as_ref().map(String::as_str)
+        self.cv_skel.as_deref()
     }
 
     pub fn set_cv_skel(&mut self, cv_skel: Option<&str>) {
@@ -114,7 +274,6 @@ impl RootFile {
     pub fn new() -> Self {
         Self {
             version: String::from(PROJECT_VERSION),
-            user_config: UserConfig::new(),
             repos: Vec::new(),
             lists: HashMap::new(),
         }
@@ -138,6 +297,12 @@ impl RootFile {
         &mut self, name: &str, path: &str,
     ) -> Result<&RepoMetadata, UserDataError> {
         let Some(path) = find_repo_root(path) else {
+            if is_sha256_repo(path) {
+                return Err(UserDataError::new_with_message(
+                    UserDataErrorCode::NotAGitRepo,
+                    String::from("The repos using SHA-256 object ids are not supported"),
+                ));
+            }
             return Err(UserDataError::new(UserDataErrorCode::NotAGitRepo));
         };
         if self.repo_exists(name, path.to_string_lossy().to_string().as_str()) {
@@ -161,27 +326,31 @@ impl RootFile {
         self.repos.as_slice()
     }
 
-    pub fn config(&self) -> &UserConfig {
-        &self.user_config
-    }
-
-    pub fn config_mut(&mut self) -> &mut UserConfig {
-        &mut self.user_config
+    pub fn set_repo_git_storage(
+        &mut self, name: &str, git_storage: bool,
+    ) -> Result<(), UserDataError> {
+        let Some(repo) = self.repos.iter_mut().find(|r| r.name() == name) else {
+            return Err(UserDataError::new(UserDataErrorCode::RepoDoesNotExist));
+        };
+        repo.set_git_storage(git_storage);
+        Ok(())
     }
 
-    pub fn add_mailing_list(&mut self, name: &str, email: &str) -> Result<(), UserDataError> {
+    pub fn add_mailing_list(
+        &mut self, name: &str, email: &str, thread_revisions: bool, base_info: bool,
+    ) -> Result<(), UserDataError> {
         if self.lists.contains_key(name) {
             return Err(UserDataError::new(UserDataErrorCode::ListAlreadyExists));
         }
-        if let Some(list) = MailingList::new(name, email) {
+        if let Some(list) = MailingList::new(name, email, thread_revisions, base_info) {
             self.lists.insert(String::from(name), list);
             Ok(())
         } else {
             Err(UserDataError::new_with_message(
                 UserDataErrorCode::InputError,
-                format!(
+                String::from(
                     "The list cannot be created, the address format must \
-                     be valid and the name must be an alphanumeric string"
+                     be valid and the name must be an alphanumeric string",
                 ),
             ))
         }
@@ -201,6 +370,31 @@ impl RootFile {
         self.lists.get(name)
     }
 
+    pub fn find_mailing_list_by_email(&self, email: &str) -> Option<&MailingList> {
+        self.lists.values().find(|l| l.email() == email)
+    }
+
+    pub fn version(&self) -> &str {
+        self.version.as_str()
+    }
+}
+
+impl ConfigFile {
+    pub fn new() -> Self {
+        Self {
+            version: String::from(PROJECT_VERSION),
+            user_config: UserConfig::new(),
+        }
+    }
+
+    pub fn config(&self) -> &UserConfig {
+        &self.user_config
+    }
+
+    pub fn config_mut(&mut self) -> &mut UserConfig {
+        &mut self.user_config
+    }
+
     pub fn version(&self) -> &str {
         self.version.as_str()
     }
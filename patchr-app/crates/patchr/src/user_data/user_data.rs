@@ -1,28 +1,56 @@
 use std::{
     env,
+    ffi::OsString,
     fmt::{Debug, Display},
-    fs::{self, File},
-    io::{BufReader, BufWriter},
-    path::PathBuf,
+    fs::{self, File, OpenOptions},
+    io::{self, BufReader, BufWriter, IsTerminal, Write},
+    path::{Path, PathBuf},
 };
 
 use common::constants::PROJECT_VERSION;
-use git::repo::{Repo, RepoData, RepoMetadata};
+use git::repo::{MergeConflict, Repo, RepoData, RepoMetadata};
 use homedir::get_my_home;
 use log::{debug, trace};
 
+use serde::{de::DeserializeOwned, Serialize};
 use uuid::Uuid;
 
+use crate::{
+    cli_print, cli_print_error,
+    environment::{PATCHR_HOME, XDG_CONFIG_HOME, XDG_DATA_HOME, XDG_RUNTIME_DIR},
+};
+
 use super::{
+    git_storage::{
+        adopt_git_data, commit_git_data, fetch_git_data, push_git_data, read_git_data,
+        pending_sends, restore_pending_sends, FetchResult, GitData, PendingSends,
+    },
+    lock::{lock_data_dir, DataLock, LockMode, LOCK_FILE_NAME},
     mailing_list::MailingList,
-    root_file::{RootFile, UserConfig, ROOT_FILE_NAME},
+    root_file::{ConfigFile, RootFile, UserConfig, CONFIG_FILE_NAME, ROOT_FILE_NAME},
 };
 
-const USER_DATA_DIR: &str = ".patchr";
+// Directory of the versions that kept everything in the home directory
+const LEGACY_DATA_DIR: &str = ".patchr";
+// Generated patches of these versions, nothing in it needs to be kept
+const LEGACY_TMP_DIR: &str = "tmp";
+// Name of the directories of patchr in the XDG base directories
+const APP_DIR: &str = "patchr";
+// Mails of the sends that can be resumed, in the data directory
+const PENDING_SENDS_DIR: &str = "pending";
+// Previous version of a data file, and the next one while it is written
+const BACKUP_EXTENSION: &str = "bak";
+const TMP_EXTENSION: &str = "tmp";
+const YES_KEY: &str = "y";
 
 pub struct UserData {
     root_file: RootFile,
+    config_file: ConfigFile,
     repo: Option<RepoData>,
+    // set when the data of the repo is stored in git
+    git_data: Option<GitData>,
+    // held until the data is saved
+    _lock: DataLock,
 }
 
 #[derive(Debug, Clone, Copy)]
@@ -37,6 +65,9 @@ pub enum UserDataErrorCode {
     FailedToReadData,
     FsError,
     InputError,
+    DataBusy,
+    // A backup must be restored, which needs an exclusive lock
+    RestoreNeeded,
 }
 
 #[derive(Clone, Debug)]
@@ -51,102 +82,401 @@ pub fn root_file_path() -> Result<PathBuf, UserDataError> {
     Ok(r)
 }
 
-pub fn root_file_dir_path() -> Result<PathBuf, UserDataError> {
+pub fn config_file_path() -> Result<PathBuf, UserDataError> {
+    let mut r = config_file_dir_path()?;
+    r.push(CONFIG_FILE_NAME);
+    Ok(r)
+}
+
+fn home_dir_path() -> Result<PathBuf, UserDataError> {
     match get_my_home() {
-        Ok(opt_home) => {
-            if let Some(mut p) = opt_home {
-                p.push(USER_DATA_DIR);
-                Ok(p)
-            } else {
-                Err(UserDataError::new_with_message(
-                    UserDataErrorCode::FsError,
-                    String::from("Failed to get home dir"),
-                ))
-            }
-        }
+        Ok(Some(p)) => Ok(p),
+        Ok(None) => Err(UserDataError::new_with_message(
+            UserDataErrorCode::FsError,
+            String::from("Failed to get home dir"),
+        )),
         Err(e) => Err(UserDataError::new_with_message(UserDataErrorCode::FsError, e.to_string())),
     }
 }
 
+// Directory given by the environment, relative paths are ignored like
+// the XDG specification requires
+fn env_dir_path(name: &str) -> Option<PathBuf> {
+    env::var_os(name)
+        .map(PathBuf::from)
+        .filter(|p| p.is_absolute())
+}
+
+// PATCHR_HOME holds all the files, otherwise the XDG directories are used
+fn xdg_dir_path(xdg_env: &str, default: &str) -> Result<PathBuf, UserDataError> {
+    if let Some(p) = env_dir_path(PATCHR_HOME) {
+        return Ok(p);
+    }
+    let base = match env_dir_path(xdg_env) {
+        Some(p) => p,
+        None => home_dir_path()?.join(default),
+    };
+    Ok(base.join(APP_DIR))
+}
+
+// Root file, repo files and backups
+pub fn root_file_dir_path() -> Result<PathBuf, UserDataError> {
+    xdg_dir_path(XDG_DATA_HOME, ".local/share")
+}
+
+pub fn config_file_dir_path() -> Result<PathBuf, UserDataError> {
+    xdg_dir_path(XDG_CONFIG_HOME, ".config")
+}
+
+// Scratch files, e.g. the generated mails
 pub fn root_tmp_dir_path() -> Result<PathBuf, UserDataError> {
-    let mut r = root_file_dir_path()?;
-    r.push("tmp");
+    let to_err = |e: io::Error| {
+        UserDataError::new_with_message(
+            UserDataErrorCode::FsError,
+            format!("Failed to create a temporary directory : {}", e),
+        )
+    };
+    let r = if let Some(p) = env_dir_path(PATCHR_HOME) {
+        p.join("tmp")
+    } else if let Some(p) = env_dir_path(XDG_RUNTIME_DIR) {
+        p.join(APP_DIR)
+    } else {
+        // env::temp_dir follows TMPDIR, the directory may be shared
+        let r = match env::var("USER") {
+            Ok(user) => env::temp_dir().join(format!("{}-{}", APP_DIR, user)),
+            Err(_) => env::temp_dir().join(APP_DIR),
+        };
+        create_private_dir(&r).map_err(to_err)?;
+        return Ok(r);
+    };
     if !fs::metadata(&r).is_ok_and(|m| m.is_dir()) {
-        if let Err(e) = fs::create_dir_all(&r) {
-            return Err(UserDataError::new_with_message(
-                UserDataErrorCode::FsError,
-                format!("Failed to create a temporary directory : {}", e),
-            ));
-        }
+        fs::create_dir_all(&r).map_err(to_err)?;
     }
     Ok(r)
 }
 
+// Another user could create the directory first in a shared parent, to read
+// or replace the mails. It must be a directory of the user that only they
+// can access, not a symlink
+#[cfg(unix)]
+fn create_private_dir(path: &Path) -> io::Result<()> {
+    use std::os::unix::fs::{DirBuilderExt, MetadataExt, PermissionsExt};
+
+    match fs::DirBuilder::new().mode(0o700).create(path) {
+        Err(e) if e.kind() != io::ErrorKind::AlreadyExists => return Err(e),
+        _ => (),
+    }
+    let meta = fs::symlink_metadata(path)?;
+    // SAFETY: getuid cannot fail
+    let uid = unsafe { libc::getuid() };
+    if !meta.is_dir() || meta.uid() != uid {
+        return Err(io::Error::new(
+            io::ErrorKind::PermissionDenied,
+            format!("{} is not a directory of the user", path.to_string_lossy()),
+        ));
+    }
+    // e.g. created by a version that did not restrict it
+    if meta.mode() & 0o077 != 0 {
+        fs::set_permissions(path, fs::Permissions::from_mode(0o700))?;
+    }
+    Ok(())
+}
+
+#[cfg(not(unix))]
+fn create_private_dir(path: &Path) -> io::Result<()> {
+    fs::create_dir_all(path)
+}
+
+// Unlike the tmp directory, it is kept by cleantmp and across sessions
+pub fn pending_sends_dir_path() -> Result<PathBuf, UserDataError> {
+    let r = root_file_dir_path()?.join(PENDING_SENDS_DIR);
+    fs::create_dir_all(&r).map_err(|e| {
+        UserDataError::new_with_message(
+            UserDataErrorCode::FsError,
+            format!("Failed to create {} : {}", r.to_string_lossy(), e),
+        )
+    })?;
+    Ok(r)
+}
+
+// The data of the versions using ~/.patchr is moved to the data directory,
+// unless PATCHR_HOME is set. Its tmp directory is deleted, these versions
+// did not keep the mails of a failed send
+pub fn move_legacy_data_dir() -> Result<(), UserDataError> {
+    if env_dir_path(PATCHR_HOME).is_some() {
+        return Ok(());
+    }
+    let legacy = home_dir_path()?.join(LEGACY_DATA_DIR);
+    let dir = root_file_dir_path()?;
+    let moved = || !legacy.is_dir() || dir.join(ROOT_FILE_NAME).exists();
+    if moved() {
+        return Ok(());
+    }
+    // Another process may be moving it, the lock file is created in dir
+    let _lock = lock_data_dir(LockMode::Exclusive)?;
+    if moved() {
+        return Ok(());
+    }
+
+    let to_err = |e: io::Error| {
+        UserDataError::new_with_message(
+            UserDataErrorCode::FsError,
+            format!(
+                "Failed to move {} to {}, {}",
+                legacy.to_string_lossy(),
+                dir.to_string_lossy(),
+                e
+            ),
+        )
+    };
+    // The root file is moved last, the move is resumed if interrupted
+    let mut entries: Vec<OsString> = fs::read_dir(&legacy)
+        .and_then(|entries| entries.map(|e| e.map(|e| e.file_name())).collect())
+        .map_err(to_err)?;
+    entries.sort_by_key(|name| name == ROOT_FILE_NAME);
+    for name in entries {
+        // dir has its own, which is held
+        if name == LOCK_FILE_NAME {
+            fs::remove_file(legacy.join(&name)).map_err(to_err)?;
+            continue;
+        }
+        if name == LEGACY_TMP_DIR {
+            continue;
+        }
+        move_path(&legacy.join(&name), &dir.join(&name)).map_err(to_err)?;
+    }
+    cli_print!("Data moved from {} to {}", legacy.to_string_lossy(), dir.to_string_lossy());
+    fs::remove_dir_all(&legacy).map_err(to_err)
+}
+
+// Renames the file or directory, the data directory may be on another file
+// system than the home directory
+fn move_path(from: &Path, to: &Path) -> io::Result<()> {
+    match fs::rename(from, to) {
+        Err(e) if e.raw_os_error() == Some(libc::EXDEV) => {
+            copy_path(from, to)?;
+            if fs::symlink_metadata(from)?.is_dir() {
+                fs::remove_dir_all(from)
+            } else {
+                fs::remove_file(from)
+            }
+        }
+        res => res,
+    }
+}
+
+fn copy_path(from: &Path, to: &Path) -> io::Result<()> {
+    let meta = fs::symlink_metadata(from)?;
+    if meta.is_dir() {
+        fs::create_dir_all(to)?;
+        for entry in fs::read_dir(from)? {
+            let name = entry?.file_name();
+            copy_path(&from.join(&name), &to.join(&name))?;
+        }
+        fs::set_permissions(to, meta.permissions())
+    } else if meta.is_symlink() {
+        let target = fs::read_link(from)?;
+        let _ = fs::remove_file(to);
+        #[cfg(unix)]
+        return std::os::unix::fs::symlink(target, to);
+        #[cfg(not(unix))]
+        return fs::copy(from, to).map(|_| ());
+    } else {
+        fs::copy(from, to).map(|_| ())
+    }
+}
+
 pub fn new_root_tmp_child_path() -> Result<PathBuf, UserDataError> {
     let mut r = root_tmp_dir_path()?;
     r.push(Uuid::new_v4().to_string());
     Ok(r)
 }
 
+fn with_extension(path: &Path, extension: &str) -> PathBuf {
+    let mut path = OsString::from(path);
+    path.push(".");
+    path.push(extension);
+    PathBuf::from(path)
+}
+
+fn backup_file_path(path: &Path) -> PathBuf {
+    with_extension(path, BACKUP_EXTENSION)
+}
+
+fn read_json_file<T: DeserializeOwned>(path: &Path) -> Result<T, String> {
+    let file = File::open(path).map_err(|e| e.to_string())?;
+    serde_json::from_reader(BufReader::new(file)).map_err(|e| e.to_string())
+}
+
+// Content of a data file. When it cannot be parsed, e.g. after a crash, the
+// user is offered to recover the previous version from its backup, only from
+// a terminal and with an exclusive lock
+fn read_data_file<T: DeserializeOwned>(
+    path: &Path, lock_mode: LockMode,
+) -> Result<T, UserDataError> {
+    let err = match read_json_file(path) {
+        Ok(data) => return Ok(data),
+        Err(e) => e,
+    };
+    let to_err = |e: String| {
+        UserDataError::new_with_message(
+            UserDataErrorCode::FailedToReadData,
+            format!("{}: {}", path.to_string_lossy(), e),
+        )
+    };
+    let backup = backup_file_path(path);
+    let Ok(content) = read_json_file::<serde_json::Value>(&backup) else {
+        return Err(to_err(err));
+    };
+    let Ok(data) = serde_json::from_value(content.clone()) else {
+        return Err(to_err(err));
+    };
+    if lock_mode == LockMode::Shared {
+        return Err(UserDataError::new(UserDataErrorCode::RestoreNeeded));
+    }
+    if !io::stdin().is_terminal() {
+        return Err(to_err(format!(
+            "{}, the previous version is kept in {}",
+            err,
+            backup.to_string_lossy()
+        )));
+    }
+
+    cli_print_error!("{} cannot be read: {}", path.to_string_lossy(), err);
+    cli_print!(
+        "Press y to restore the previous version from {}, any other key to abort",
+        backup.to_string_lossy()
+    );
+    let mut k = String::new();
+    if io::stdin().read_line(&mut k).is_err() || !k.trim().eq_ignore_ascii_case(YES_KEY) {
+        return Err(to_err(err));
+    }
+    // The corrupted version must not replace the backup
+    replace_data_file(path, &content).map_err(|e| to_err(e.to_string()))?;
+    cli_print!("{} restored", path.to_string_lossy());
+    Ok(data)
+}
+
+// The content is written to a temporary file which replaces the data file
+// once synced, so that a crash leaves either version. The replaced version
+// is kept as a backup
+pub fn write_data_file<T: Serialize>(path: &Path, data: &T) -> io::Result<()> {
+    if path.is_file() {
+        let backup = backup_file_path(path);
+        fs::copy(path, &backup)?;
+        OpenOptions::new().write(true).open(&backup)?.sync_all()?;
+    }
+    replace_data_file(path, data)
+}
+
+// See write_data_file, without backup
+fn replace_data_file<T: Serialize>(path: &Path, data: &T) -> io::Result<()> {
+    let tmp = with_extension(path, TMP_EXTENSION);
+    let write_tmp = || {
+        let mut writer = BufWriter::new(File::create(&tmp)?);
+        serde_json::to_writer_pretty(&mut writer, data)?;
+        writer.flush()?;
+        writer.get_ref().sync_all()
+    };
+    if let Err(e) = write_tmp() {
+        let _ = fs::remove_file(&tmp);
+        return Err(e);
+    }
+    fs::rename(&tmp, path)?;
+    // the rename is durable once the directory is synced, directories
+    // cannot be opened as files on Windows
+    #[cfg(unix)]
+    if let Some(dir) = path.parent() {
+        File::open(dir)?.sync_all()?;
+    }
+    Ok(())
+}
+
 impl UserData {
-    pub fn load() -> Result<Self, UserDataError> {
+    // The data is locked in the given mode until the UserData is dropped,
+    // only an exclusive lock allows to save it
+    pub fn load(lock_mode: LockMode) -> Result<Self, UserDataError> {
+        match Self::load_locked(lock_mode) {
+            // The shared lock is released before taking the exclusive one
+            Err(UserDataError {
+                code: UserDataErrorCode::RestoreNeeded,
+                ..
+            }) => Self::load_locked(LockMode::Exclusive),
+            res => res,
+        }
+    }
+
+    fn load_locked(lock_mode: LockMode) -> Result<Self, UserDataError> {
+        let lock = lock_data_dir(lock_mode)?;
         let root_file_path = root_file_path()?;
         debug!("load user data from {:?}", root_file_path);
         if !root_file_path.exists() {
             // make sure the directory exists, the file will be created when saving the returned UserData struct
-            fs::create_dir_all(root_file_path.parent().unwrap()).or_else(|e| {
-                Err(UserDataError::new_with_message(UserDataErrorCode::FsError, e.to_string()))
+            fs::create_dir_all(root_file_path.parent().unwrap()).map_err(|e| {
+                UserDataError::new_with_message(UserDataErrorCode::FsError, e.to_string())
             })?;
         }
-        let root_file = Self::read_or_create_root_file()?;
-        let repo = UserData::find_current_repo(&root_file)?;
+        let root_file = Self::read_or_create_root_file(lock_mode)?;
+        let config_file = Self::read_or_create_config_file(lock_mode)?;
+        let (repo, git_data) = match UserData::find_current_repo(&root_file, lock_mode)? {
+            Some((repo, git_data)) => (Some(repo), git_data),
+            None => (None, None),
+        };
 
         if root_file.version() != PROJECT_VERSION
+            || config_file.version() != PROJECT_VERSION
             || (repo.is_some() && repo.as_ref().unwrap().repo().version() != PROJECT_VERSION)
         {
             return Err(UserDataError::new_with_message(
                 UserDataErrorCode::FailedToReadData,
-                String::from("Version mismatch, consider using the migration script"),
+                String::from("Version mismatch, run patchr migrate to update the data"),
             ));
         }
 
-        Ok(Self { root_file, repo })
+        Ok(Self {
+            root_file,
+            config_file,
+            repo,
+            git_data,
+            _lock: lock,
+        })
     }
 
-    fn read_or_create_root_file() -> Result<RootFile, UserDataError> {
-        let Ok(file) = File::open(root_file_path()?) else {
+    fn read_or_create_root_file(lock_mode: LockMode) -> Result<RootFile, UserDataError> {
+        let path = root_file_path()?;
+        if !path.exists() && !backup_file_path(&path).exists() {
             return Ok(RootFile::new());
-        };
-        let reader = BufReader::new(file);
-        serde_json::from_reader(reader).map_err(|e| {
-            UserDataError::new_with_message(UserDataErrorCode::FailedToReadData, e.to_string())
+        }
+        read_data_file(&path, lock_mode)
+    }
+
+    fn read_or_create_config_file(lock_mode: LockMode) -> Result<ConfigFile, UserDataError> {
+        let path = config_file_path()?;
+        if !path.exists() && !backup_file_path(&path).exists() {
+            // the config moves out of the root file in the migration
+            return Ok(ConfigFile::new());
+        }
+        read_data_file(&path, lock_mode)
+    }
+
+    pub fn save_config_file(&self) -> Result<(), UserDataError> {
+        trace!("Save config file");
+        let path = config_file_path()?;
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir).map_err(|e| {
+                UserDataError::new_with_message(UserDataErrorCode::FsError, e.to_string())
+            })?;
+        }
+        write_data_file(&path, &self.config_file).map_err(|e| {
+            UserDataError::new_with_message(UserDataErrorCode::FailedToSaveRootFile, e.to_string())
         })
     }
 
     pub fn save_root_file(&self) -> Result<(), UserDataError> {
         trace!("Save root file");
-        match File::options()
-            .create(true)
-            .write(true)
-            .truncate(true)
-            .open(root_file_path()?)
-        {
-            Ok(file) => {
-                let mut writer = BufWriter::new(file);
-                if let Err(e) = serde_json::to_writer_pretty(&mut writer, &self.root_file) {
-                    Err(UserDataError::new_with_message(
-                        UserDataErrorCode::FailedToSaveRootFile,
-                        e.to_string(),
-                    ))
-                } else {
-                    Ok(())
-                }
-            }
-            Err(e) => Err(UserDataError::new_with_message(
-                UserDataErrorCode::FailedToSaveRootFile,
-                e.to_string(),
-            )),
-        }
+        write_data_file(&root_file_path()?, &self.root_file).map_err(|e| {
+            UserDataError::new_with_message(UserDataErrorCode::FailedToSaveRootFile, e.to_string())
+        })
     }
 
     pub fn save_repo(&self) -> Result<(), UserDataError> {
@@ -156,33 +486,33 @@ impl UserData {
         };
 
         let path = UserData::get_repo_data_file_path(repo.meta())?;
-        match File::options()
-            .create(true)
-            .write(true)
-            .truncate(true)
-            .open(path)
-        {
-            Ok(file) => {
-                let mut writer = BufWriter::new(file);
-                if let Err(_e) = serde_json::to_writer_pretty(&mut writer, repo.repo()) {
-                    Err(UserDataError::new_with_message(
-                        UserDataErrorCode::FailedToSaveData,
-                        String::from("Failed to save the repo"),
-                    ))
-                } else {
-                    Ok(())
-                }
-            }
-            Err(e) => {
-                return Err(UserDataError::new_with_message(
-                    UserDataErrorCode::FailedToSaveData,
-                    e.to_string(),
-                ));
+        let pending_path = Self::get_pending_sends_file_path(repo.meta())?;
+        if let Some(git_data) = self.git_data.as_ref() {
+            commit_git_data(repo.meta(), repo.repo(), git_data, "patchr: update the series")?;
+            Self::save_pending_sends(&pending_path, repo.repo())?;
+            // the data file is left once the data is in git
+            if path.exists() {
+                let _ = fs::remove_file(&path);
+                let _ = fs::remove_file(backup_file_path(&path));
             }
+            return Ok(());
+        }
+        // the data file has the pending sends
+        if pending_path.exists() {
+            let _ = fs::remove_file(&pending_path);
+            let _ = fs::remove_file(backup_file_path(&pending_path));
         }
+        write_data_file(&path, repo.repo()).map_err(|e| {
+            UserDataError::new_with_message(
+                UserDataErrorCode::FailedToSaveData,
+                format!("Failed to save the repo, {}", e),
+            )
+        })
     }
 
-    fn find_current_repo(root_file: &RootFile) -> Result<Option<RepoData>, UserDataError> {
+    fn find_current_repo(
+        root_file: &RootFile, lock_mode: LockMode,
+    ) -> Result<Option<(RepoData, Option<GitData>)>, UserDataError> {
         let current_dir = env::current_dir()
             .ok()
             .ok_or(UserDataError::new_with_message(
@@ -192,29 +522,23 @@ impl UserData {
         if let Some(meta) =
             root_file.find_repo_by_path(current_dir.to_string_lossy().to_string().as_str())
         {
+            if meta.git_storage() {
+                let (mut data, git_data) = read_git_data(&meta)?;
+                let pending_path = Self::get_pending_sends_file_path(&meta)?;
+                if pending_path.exists() || backup_file_path(&pending_path).exists() {
+                    let pending: PendingSends = read_data_file(&pending_path, lock_mode)?;
+                    restore_pending_sends(&mut data, pending);
+                }
+                trace!("Repo found: {}, data in git", meta.path());
+                return Ok(Some((RepoData::new(meta, data), Some(git_data))));
+            }
             let repo_data_file_path = Self::get_repo_data_file_path(&meta)?;
             if !fs::metadata(&repo_data_file_path).is_ok_and(|m| m.is_file()) {
                 return Ok(None); // not in a repo
             }
-            match File::open(&repo_data_file_path) {
-                Ok(file) => {
-                    let reader = BufReader::new(file);
-                    match serde_json::from_reader(reader) {
-                        Ok(data) => {
-                            trace!("Repo found: {}", meta.path());
-                            Ok(Some(RepoData::new(meta, data)))
-                        }
-                        Err(e) => Err(UserDataError::new_with_message(
-                            UserDataErrorCode::FailedToReadData,
-                            e.to_string(),
-                        )),
-                    }
-                }
-                Err(e) => Err(UserDataError::new_with_message(
-                    UserDataErrorCode::FailedToReadData,
-                    e.to_string(),
-                )),
-            }
+            let data = read_data_file(&repo_data_file_path, lock_mode)?;
+            trace!("Repo found: {}", meta.path());
+            Ok(Some((RepoData::new(meta, data), None)))
         } else {
             trace!("Not in a repo");
             Ok(None)
@@ -239,7 +563,7 @@ impl UserData {
         let Some(r) = self.repo.as_ref() else {
             return Err(UserDataError::new_with_message(
                 UserDataErrorCode::RepoDoesNotExist,
-                format!("Not in a repo"),
+                String::from("Not in a repo"),
             ));
         };
         // The repo exists, deletion should not fail
@@ -249,14 +573,88 @@ impl UserData {
         let data_path = Self::get_repo_data_file_path(r.meta())?;
         debug!("Delete repo data : {}", data_path.to_string_lossy());
         let _ = fs::remove_file(&data_path);
+        let _ = fs::remove_file(backup_file_path(&data_path));
         self.repo = None;
+        self.git_data = None;
         Ok(())
     }
 
+    // Stores the data of the current repo in its data ref, merged with the
+    // data already there, or back in its data file. Returns the series
+    // differing in both versions, the local version is kept
+    pub fn set_git_storage(
+        &mut self, git_storage: bool,
+    ) -> Result<Vec<MergeConflict>, UserDataError> {
+        let Some(repo) = self.repo.as_mut() else {
+            return Err(UserDataError::new_with_message(
+                UserDataErrorCode::RepoDoesNotExist,
+                String::from("Not in a repo"),
+            ));
+        };
+        let mut conflicts = Vec::new();
+        if git_storage && self.git_data.is_none() {
+            let meta = repo.meta().clone();
+            let (git_data, c) = adopt_git_data(&meta, repo.repo_mut())?;
+            self.git_data = Some(git_data);
+            conflicts = c;
+        } else if !git_storage {
+            self.git_data = None;
+        }
+        repo.meta_mut().set_git_storage(git_storage);
+        self.root_file
+            .set_repo_git_storage(repo.meta().name(), git_storage)?;
+        Ok(conflicts)
+    }
+
+    fn git_data_or_fail(&mut self) -> Result<(&mut RepoData, &mut GitData), UserDataError> {
+        match (self.repo.as_mut(), self.git_data.as_mut()) {
+            (Some(repo), Some(git_data)) => Ok((repo, git_data)),
+            (None, _) => Err(UserDataError::new_with_message(
+                UserDataErrorCode::RepoDoesNotExist,
+                String::from("Not in a repo"),
+            )),
+            (Some(_), None) => Err(UserDataError::new_with_message(
+                UserDataErrorCode::InputError,
+                String::from("The data of this repo is not stored in git"),
+            )),
+        }
+    }
+
+    pub fn fetch_git_data(&mut self, remote: &str) -> Result<FetchResult, UserDataError> {
+        let (repo, git_data) = self.git_data_or_fail()?;
+        let meta = repo.meta().clone();
+        fetch_git_data(&meta, repo.repo_mut(), git_data, remote)
+    }
+
+    pub fn push_git_data(&mut self, remote: &str) -> Result<(), UserDataError> {
+        let (repo, git_data) = self.git_data_or_fail()?;
+        push_git_data(repo.meta(), git_data, remote)
+    }
+
     pub fn repos(&self) -> &[RepoMetadata] {
         self.root_file.repos()
     }
 
+    // The pending sends of a repo whose data is stored in git, see PendingSends
+    fn get_pending_sends_file_path(repo: &RepoMetadata) -> Result<PathBuf, UserDataError> {
+        Ok(pending_sends_dir_path()?.join(format!("{}.json", repo.name())))
+    }
+
+    fn save_pending_sends(path: &Path, repo: &Repo) -> Result<(), UserDataError> {
+        let pending = pending_sends(repo);
+        if pending.is_empty() {
+            let _ = fs::remove_file(path);
+            let _ = fs::remove_file(backup_file_path(path));
+            return Ok(());
+        }
+        write_data_file(path, &pending).map_err(|e| {
+            UserDataError::new_with_message(
+                UserDataErrorCode::FailedToSaveData,
+                format!("Failed to save the pending sends, {}", e),
+            )
+        })
+    }
+
     fn get_repo_data_file_path(repo: &RepoMetadata) -> Result<PathBuf, UserDataError> {
         let mut r = root_file_dir_path()?;
         r.push(repo.name());
@@ -272,15 +670,20 @@ impl UserData {
     }
 
     pub fn config(&self) -> &UserConfig {
-        self.root_file.config()
+        self.config_file.config()
     }
 
     pub fn config_mut(&mut self) -> &mut UserConfig {
-        self.root_file.config_mut()
+        self.config_file.config_mut()
     }
 
-    pub fn add_mailing_list(&mut self, name: &str, email: &str) -> Result<(), UserDataError> {
-        match self.root_file.add_mailing_list(name, email) {
+    pub fn add_mailing_list(
+        &mut self, name: &str, email: &str, thread_revisions: bool, base_info: bool,
+    ) -> Result<(), UserDataError> {
+        match self
+            .root_file
+            .add_mailing_list(name, email, thread_revisions, base_info)
+        {
             Ok(()) => Ok(()),
             Err(e) => {
                 debug!("{}", e);
@@ -296,6 +699,10 @@ impl UserData {
     pub fn find_mailing_list(&self, name: &str) -> Option<&MailingList> {
         self.root_file.find_mailing_list(name)
     }
+
+    pub fn find_mailing_list_by_email(&self, email: &str) -> Option<&MailingList> {
+        self.root_file.find_mailing_list_by_email(email)
+    }
 }
 
 impl UserDataError {
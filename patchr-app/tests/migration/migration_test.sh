@@ -0,0 +1,114 @@
+#! /usr/bin/env bash
+
+source "$(dirname $0)/../prolog.sh"
+
+# Write the files back as they were in an older version, 0.15 by default
+downgrade() {
+    local file="$1"
+    local filter="$2"
+    local version="${3:-0.15}"
+    jq "$filter | .version = \"$version\"" < "$file" > "$file.tmp"
+    mv "$file.tmp" "$file"
+}
+
+# The config was kept in the root file before 0.16
+downgrade_config() {
+    local version="$1"
+    jq --slurpfile c "$CONFIG_FILE" ".user_config = \$c[0].user_config | .version = \"$version\"" \
+        < "$ROOT_FILE" > "$ROOT_FILE.tmp"
+    mv "$ROOT_FILE.tmp" "$ROOT_FILE"
+    rm "$CONFIG_FILE"
+}
+
+test_migrate() {
+    r=$(create_test_repo)
+    cd "$r"
+    echo first > first && git add first && git commit -q -m 'First patch'
+
+    run register r
+    run config from test@patchr.com
+    run addlist ml list@patchr.com
+    run create s 'Test series'
+    md="$(mktemp -d -p "$TMP_DIR")"
+    run config transport maildir
+    run config maildir "$md"
+    run send s HEAD~1 HEAD ml
+
+    downgrade_config 0.15
+    downgrade "$ROOT_FILE" 'del(.lists[].base_info, .repos[].git_storage)'
+    downgrade "$(repo_file r)" \
+        'del(.series[].branch, .series[].base, .series[].base_info, .series[].sends)'
+    if run list; then
+        exit 1
+    fi
+
+    out=$(run migrate --dry-run)
+    echo "$out" | grep -q "root.json: 0.15 to"
+    echo "$out" | grep -q "/r: 0.15 to"
+    check_json_root_file '.version' '"0.15"'
+    [ ! -d "$DATA_DIR/backups" ]
+
+    run migrate
+    [ "$(ls "$DATA_DIR/backups" | wc -l)" -eq 1 ]
+    tar -tf "$DATA_DIR"/backups/*.tar | grep -q '^root.json$'
+    check_json_root_file '.lists.ml.base_info' 'false'
+    check_json_root_file '.repos[0].git_storage' 'false'
+    check_json "$(repo_file r)" '.series[0] | has("base_info")' 'true'
+    check_json "$(repo_file r)" '.series[0].sends | length' '0'
+    check_json_root_file 'has("user_config")' 'false'
+    check_json "$CONFIG_FILE" '.user_config.from_email' '"test@patchr.com"'
+    run list | grep -q '^- s'
+    run migrate | grep -q 'Already up to date'
+}
+
+test_legacy_data_dir() {
+    r=$(create_test_repo)
+    cd "$r"
+    run register r
+    run config from test@patchr.com
+    run create s 'Test series'
+
+    # ~/.patchr of a version before 0.16, 0.15 only changed the version
+    downgrade_config 0.14
+    downgrade "$ROOT_FILE" 'del(.repos[].git_storage)' 0.14
+    downgrade "$(repo_file r)" 'del(.series[].base_info)' 0.14
+    home="$(mktemp -d -p "$TMP_DIR")"
+    cp -r "$DATA_DIR" "$home/.patchr"
+    mkdir -p "$home/.patchr/tmp/s"
+    echo patch > "$home/.patchr/tmp/s/0001-first.patch"
+    legacy() {
+        env -u PATCHR_HOME -u XDG_DATA_HOME -u XDG_CONFIG_HOME HOME="$home" "$BIN" "$@"
+    }
+
+    out=$(legacy migrate)
+    echo "$out" | grep -q "Data moved from $home/.patchr to $home/.local/share/patchr"
+    [ ! -e "$home/.patchr" ]
+    [ ! -e "$home/.local/share/patchr/tmp" ]
+    check_json "$home/.local/share/patchr/root.json" 'has("user_config")' 'false'
+    check_json "$home/.config/patchr/config.json" '.user_config.from_email' '"test@patchr.com"'
+    legacy list | grep -q '^- s'
+}
+
+# The data stored in git is migrated in a new commit of its ref
+test_migrate_git_data() {
+    r=$(create_test_repo)
+    cd "$r"
+    run register r
+    run create s 'Test series'
+    run storage git
+
+    git show refs/patchr/data:repo.json | jq '.version = "0.15"' > "$TMP_DIR/repo.json"
+    blob=$(git hash-object -w "$TMP_DIR/repo.json")
+    tree=$(printf '100644 blob %s\trepo.json\n' "$blob" | git mktree)
+    git update-ref refs/patchr/data "$(git commit-tree -p refs/patchr/data -m 'Old data' "$tree")"
+    if run list; then
+        exit 1
+    fi
+
+    out=$(run migrate)
+    echo "$out" | grep -q "$r/refs/patchr/data: 0.15 to"
+    [ "$(git rev-list --count refs/patchr/data)" -eq 3 ]
+    run list | grep -q '^- s'
+}
+
+run_test_funcs test_migrate test_legacy_data_dir test_migrate_git_data
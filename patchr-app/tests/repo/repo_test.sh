@@ -116,4 +116,283 @@ test_register_subdir() {
     ! known_repo r2
 }
 
-run_test_funcs test_register_delete test_list test_register_subdir
+# The repos using SHA-256 object ids are rejected with a clear error
+test_register_sha256() {
+    r="$(mktemp -d -p "$TMP_DIR")"
+    git init -q --object-format=sha256 "$r"
+    cd "$r"
+
+    if out=$(run register r 2>&1); then
+        false
+    fi
+    echo "$out" | grep -q 'SHA-256 object ids are not supported'
+    ! known_repo r
+}
+
+# Tagging a range rewrites its commits at once
+test_tag_range() {
+    r="$(create_test_repo)"
+    cd "$r"
+    run register r
+    echo first > first && git add first && git commit -q -m 'First'
+    echo second > second && git add second && git commit -q -m 'Second'
+    echo third > third && git add third && git commit -q -m 'Third'
+
+    out=$(run tag HEAD~3 HEAD rb 'Rev <rev@patchr.com>')
+    echo "$out" | grep -q "^HEAD~3 is $(git rev-parse HEAD~3)$"
+    [ "$(git log -3 --format=%B | grep -c '^Reviewed-by: Rev <rev@patchr.com>$')" -eq 3 ]
+
+    # Without a value, a range needs .. or --range
+    run config from 'Me <me@patchr.com>'
+    run tag HEAD~1..HEAD so
+    git log -1 --format=%B | grep -q '^Signed-off-by: Me <me@patchr.com>$'
+    [ "$(git log -2 --format=%B | grep -c '^Signed-off-by:')" -eq 1 ]
+
+    out=$(run tag --range $(git rev-parse HEAD~3)..$(git rev-parse HEAD~1) Acked-by 'Ack <ack@patchr.com>')
+    echo "$out" | grep -q "^New range: .*\.\.$(git rev-parse HEAD~1)$"
+    [ "$(git log -4 --format=%B | grep -c '^Acked-by: Ack <ack@patchr.com>$')" -eq 2 ]
+    git log -1 --format=%B | grep -q '^Third$'
+    [ "$(git log -3 --format=%B | grep -c '^Reviewed-by: Rev <rev@patchr.com>$')" -eq 3 ]
+}
+
+# Rewrites are refused with uncommitted changes and can be undone
+test_tag_undo() {
+    r="$(create_test_repo)"
+    cd "$r"
+    run register r
+    echo first > first && git add first && git commit -q -m 'First'
+    head=$(git rev-parse HEAD)
+
+    echo changed > first
+    if run tag $head rb 'Rev <rev@patchr.com>'; then
+        exit 1
+    fi
+    git checkout -q first
+
+    run tag $head rb 'Rev <rev@patchr.com>'
+    [ "$(git rev-parse HEAD)" != "$head" ]
+    [ "$(git for-each-ref refs/patchr/backup | wc -l)" -eq 1 ]
+    run undo
+    [ "$(git rev-parse HEAD)" = "$head" ]
+    [ -z "$(git for-each-ref refs/patchr/backup)" ]
+    if run undo; then
+        exit 1
+    fi
+}
+
+# Aliases and people expand the tags and their values
+test_tag_shortcuts() {
+    r="$(create_test_repo)"
+    cd "$r"
+    run register r
+    echo first > first && git add first && git commit -q -m 'First'
+
+    run alias ack Acked-by
+    run person alice 'Alice Example <alice@example.org>'
+    if run person bob 'not an identity'; then
+        exit 1
+    fi
+    run alias | grep -q '^- ack : Acked-by$'
+
+    run tag $(git rev-parse HEAD) ack alice
+    git log -1 --format=%B | grep -q '^Acked-by: Alice Example <alice@example.org>$'
+
+    # Signed-off-by defaults to the from address
+    if run tag $(git rev-parse HEAD) so; then
+        exit 1
+    fi
+    run config from 'Me <me@patchr.com>'
+    run tag $(git rev-parse HEAD) so
+    git log -1 --format=%B | grep -q '^Signed-off-by: Me <me@patchr.com>$'
+
+    run untag $(git rev-parse HEAD) ack alice
+    [ -z "$(git log -1 --format=%B | grep '^Acked-by')" ]
+    run alias -d ack
+    [ -z "$(run alias)" ]
+}
+
+# Fixes trailers name an ancestor with its abbreviated id and subject
+test_tag_fixes() {
+    r="$(create_test_repo)"
+    cd "$r"
+    run register r
+    echo bug > bug && git add bug && git commit -q -m 'Add a bug'
+    echo fix > bug && git add bug && git commit -q -m 'Fix the bug'
+    culprit=$(git rev-parse HEAD~1)
+
+    run tag HEAD fixes HEAD~1
+    git log -1 --format=%B | grep -q "^Fixes: ${culprit:0:12} (\"Add a bug\")$"
+
+    if run tag $(git rev-parse HEAD~1) fixes HEAD; then
+        exit 1
+    fi
+    run config abbrev 8
+    run untag $(git rev-parse HEAD) Fixes
+    run tag $(git rev-parse HEAD) fixes $culprit
+    git log -1 --format=%B | grep -q "^Fixes: ${culprit:0:8} (\"Add a bug\")$"
+}
+
+# A data file that cannot be read is restored from its previous version
+test_corrupt_data() {
+    r=$(create_test_repo)
+    cd "$r"
+    run register r
+    run create s 'Test series'
+    run create s2 'Other series'
+    [ -f "$(repo_file r).bak" ]
+    [ ! -e "$(repo_file r).tmp" ]
+
+    head -c 10 "$(repo_file r).bak" > "$(repo_file r)"
+    if run_tty n list; then
+        exit 1
+    fi
+    # Nobody to ask without a terminal
+    if out=$(echo y | run list 2>&1); then
+        exit 1
+    fi
+    echo "$out" | grep -q "the previous version is kept in $(repo_file r).bak"
+    out=$(run_tty y list)
+    echo "$out" | grep -q '^- s'
+    check_json "$(repo_file r)" '.series | length' 1
+    # The backup is still the version before the last write
+    check_json "$(repo_file r).bak" '.series | length' 1
+
+    head -c 10 "$ROOT_FILE" > "$ROOT_FILE.tmp"
+    mv "$ROOT_FILE.tmp" "$ROOT_FILE"
+    run_tty y repos
+    known_repo r
+}
+
+# Without a runtime directory, the mails are written in a directory of the
+# user in the shared temporary directory
+test_shared_tmp_dir() {
+    home="$(mktemp -d -p "$TMP_DIR")"
+    tmp="$(mktemp -d -p "$TMP_DIR")"
+    shared() {
+        env -u PATCHR_HOME -u XDG_RUNTIME_DIR HOME="$home" TMPDIR="$tmp" USER=u "$BIN" "$@"
+    }
+
+    shared cleantmp
+    [ ! -e "$tmp/patchr-u" ]
+
+    # Created by someone else
+    mkdir "$tmp/other"
+    ln -s "$tmp/other" "$tmp/patchr-u"
+    if out=$(shared cleantmp 2>&1); then
+        exit 1
+    fi
+    echo "$out" | grep -q "$tmp/patchr-u is not a directory of the user"
+    [ -d "$tmp/other" ]
+}
+
+# Commands that write the data wait for the other processes to release it
+test_data_lock() {
+    r=$(create_test_repo)
+    cd "$r"
+    run register r
+    run create s 'Test series'
+
+    flock -s "$DATA_DIR/lock" sleep 2 &
+    sleep 0.5
+    PATCHR_LOCK_TIMEOUT=0 run list
+    if PATCHR_LOCK_TIMEOUT=0 run create s2 'Other series'; then
+        exit 1
+    fi
+    PATCHR_LOCK_TIMEOUT=5 run create s2 'Other series'
+    wait
+
+    flock -x "$DATA_DIR/lock" sleep 2 &
+    sleep 0.5
+    if PATCHR_LOCK_TIMEOUT=0 run list; then
+        exit 1
+    fi
+    wait
+    out=$(run list)
+    echo "$out" | grep -q '^- s2'
+}
+
+# The series of a repo stored in git are exchanged through a remote
+test_git_storage() {
+    remote="$(mktemp -d -p "$TMP_DIR")"
+    git init -q --bare "$remote"
+    r=$(create_test_repo)
+    cd "$r"
+    git remote add origin "$remote"
+    run register r
+    run create s 'Test series'
+    run storage git
+    [ ! -f "$(repo_file r)" ]
+    git show refs/patchr/data:repo.json | jq -e '.series[0].name == "s"'
+    run pushdata
+
+    # a co-maintainer gets the series
+    r2="$(mktemp -d -p "$TMP_DIR")"
+    git clone -q "$remote" "$r2"
+    cd "$r2"
+    run register r2
+    run storage git
+    out=$(run fetchdata)
+    echo "$out" | grep -q 'Series updated from origin'
+    run list | grep -q '^- s'
+    run create s2 'Other series'
+    run pushdata
+
+    # concurrent edits are merged
+    cd "$r"
+    run create s3 'Third series'
+    if run pushdata; then
+        exit 1
+    fi
+    out=$(run fetchdata)
+    echo "$out" | grep -q 'Series merged with origin'
+    out=$(run list)
+    echo "$out" | grep -q '^- s2'
+    echo "$out" | grep -q '^- s3'
+    run pushdata
+    out=$(run fetchdata)
+    echo "$out" | grep -q 'Already up to date'
+
+    # a series changed on both sides keeps both versions
+    cd "$r2"
+    run fetchdata
+    run edit title s 'Remote title'
+    run pushdata
+    cd "$r"
+    run edit title s 'Local title'
+    out=$(run fetchdata)
+    echo "$out" | grep -q '^s changed on both sides, the local version is kept$'
+    echo "$out" | grep -q '^The version of origin is kept in s-theirs$'
+    out=$(run show -v s)
+    echo "$out" | grep -q '^Title : Local title$'
+    out=$(run show -v s-theirs)
+    echo "$out" | grep -q '^Title : Remote title$'
+
+    # the pending sends stay out of the data ref
+    out="$(mktemp -d -p "$TMP_DIR")"
+    cat > "$out/sendmail" <<EOF
+#! /bin/sh
+cat >> "$out/mails"
+[ ! -e "$out/fail" ]
+EOF
+    chmod +x "$out/sendmail"
+    touch "$out/fail"
+    run config from test@patchr.com
+    run config transport sendmail
+    run config sendcmd "$out/sendmail"
+    echo first > first && git add first && git commit -q -m 'First patch'
+    if run send s HEAD~1 HEAD list@patchr.com; then
+        exit 1
+    fi
+    run show -v s | grep -q '^Pending send : v1 to list@patchr.com'
+    git show refs/patchr/data:repo.json | jq -e 'all(.series[]; .pending_send == null)'
+    rm "$out/fail"
+    run send --resume s
+    [ -z "$(run show -v s | grep '^Pending send')" ]
+
+    run storage file
+    check_json "$(repo_file r)" '.series | length' 4
+}
+
+run_test_funcs test_register_delete test_list test_register_subdir test_register_sha256 \
+    test_tag_range test_tag_undo test_tag_shortcuts test_tag_fixes test_corrupt_data \
+    test_shared_tmp_dir test_data_lock test_git_storage
@@ -56,4 +56,270 @@ test_edit_short_name() {
     run show -v s | grep -q '^Short name : $'
 }
 
-run_test_funcs test_create_series test_cv_skel test_edit_short_name
\ No newline at end of file
+# Repo with a registered series and two commits on top of the initial one
+setup_send_repo() {
+    echo first > first && git add first && git commit -q -m 'First patch'
+    echo second > second && git add second && git commit -q -m 'Second patch'
+    run register r
+    run create s 'Test series'
+    run config from test@patchr.com
+}
+
+# The maildir transport keeps the mails that would have been sent
+test_send_maildir() {
+    r=$(create_test_repo)
+    cd "$r"
+    setup_send_repo
+    md="$(mktemp -d -p "$TMP_DIR")"
+    run config transport maildir
+    run config maildir "$md"
+    run send s $(git rev-parse HEAD~2) $(git rev-parse HEAD) list@patchr.com -c cc@patchr.com
+
+    [ "$(ls "$md/new" | wc -l)" -eq 3 ]
+    [ -z "$(ls "$md/tmp")" ]
+    grep -q '^Subject: \[PATCH .*v1 0/2\] Test series$' "$md"/new/*
+    grep -q '^Subject: \[PATCH .*v1 1/2\] First patch$' "$md"/new/*
+    grep -q '^Subject: \[PATCH .*v1 2/2\] Second patch$' "$md"/new/*
+    [ "$(grep -l '^To: list@patchr.com$' "$md"/new/* | wc -l)" -eq 3 ]
+    [ "$(grep -l '^Cc: cc@patchr.com$' "$md"/new/* | wc -l)" -eq 3 ]
+}
+
+# The commits of a range can be given as any git revision
+test_send_revisions() {
+    r=$(create_test_repo)
+    cd "$r"
+    setup_send_repo
+    md="$(mktemp -d -p "$TMP_DIR")"
+    run config transport maildir
+    run config maildir "$md"
+    branch=$(git branch --show-current)
+    out=$(run send s HEAD~2 "$branch" list@patchr.com)
+
+    echo "$out" | grep -q "^HEAD~2 is $(git rev-parse HEAD~2)$"
+    echo "$out" | grep -q "^$branch is $(git rev-parse HEAD)$"
+    [ "$(ls "$md/new" | wc -l)" -eq 3 ]
+    # The resolved range is recorded
+    out=$(run show -v s)
+    echo "$out" | grep -q "$(git rev-parse HEAD~2)\.\.$(git rev-parse HEAD)"
+}
+
+# A dry run edits the cover letter and keeps the mails without sending them
+test_send_dry_run() {
+    r=$(create_test_repo)
+    cd "$r"
+    setup_send_repo
+    md="$(mktemp -d -p "$TMP_DIR")"
+    run config transport maildir
+    run config maildir "$md"
+    setup_fake_editor 'Edited cover letter'
+    # the values after -- are not parsed as flags
+    out=$(run send --dry-run -c cc@patchr.com -- s HEAD~2 HEAD list@patchr.com)
+
+    [ -z "$(ls "$md")" ]
+    echo "$out" | grep -q '^    To: list@patchr.com$'
+    echo "$out" | grep -q '^    Cc: cc@patchr.com$'
+    dir=$(echo "$out" | sed -n 's/^Nothing was sent, the mails are kept in //p')
+    grep -q '^Edited cover letter$' "$dir/0000-cover-letter.patch"
+}
+
+# The sendmail transport runs the command once per mail and logs its output
+test_send_sendmail() {
+    r=$(create_test_repo)
+    cd "$r"
+    setup_send_repo
+    out="$(mktemp -d -p "$TMP_DIR")"
+    cat > "$out/sendmail" <<EOF
+#! /bin/sh
+echo "\$@" >> "$out/args"
+cat >> "$out/mails"
+echo 'queued' >&2
+EOF
+    chmod +x "$out/sendmail"
+    run config transport sendmail
+    run config sendcmd "$out/sendmail"
+    run send s $(git rev-parse HEAD~2) $(git rev-parse HEAD) list@patchr.com
+
+    [ "$(grep -c '^-i -f test@patchr.com list@patchr.com$' "$out/args")" -eq 3 ]
+    [ "$(grep -c '^Subject: ' "$out/mails")" -eq 3 ]
+    [ "$(run show -v s | grep -c 'delivered, exit code 0, stderr: queued$')" -eq 3 ]
+
+    # A failing command stops the send
+    echo 'exit 1' >> "$out/sendmail"
+    if run send s list@patchr.com; then
+        exit 1
+    fi
+    [ "$(wc -l < "$out/args")" -eq 4 ]
+}
+
+# A failed send keeps its mails, resuming sends only the remaining ones
+test_send_resume() {
+    r=$(create_test_repo)
+    cd "$r"
+    setup_send_repo
+    out="$(mktemp -d -p "$TMP_DIR")"
+    # Fails on the second mail
+    cat > "$out/sendmail" <<EOF
+#! /bin/sh
+cat >> "$out/mails"
+echo x >> "$out/count"
+[ "\$(wc -l < "$out/count")" -ne 2 ]
+EOF
+    chmod +x "$out/sendmail"
+    run config transport sendmail
+    run config sendcmd "$out/sendmail"
+    if run send s $(git rev-parse HEAD~2) $(git rev-parse HEAD) list@patchr.com; then
+        exit 1
+    fi
+    run show -v s | grep -q '^Pending send : v1 to list@patchr.com'
+    run show -v s | grep -q 'interrupted, 1/3 mails delivered$'
+    [ "$(grep -c '^Subject: ' "$out/mails")" -eq 2 ]
+
+    # The mails of a pending send are not in the tmp directory
+    run cleantmp
+    run send --resume s
+    [ "$(grep -c '^Subject: ' "$out/mails")" -eq 4 ]
+    [ "$(grep -c '^Subject: \[PATCH .*v1 0/2\]' "$out/mails")" -eq 1 ]
+    [ "$(grep -c '^Subject: \[PATCH .*v1 1/2\]' "$out/mails")" -eq 2 ]
+    # The resumed mails keep their threading headers
+    [ "$(grep -c '^In-Reply-To: ' "$out/mails")" -eq 3 ]
+    [ -z "$(run show -v s | grep '^Pending send')" ]
+    run history -j s | jq -e '.sends | length == 1'
+    history=$(run history s)
+    echo "$history" | grep -q ' test@patchr.com  *list@patchr.com '
+    echo "$history" | grep -q '^    \[PATCH .*v1 2/2\] Second patch$'
+
+    # Nothing left to resume
+    if run send --resume s; then
+        exit 1
+    fi
+}
+
+# The subject prefix of a send is recorded and reused when sending again
+test_send_subject_prefix() {
+    r=$(create_test_repo)
+    cd "$r"
+    setup_send_repo
+    md="$(mktemp -d -p "$TMP_DIR")"
+    run config transport maildir
+    run config maildir "$md"
+    run edit short s ''
+    run edit prefix s 'PATCH net-next'
+    run edit v1 s no
+    run send s $(git rev-parse HEAD~2) $(git rev-parse HEAD) list@patchr.com --rfc
+    [ "$(grep -l '^Subject: \[RFC PATCH net-next 1/2\] First patch$' "$md"/new/* | wc -l)" -eq 1 ]
+
+    rm "$md"/new/*
+    run send s list@patchr.com
+    [ "$(grep -l '^Subject: \[RFC PATCH net-next 0/2\]' "$md"/new/* | wc -l)" -eq 1 ]
+
+    rm "$md"/new/*
+    run send s list@patchr.com --resend
+    [ "$(grep -l '^Subject: \[PATCH net-next RESEND 0/2\]' "$md"/new/* | wc -l)" -eq 1 ]
+    run history -j s | jq -e '[.sends[].subject_prefix] == ["RFC PATCH net-next", "RFC PATCH net-next", "PATCH net-next RESEND"]'
+}
+
+# Review trailers of the replies are added to the sent commits
+test_trailers() {
+    r=$(create_test_repo)
+    cd "$r"
+    setup_send_repo
+    md="$(mktemp -d -p "$TMP_DIR")"
+    run config transport maildir
+    run config maildir "$md"
+    run send s $(git rev-parse HEAD~2) $(git rev-parse HEAD) list@patchr.com
+    cover_id=$(grep -h '^Message-Id: ' $(grep -l '^Subject: .*0/2\]' "$md"/new/*) | cut -d' ' -f2)
+
+    replies="$TMP_DIR/replies.mbox"
+    cat > "$replies" <<EOF
+From reviewer Mon Sep 17 00:00:00 2001
+Subject: Re: [PATCH v1 0/2] Test series
+In-Reply-To: $cover_id
+
+Acked-by: Reviewer <reviewer@patchr.com>
+
+From tester Mon Sep 17 00:00:00 2001
+Subject: Re: [PATCH v1 2/2] Second patch
+
+Tested-by: Tester <tester@patchr.com>
+EOF
+    head=$(git rev-parse HEAD)
+    run trailers s "$replies" --dry-run | grep -q 'Tested-by: Tester <tester@patchr.com>'
+    [ "$(git rev-parse HEAD)" = "$head" ]
+
+    run trailers s "$replies"
+    [ "$(git log -2 --format=%B | grep -c '^Acked-by: Reviewer <reviewer@patchr.com>$')" -eq 2 ]
+    git log -1 --format=%B | grep -q '^Tested-by: Tester <tester@patchr.com>$'
+    run trailers s "$replies" | grep -q '^No new trailer'
+}
+
+# A series kept on a branch is sent without giving its commits
+test_series_branch() {
+    r=$(create_test_repo)
+    cd "$r"
+    base=$(git branch --show-current)
+    fork=$(git rev-parse HEAD)
+    git checkout -q -b topic
+    echo first > first && git add first && git commit -q -m 'First patch'
+    echo second > second && git add second && git commit -q -m 'Second patch'
+    git checkout -q "$base"
+    echo upstream > upstream && git add upstream && git commit -q -m 'Upstream'
+
+    run register r
+    run config from test@patchr.com
+    run create s 'Test series' topic "$base"
+    run show -v s | grep -q '^Branch : topic$'
+    run list | grep -q "^- s (v1) topic: 2 ahead, 1 behind $base$"
+
+    md="$(mktemp -d -p "$TMP_DIR")"
+    run config transport maildir
+    run config maildir "$md"
+    run send s list@patchr.com
+    [ "$(ls "$md/new" | wc -l)" -eq 3 ]
+    run show -v s | grep -q "^v1 $fork\.\.$(git rev-parse topic)"
+
+    # The upstream of the branch is the default base
+    run edit base s ''
+    if run send s list@patchr.com; then
+        exit 1
+    fi
+    git branch -q --set-upstream-to="$base" topic
+    run list | grep -q "^- s (v1) topic: 2 ahead, 1 behind upstream$"
+}
+
+# The mails give the fork point of the branch and the commits before the range
+test_send_base_info() {
+    r=$(create_test_repo)
+    cd "$r"
+    base=$(git branch --show-current)
+    fork=$(git rev-parse HEAD)
+    git checkout -q -b topic
+    echo first > first && git add first && git commit -q -m 'First patch'
+    echo second > second && git add second && git commit -q -m 'Second patch'
+
+    run register r
+    run config from test@patchr.com
+    run addlist ml list@patchr.com -b
+    run create s 'Test series' topic "$base"
+    md="$(mktemp -d -p "$TMP_DIR")"
+    run config transport maildir
+    run config maildir "$md"
+    run send s topic~1 topic ml
+    grep -q "^base-commit: $fork$" "$md"/new/*
+    [ "$(cat "$md"/new/* | grep -c '^prerequisite-patch-id: ')" -eq 1 ]
+    run show -v s | grep -q "base-commit $fork"
+    # export has the same default, the list the series was sent to
+    run export s -o "$TMP_DIR/s.mbox"
+    grep -q "^base-commit: $fork$" "$TMP_DIR/s.mbox"
+
+    # The series setting overrides the list one
+    rm "$md"/new/*
+    run edit baseinfo s no
+    run send s ml
+    if grep -q '^base-commit: ' "$md"/new/*; then
+        exit 1
+    fi
+}
+
+run_test_funcs test_create_series test_cv_skel test_edit_short_name test_send_maildir test_send_revisions \
+    test_send_dry_run test_send_sendmail test_send_resume test_send_subject_prefix test_trailers \
+    test_series_branch test_send_base_info
\ No newline at end of file
@@ -6,8 +6,6 @@ BIN="$(realpath ../target/release/patchr)"
 
 export FAKE_EDITOR="$(pwd)/fake_editor.sh"
 
-source ./util.sh
-
 set -ex
 
 init_tmp_env() {
@@ -47,7 +45,10 @@ cargo build -r
 cargo test
 
 tmp_dir="$(init_tmp_env /tmp)"
-export HOME="$tmp_dir" # use a temporary .patchr directory
+# use a temporary data directory and git config
+export PATCHR_HOME="$tmp_dir/patchr"
+export GIT_CONFIG_GLOBAL="$tmp_dir/gitconfig"
+source ./util.sh
 setup_git
 
 # Check if the user wants to run all the tests or just a subset
@@ -1,11 +1,19 @@
-DATA_DIR="$(realpath $HOME/.patchr)"
+DATA_DIR="$PATCHR_HOME"
 ROOT_FILE="$DATA_DIR/root.json"
+CONFIG_FILE="$DATA_DIR/config.json"
 
 run() {
     "$BIN" "$@"
     return $?
 }
 
+# Same as run with a terminal as stdin, the input is typed in it
+run_tty() {
+    local input="$1"
+    shift
+    echo "$input" | script -qec "$(printf '%q ' "$BIN" "$@")" /dev/null
+}
+
 setup_fake_editor() {
     data="$1"
     run config editor "$FAKE_EDITOR \"$data\""
@@ -18,7 +26,7 @@ repo_file() {
 
 clear_data_dir() {
     # Make sure we are not doing bad things
-    if ! echo "$HOME" | grep -q '^/tmp' || [ -z "$DATA_DIR" ]
+    if ! echo "$DATA_DIR" | grep -q '^/tmp' || [ -z "$DATA_DIR" ]
     then
         exit 1
     fi